@@ -1,5 +1,17 @@
-use agprefs::Agpref;
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use agprefs::{Agpref, Document};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+/// Many develop settings blobs one after another, like a catalog export
+fn corpus() -> String {
+    const METADATA: &str = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/assets/metadata"
+    ));
+    let settings = METADATA.strip_prefix("s = ").unwrap();
+    (0..1000)
+        .map(|i| format!("s{} = {}", i, settings))
+        .collect()
+}
 
 fn criterion_benchmark(c: &mut Criterion) {
     const BASIC: &str = include_str!(concat!(
@@ -12,6 +24,22 @@ fn criterion_benchmark(c: &mut Criterion) {
             black_box(Agpref::parse(s).unwrap());
         })
     });
+    #[cfg(feature = "serde")]
+    c.bench_function("deserializing basic", |b| {
+        b.iter(|| {
+            let s = black_box(&BASIC);
+            black_box(agprefs::from_str::<agprefs::Value>(s).unwrap());
+        })
+    });
+
+    let corpus = corpus();
+    let mut group = c.benchmark_group("corpus");
+    group.throughput(Throughput::Bytes(corpus.len() as u64));
+    group.sample_size(20);
+    group.bench_function("parsing corpus", |b| {
+        b.iter(|| black_box(Document::parse(black_box(&corpus)).unwrap()))
+    });
+    group.finish();
 }
 
 criterion_group!(benches, criterion_benchmark);
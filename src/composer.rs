@@ -1,209 +1,320 @@
-use crate::types::{Agpref, Value};
+use crate::style::{ComposeOptions, Indent, Layout, Newline, TrailingComma};
+use crate::types::{Agpref, Document, Key, Value};
 use cookie_factory::{combinator::string, sequence::tuple, GenResult};
 use std::io::BufWriter;
 use std::io::Write;
 
 impl Agpref<'_> {
-    /// Write the struct to a buffer
+    /// Write the struct to a buffer in its [`style`](Agpref::style)
+    pub fn write<W: Write>(&self, w: W) -> Result<(), crate::errors::Errors> {
+        self.write_with(w, &self.style.unwrap_or_default())
+    }
+    /// Write the struct to a buffer with the given formatting options
+    pub fn write_with<W: Write>(
+        &self,
+        mut w: W,
+        options: &ComposeOptions,
+    ) -> Result<(), crate::errors::Errors> {
+        let mut bw = BufWriter::new(&mut w);
+        let cfw = cookie_factory::WriteContext::from(&mut bw);
+        gen_agpref(self, options, cfw)?;
+        Ok(())
+    }
+    /// Write the struct to a string in its [`style`](Agpref::style)
+    pub fn to_str(&self) -> Result<String, crate::errors::Errors> {
+        self.to_str_with(&self.style.unwrap_or_default())
+    }
+    /// Write the struct to a string with the given formatting options
+    pub fn to_str_with(&self, options: &ComposeOptions) -> Result<String, crate::errors::Errors> {
+        let mut buf = Vec::new();
+        let cfw = cookie_factory::WriteContext::from(&mut buf);
+        gen_agpref(self, options, cfw)?;
+        Ok(String::from_utf8(buf)?)
+    }
+}
+
+impl Document<'_> {
+    /// Write all the assignments to a buffer, each in its own [`style`](Agpref::style)
     pub fn write<W: Write>(&self, mut w: W) -> Result<(), crate::errors::Errors> {
         let mut bw = BufWriter::new(&mut w);
         let cfw = cookie_factory::WriteContext::from(&mut bw);
-        gen_agpref(self, cfw)?;
+        gen_document(self, None, cfw)?;
         Ok(())
     }
-    /// Write the struct to a string
+    /// Write all the assignments to a buffer with the given formatting options
+    pub fn write_with<W: Write>(
+        &self,
+        mut w: W,
+        options: &ComposeOptions,
+    ) -> Result<(), crate::errors::Errors> {
+        let mut bw = BufWriter::new(&mut w);
+        let cfw = cookie_factory::WriteContext::from(&mut bw);
+        gen_document(self, Some(options), cfw)?;
+        Ok(())
+    }
+    /// Write all the assignments to a string, each in its own [`style`](Agpref::style)
+    pub fn to_str(&self) -> Result<String, crate::errors::Errors> {
+        let mut buf = Vec::new();
+        let cfw = cookie_factory::WriteContext::from(&mut buf);
+        gen_document(self, None, cfw)?;
+        Ok(String::from_utf8(buf)?)
+    }
+    /// Write all the assignments to a string with the given formatting options
+    pub fn to_str_with(&self, options: &ComposeOptions) -> Result<String, crate::errors::Errors> {
+        let mut buf = Vec::new();
+        let cfw = cookie_factory::WriteContext::from(&mut buf);
+        gen_document(self, Some(options), cfw)?;
+        Ok(String::from_utf8(buf)?)
+    }
+}
+
+impl Value<'_> {
+    /// Write the value on its own to a string
     pub fn to_str(&self) -> Result<String, crate::errors::Errors> {
+        self.to_str_with(&ComposeOptions::default())
+    }
+    /// Write the value on its own to a string with the given formatting options
+    pub fn to_str_with(&self, options: &ComposeOptions) -> Result<String, crate::errors::Errors> {
         let mut buf = Vec::new();
         let cfw = cookie_factory::WriteContext::from(&mut buf);
-        gen_agpref(self, cfw)?;
+        compose_value(self, options, 0, cfw)?;
         Ok(String::from_utf8(buf)?)
     }
 }
 
+/// Writes all the assignments with `options` or their own style if it is `None`
+fn gen_document<W: Write>(
+    document: &Document,
+    options: Option<&ComposeOptions>,
+    writer: cookie_factory::WriteContext<W>,
+) -> cookie_factory::GenResult<W> {
+    let mut result = writer;
+    for agpref in &document.entries {
+        let options = options.copied().unwrap_or(agpref.style.unwrap_or_default());
+        result = gen_agpref(agpref, &options, result)?;
+        if !options.final_newline {
+            // Assignments still need to be separated
+            result = string(options.newline.as_str())(result)?;
+        }
+    }
+    Ok(result)
+}
+
 fn gen_agpref<W: Write>(
     agpref: &Agpref,
+    options: &ComposeOptions,
     writer: cookie_factory::WriteContext<W>,
 ) -> cookie_factory::GenResult<W> {
     let mut result = writer;
     result = string(&agpref.name)(result)?;
     result = string(" = ")(result)?;
-
-    // let mut len = match agpref.values {
-    //     Value::Struct(ref s) => s.len(),
-    //     Value::Values(ref v) => v.len(),
-    //     _ => panic!("Impossible"),
-    // };
-    // for (name, value) in agpref.values.iter() {
-    //     result = string(name)(result)?;
-    //     result = string(" = ")(result)?;
-    //     result = compose_value(value, result)?;
-    //     if len > 1 {
-    //         result = string(",\n")(result)?;
-    //         len -= 1;
-    //     }
-    // }
-    result = compose_value(
-        &agpref.values,
-        Info {
-            newline: true,
-            ..Default::default()
-        },
-        result,
-    )?;
-
-    result = string("\n")(result)?;
+    result = compose_value(&agpref.values, options, 0, result)?;
+    if options.final_newline {
+        result = string(options.newline.as_str())(result)?;
+    }
     Ok(result)
 }
 
-#[derive(Debug, Clone, Copy, Default)]
-pub struct Info {
-    newline: bool,
-    inherit: bool,
+fn compose_indent<W: Write>(
+    options: &ComposeOptions,
     depth: usize,
+    writer: cookie_factory::WriteContext<W>,
+) -> GenResult<W> {
+    let mut result = writer;
+    for _ in 0..depth {
+        result = match options.indent {
+            Indent::None => result,
+            Indent::Tabs => string("\t")(result)?,
+            Indent::Spaces(n) => string(" ".repeat(n))(result)?,
+        };
+    }
+    Ok(result)
 }
 
-// #[derive(Debug, Clone, Copy, Default)]
-// pub struct ComposeInfo {
-//     pub newline: bool,
-//     pub inherit: Inherit,
-//     pub indent: Indent,
-// }
-
-// impl ComposeInfo {
-//     pub fn step_in(self) -> Self {
-//         Self {
-//             indent: Indent {
-//                 indent: self.indent.indent,
-//                 depth: self.indent.depth + 1,
-//             },
-//             inherit: Inherit {
-//                 inherit: self.inherit.inherit,
-//                 max_depth: self.inherit.max_depth - 1,
-//             },
-//             ..self
-//         }
-//     }
-// }
-
-// #[derive(Debug, Clone, Copy, Default)]
-// pub struct Inherit {
-//     pub inherit: bool,
-//     pub max_depth: usize,
-// }
-
-// #[derive(Debug, Clone, Copy, Default)]
-// pub struct Indent {
-//     pub indent: bool,
-//     pub depth: usize,
-// }
-
+/// Writes a value nested `depth` tables deep
 pub fn compose_value<W: Write>(
     value: &Value,
-    info: Info,
+    options: &ComposeOptions,
+    depth: usize,
     writer: cookie_factory::WriteContext<W>,
 ) -> GenResult<W> {
     let result = match value {
-        Value::String(s) => tuple((string("\""), string(escape_string(s)), string("\"")))(writer)?,
+        Value::String(s) => tuple((
+            string("\""),
+            string(escape_string_with(s, options.newline)),
+            string("\""),
+        ))(writer)?,
+        Value::Zstr(s) => tuple((
+            string("ZSTR \""),
+            string(escape_string_with(s, options.newline)),
+            string("\""),
+        ))(writer)?,
         Value::Int(i) => string(i.to_string())(writer)?,
-        Value::Float(f) => string(f.to_string())(writer)?,
+        Value::Float(f) => string(format_float(*f))(writer)?,
+        Value::Number(n) => string(n.as_str())(writer)?,
         Value::Bool(b) => string(b.to_string())(writer)?,
-        Value::Values(values) => {
-            let mut result = writer;
-            if info.newline {
-                result = string("{\n")(result)?;
-            } else {
-                result = string("{ ")(result)?;
-            }
+        Value::Values(values) => compose_table(
+            values.iter().map(|v| (None, v)).collect(),
+            options,
+            depth,
+            writer,
+        )?,
+        Value::Struct(s) => compose_table(
+            s.iter().map(|(k, v)| (Some(k), v)).collect(),
+            options,
+            depth,
+            writer,
+        )?,
+        Value::Unit => compose_table(Vec::new(), options, depth, writer)?,
+        Value::Embedded(agpref) => {
+            // Lightroom writes nested agprefs in the preferences style, the escaping takes care
+            // of the line endings
+            let style = agpref
+                .style
+                .unwrap_or_else(ComposeOptions::preferences)
+                .newline(Newline::Lf);
+            compose_nested(|w| gen_agpref(agpref, &style, w), options, writer)?
+        }
+        #[cfg(feature = "namedlist")]
+        Value::NamedList(namedlist) => compose_namedlist(namedlist, options, writer)?,
+    };
+    Ok(result)
+}
 
-            let mut len = values.len();
-            for value in values {
-                result = compose_value(
-                    value,
-                    Info {
-                        inherit: if info.depth > 0 { info.inherit } else { false },
-                        depth: if info.depth > 1 { info.depth - 1 } else { 0 },
-                        newline: if info.depth > 0 { info.newline } else { false },
-                    },
-                    result,
-                )?;
-                if len > 1 {
-                    result = string(",\n")(result)?;
-                    len -= 1;
-                }
-            }
-            result = string(" }")(result)?;
-            result
+fn compose_table<W: Write>(
+    entries: Vec<(Option<&Key>, &Value)>,
+    options: &ComposeOptions,
+    depth: usize,
+    writer: cookie_factory::WriteContext<W>,
+) -> GenResult<W> {
+    let is_table = |v: &Value| matches!(v, Value::Values(_) | Value::Struct(_) | Value::Unit);
+    let layout = entries
+        .iter()
+        .map(|(k, v)| (*k, is_table(v)))
+        .collect::<Vec<_>>();
+    compose_entries(
+        &layout,
+        |i, w| compose_value(entries[i].1, options, depth + 1, w),
+        options,
+        depth,
+        writer,
+    )
+}
+
+/// Writes a table with the given keys, whether the entries are tables themselves and a function
+/// writing the value of the `i`th entry
+pub(crate) fn compose_entries<W: Write>(
+    entries: &[(Option<&Key>, bool)],
+    mut compose_value: impl FnMut(usize, cookie_factory::WriteContext<W>) -> GenResult<W>,
+    options: &ComposeOptions,
+    depth: usize,
+    writer: cookie_factory::WriteContext<W>,
+) -> GenResult<W> {
+    let layout = if !entries.is_empty()
+        && entries.len() <= options.max_inline_entries
+        && !entries.iter().any(|(_, is_table)| *is_table)
+    {
+        Layout::Inline
+    } else {
+        options.layout
+    };
+    let trailing_comma = match options.trailing_comma {
+        TrailingComma::Never => false,
+        TrailingComma::Always => !entries.is_empty(),
+        TrailingComma::Multiline => layout == Layout::Multiline && !entries.is_empty(),
+    };
+    let newline = options.newline.as_str();
+
+    let mut result = writer;
+    result = match layout {
+        Layout::Multiline => tuple((string("{"), string(newline)))(result)?,
+        Layout::Compact | Layout::Inline => string("{ ")(result)?,
+    };
+    let len = entries.len();
+    for (i, (key, _)) in entries.iter().enumerate() {
+        if layout == Layout::Multiline || (layout == Layout::Compact && i > 0) {
+            result = compose_indent(options, depth + 1, result)?;
         }
-        Value::Struct(s) => {
-            let mut result = writer;
-            if info.newline {
-                result = string("{\n")(result)?;
-            } else {
-                result = string("{ ")(result)?;
-            }
-            let mut len = s.len();
-            for (name, value) in s {
-                result = string(name)(result)?;
-                result = string(" = ")(result)?;
-                result = compose_value(
-                    value,
-                    Info {
-                        inherit: if info.depth > 0 { info.inherit } else { false },
-                        depth: if info.depth > 1 { info.depth - 1 } else { 0 },
-                        newline: if info.depth > 0 { info.newline } else { false },
-                    },
-                    result,
-                )?;
-                if len > 1 {
-                    result = string(",\n")(result)?;
-                    len -= 1;
-                }
-            }
-            if info.newline {
-                result = string("\n}")(result)?;
-            } else {
-                result = string(" }")(result)?;
-            }
-            result
+        if let Some(key) = key {
+            result = compose_key(key, result)?;
+            result = string(" = ")(result)?;
         }
-        Value::Unit => string("{ }")(writer)?,
+        result = compose_value(i, result)?;
+        if i + 1 < len || trailing_comma {
+            result = string(",")(result)?;
+        }
+        result = match layout {
+            Layout::Multiline => string(newline)(result)?,
+            Layout::Compact if i + 1 < len => string(newline)(result)?,
+            Layout::Inline if i + 1 < len => string(" ")(result)?,
+            _ => result,
+        };
+    }
+    result = match layout {
+        Layout::Multiline => tuple((|w| compose_indent(options, depth, w), string("}")))(result)?,
+        Layout::Compact | Layout::Inline => string(" }")(result)?,
     };
     Ok(result)
 }
 
+/// Formats a float so it is read back as one
+pub(crate) fn format_float(f: f64) -> String {
+    // Debug keeps the decimal point of integral floats so they aren't read back as ints
+    format!("{:?}", f)
+}
+
+/// Writes the key of a struct entry, bracketing it if it isn't a valid identifier
+pub fn compose_key<W: Write>(key: &Key, writer: cookie_factory::WriteContext<W>) -> GenResult<W> {
+    if key.needs_brackets() {
+        tuple((string("[\""), string(escape_string(key)), string("\"]")))(writer)
+    } else {
+        string(key)(writer)
+    }
+}
+
+/// Writes a named list as the string Lightroom nests it in
 #[cfg(feature = "namedlist")]
 pub fn compose_namedlist<W: Write>(
     namedlist: &crate::types::NamedList,
+    options: &ComposeOptions,
     writer: cookie_factory::WriteContext<W>,
 ) -> GenResult<W> {
-    let mut result = writer;
-    result = string("\"")(result)?;
-    result = string(&namedlist.name)(result)?;
-    result = string(" = {\\\n")(result)?;
-    let mut len = namedlist.values.len();
-    for value in &namedlist.values {
-        result = if let Value::String(s) = value {
-            tuple((
-                string("\\\""),
-                string(escape_string(&escape_string(s))),
-                string("\\\""),
-            ))(result)?
-        } else {
-            compose_value(&value, result)?
-        };
-        if len > 1 {
-            result = string(",\\\n")(result)?;
-            len -= 1;
-        }
-    }
-    result = string(" }\\\n")(result)?;
-    result = string("\"")(result)?;
-    Ok(result)
+    let style = ComposeOptions::preferences();
+    let values = namedlist.values.iter().map(|v| (None, v)).collect();
+    compose_nested(
+        |w| {
+            let w = tuple((string(&namedlist.name), string(" = ")))(w)?;
+            let w = compose_table(values, &style, 0, w)?;
+            string(style.newline.as_str())(w)
+        },
+        options,
+        writer,
+    )
+}
+
+/// Writes the output of `inner` as a string, escaping it a second time
+fn compose_nested<W: Write>(
+    inner: impl FnOnce(cookie_factory::WriteContext<&mut Vec<u8>>) -> GenResult<&mut Vec<u8>>,
+    options: &ComposeOptions,
+    writer: cookie_factory::WriteContext<W>,
+) -> GenResult<W> {
+    let mut buf = Vec::new();
+    inner(cookie_factory::WriteContext::from(&mut buf))?;
+    let text = escape_string_with(&String::from_utf8_lossy(&buf), options.newline).into_owned();
+    tuple((string("\""), string(text), string("\"")))(writer)
 }
 
 // #[cfg(feature = "namedlist")]
 pub fn escape_string<'str>(
     input: &'str (impl AsRef<str> + 'str + ?Sized),
+) -> std::borrow::Cow<'str, str> {
+    escape_string_with(input, Newline::Lf)
+}
+
+/// Escapes a string, writing escaped newlines with the given line ending
+pub fn escape_string_with<'str>(
+    input: &'str (impl AsRef<str> + 'str + ?Sized),
+    newline: Newline,
 ) -> std::borrow::Cow<'str, str> {
     if memchr::memchr3(b'\\', b'"', b'\n', input.as_ref().as_bytes()).is_some()
         || memchr::memchr2(b'\r', b'\t', input.as_ref().as_bytes()).is_some()
@@ -213,7 +324,10 @@ pub fn escape_string<'str>(
             match c {
                 '\\' => result.push_str("\\\\"),
                 '"' => result.push_str("\\\""),
-                '\n' => result.push_str("\\\n"),
+                '\n' => {
+                    result.push('\\');
+                    result.push_str(newline.as_str());
+                }
                 '\r' => result.push_str("\\r"),
                 // '\t' => result.push_str("\\t"),
                 _ => result.push(c),
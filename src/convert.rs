@@ -0,0 +1,213 @@
+//! `TryFrom` conversions from values to Rust types
+//!
+//! Numbers convert leniently like [`Value::as_f64`], [`Value::as_i64`] and [`Value::as_bool`],
+//! integers are range checked. Lists convert to `Vec`s and structs to maps, with the path of an
+//! element which failed to convert in the [`ConversionError`]. `nil` converts to `None`. Localized
+//! strings convert to strings of their text like [`Value::as_text`].
+//!
+//! ```
+//! use agprefs::{Agpref, Value};
+//! use std::collections::HashMap;
+//! let x = Agpref::parse("s = { Amount = 0, Curve = { 0, 0, 255, 256 }, Flag = 1 }").unwrap();
+//! assert_eq!(f64::try_from(&x["Amount"]), Ok(0.0));
+//! assert_eq!(bool::try_from(&x["Flag"]), Ok(true));
+//!
+//! let error = HashMap::<String, Vec<u8>>::try_from(&x.values).unwrap_err();
+//! assert_eq!(error.path, "Amount");
+//! let error = Vec::<u8>::try_from(&x["Curve"]).unwrap_err();
+//! assert_eq!(error.to_string(), "expected u8 at `3`, found integer 256");
+//! ```
+use crate::errors::ConversionError;
+use crate::types::{HashMap, Value};
+use std::borrow::Cow;
+
+macro_rules! scalar {
+    ($ty:ty, $expected:expr, |$value:ident| $convert:expr) => {
+        impl<'v> TryFrom<&Value<'v>> for $ty {
+            type Error = ConversionError;
+            fn try_from($value: &Value<'v>) -> Result<Self, Self::Error> {
+                $convert.ok_or_else(|| ConversionError::new($expected, $value))
+            }
+        }
+
+        impl<'v> TryFrom<Value<'v>> for $ty {
+            type Error = ConversionError;
+            fn try_from(value: Value<'v>) -> Result<Self, Self::Error> {
+                Self::try_from(&value)
+            }
+        }
+    };
+}
+
+macro_rules! integer {
+    ($($ty:ident),*) => {
+        $(scalar!($ty, stringify!($ty), |value| value.as_i64().and_then(|i| i.try_into().ok()));)*
+    };
+}
+
+integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+scalar!(f64, "f64", |value| value.as_f64());
+scalar!(f32, "f32", |value| value.as_f64().map(|f| f as f32));
+scalar!(bool, "bool", |value| value.as_bool());
+scalar!(String, "string", |value| value.as_text().map(String::from));
+
+impl<'a, 'v> TryFrom<&'a Value<'v>> for &'a str {
+    type Error = ConversionError;
+    fn try_from(value: &'a Value<'v>) -> Result<Self, Self::Error> {
+        value
+            .as_text()
+            .ok_or_else(|| ConversionError::new("string", value))
+    }
+}
+
+impl<'v> TryFrom<Value<'v>> for Cow<'v, str> {
+    type Error = ConversionError;
+    fn try_from(value: Value<'v>) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(s) => Ok(s),
+            Value::Zstr(Cow::Borrowed(s)) => Ok(Cow::Borrowed(text(s))),
+            Value::Zstr(Cow::Owned(s)) => Ok(Cow::Owned(text(&s).to_string())),
+            value => Err(ConversionError::new("string", &value)),
+        }
+    }
+}
+
+/// The text of a localized string without its translation key
+fn text(zstr: &str) -> &str {
+    zstr.split_once('=').map_or(zstr, |(_, text)| text)
+}
+
+/// The elements of a list, looking through embedded agprefs
+fn elements<'a, 'v>(value: &'a Value<'v>) -> Option<&'a [Value<'v>]> {
+    match value {
+        Value::Values(v) => Some(v),
+        Value::Embedded(e) => elements(&e.values),
+        #[cfg(feature = "namedlist")]
+        Value::NamedList(n) => Some(&n.values),
+        _ => None,
+    }
+}
+
+fn into_elements(value: Value) -> Result<Vec<Value>, ConversionError> {
+    match value {
+        Value::Values(v) => Ok(v),
+        Value::Embedded(e) => into_elements(e.values),
+        #[cfg(feature = "namedlist")]
+        Value::NamedList(n) => Ok(n.values),
+        value => Err(ConversionError::new("list", &value)),
+    }
+}
+
+/// The entries of a struct, looking through embedded agprefs. Empty tables are structs without
+/// entries.
+fn entries<'a, 'v>(value: &'a Value<'v>) -> Option<Vec<(&'a str, &'a Value<'v>)>> {
+    match value {
+        Value::Struct(s) => Some(s.iter().map(|(k, v)| (k.as_str(), v)).collect()),
+        Value::Values(v) if v.is_empty() => Some(Vec::new()),
+        Value::Embedded(e) => entries(&e.values),
+        _ => None,
+    }
+}
+
+fn into_entries(value: Value) -> Result<Vec<(String, Value)>, ConversionError> {
+    match value {
+        Value::Struct(s) => Ok(s.into_iter().map(|(k, v)| (k.to_string(), v)).collect()),
+        Value::Values(v) if v.is_empty() => Ok(Vec::new()),
+        Value::Embedded(e) => into_entries(e.values),
+        value => Err(ConversionError::new("struct", &value)),
+    }
+}
+
+impl<'a, 'v, T> TryFrom<&'a Value<'v>> for Vec<T>
+where
+    T: TryFrom<&'a Value<'v>, Error = ConversionError>,
+{
+    type Error = ConversionError;
+    fn try_from(value: &'a Value<'v>) -> Result<Self, Self::Error> {
+        let elements = elements(value).ok_or_else(|| ConversionError::new("list", value))?;
+        elements
+            .iter()
+            .enumerate()
+            .map(|(i, v)| T::try_from(v).map_err(|e| e.within(&i.to_string())))
+            .collect()
+    }
+}
+
+impl<'v, T> TryFrom<Value<'v>> for Vec<T>
+where
+    T: TryFrom<Value<'v>, Error = ConversionError>,
+{
+    type Error = ConversionError;
+    fn try_from(value: Value<'v>) -> Result<Self, Self::Error> {
+        into_elements(value)?
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| T::try_from(v).map_err(|e| e.within(&i.to_string())))
+            .collect()
+    }
+}
+
+macro_rules! map {
+    ($map:ident) => {
+        impl<'a, 'v, T> TryFrom<&'a Value<'v>> for $map<String, T>
+        where
+            T: TryFrom<&'a Value<'v>, Error = ConversionError>,
+        {
+            type Error = ConversionError;
+            fn try_from(value: &'a Value<'v>) -> Result<Self, Self::Error> {
+                let entries =
+                    entries(value).ok_or_else(|| ConversionError::new("struct", value))?;
+                entries
+                    .into_iter()
+                    .map(|(k, v)| Ok((k.to_string(), T::try_from(v).map_err(|e| e.within(k))?)))
+                    .collect()
+            }
+        }
+
+        impl<'v, T> TryFrom<Value<'v>> for $map<String, T>
+        where
+            T: TryFrom<Value<'v>, Error = ConversionError>,
+        {
+            type Error = ConversionError;
+            fn try_from(value: Value<'v>) -> Result<Self, Self::Error> {
+                into_entries(value)?
+                    .into_iter()
+                    .map(|(k, v)| {
+                        let v = T::try_from(v).map_err(|e| e.within(&k))?;
+                        Ok((k, v))
+                    })
+                    .collect()
+            }
+        }
+    };
+}
+
+use std::collections::HashMap as StdHashMap;
+map!(StdHashMap);
+map!(HashMap);
+
+impl<'a, 'v, T> TryFrom<&'a Value<'v>> for Option<T>
+where
+    T: TryFrom<&'a Value<'v>, Error = ConversionError>,
+{
+    type Error = ConversionError;
+    fn try_from(value: &'a Value<'v>) -> Result<Self, Self::Error> {
+        match value {
+            Value::Unit => Ok(None),
+            value => T::try_from(value).map(Some),
+        }
+    }
+}
+
+impl<'v, T> TryFrom<Value<'v>> for Option<T>
+where
+    T: TryFrom<Value<'v>, Error = ConversionError>,
+{
+    type Error = ConversionError;
+    fn try_from(value: Value<'v>) -> Result<Self, Self::Error> {
+        match value {
+            Value::Unit => Ok(None),
+            value => T::try_from(value).map(Some),
+        }
+    }
+}
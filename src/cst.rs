@@ -0,0 +1,364 @@
+//! A lossless view of an agprefs file for editing it in place
+//!
+//! [`Agpref`] only keeps the values of a file, so writing it back normalizes the whole layout.
+//! [`Cst`] keeps the original text along with the position of every value in it and every edit
+//! only replaces the bytes of the value it touches. Whitespace, indentation, trailing commas,
+//! number spellings and string escapes of everything else stay exactly as they were.
+//!
+//! ```
+//! use agprefs::{cst::Cst, Value};
+//! let mut cst = Cst::parse("s = {\n\tAmount = 1.0,\n\tName = \"Adobe\",\n}\n").unwrap();
+//! cst.set(&["Name"], &Value::from("Adobe Color")).unwrap();
+//! assert_eq!(cst.as_str(), "s = {\n\tAmount = 1.0,\n\tName = \"Adobe Color\",\n}\n");
+//! ```
+use crate::errors::Errors;
+use crate::style::{ComposeOptions, Indent, Layout, Newline, TrailingComma};
+use crate::types::{Agpref, Key, Value};
+use std::ops::Range;
+
+#[derive(Debug, Clone, PartialEq)]
+/// The original text of an agprefs file along with the positions of all of its values
+pub struct Cst {
+    text: String,
+    name: Range<usize>,
+    root: Node,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Node {
+    span: Range<usize>,
+    /// The entries of the node if it is a table
+    entries: Option<Vec<Entry>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    /// The key of the entry, `None` for list elements
+    key: Option<Key<'static>>,
+    /// From the start of the key (or value for list elements) to the end of the value
+    span: Range<usize>,
+    value: Node,
+    /// Position of the comma following the value
+    comma: Option<usize>,
+}
+
+impl Entry {
+    /// The end of the entry including its comma
+    fn end(&self) -> usize {
+        self.comma.map(|c| c + 1).unwrap_or(self.span.end)
+    }
+}
+
+impl Cst {
+    /// Parse the given string, keeping all of its formatting
+    pub fn parse(s: &str) -> Result<Self, Errors> {
+        Self::from_string(s.to_string())
+    }
+
+    fn from_string(text: String) -> Result<Self, Errors> {
+        // Validate first so malformed input gets a proper error
+        Agpref::parse(&text)?;
+        let (name, root) = scan_assignment(&text).ok_or_else(invalid)?;
+        Ok(Self { text, name, root })
+    }
+
+    /// The text with all the edits applied
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn into_string(self) -> String {
+        self.text
+    }
+
+    /// The name of the top level assignment
+    pub fn name(&self) -> &str {
+        &self.text[self.name.clone()]
+    }
+
+    /// Parse the current text into an [`Agpref`]
+    pub fn to_agpref(&self) -> Result<Agpref<'_>, Errors> {
+        Agpref::parse(&self.text)
+    }
+
+    /// The value at the given path of keys and list indices
+    pub fn get(&self, path: &[&str]) -> Option<Value<'_>> {
+        let source = self.source(path)?;
+        crate::parser::get_value(source).ok().map(|(_, v)| v)
+    }
+
+    /// The original text of the value at the given path
+    pub fn source(&self, path: &[&str]) -> Option<&str> {
+        let node = self.node(path)?;
+        Some(&self.text[node.span.clone()])
+    }
+
+    fn node(&self, path: &[&str]) -> Option<&Node> {
+        path.iter().try_fold(&self.root, |node, segment| {
+            Some(&find(node, segment)?.1.value)
+        })
+    }
+
+    /// Replace the value at the given path, only touching the bytes of that value.
+    ///
+    /// If the last key of the path doesn't exist in its struct (or is the length of its list)
+    /// a new entry is added after the last one, following the layout of the existing entries.
+    pub fn set(&mut self, path: &[&str], value: &Value) -> Result<(), Errors> {
+        let (last, parent) = path.split_last().ok_or_else(|| not_found(path))?;
+        let parent = self.node(parent).ok_or_else(|| not_found(path))?;
+        let entries = parent.entries.as_deref().ok_or_else(|| not_found(path))?;
+
+        if let Some((_, entry)) = find(parent, last) {
+            if self.get(path).as_ref() == Some(value) {
+                return Ok(());
+            }
+            let range = entry.value.span.clone();
+            return self.splice(range, &value.to_str()?);
+        }
+
+        let key = if entries.iter().any(|e| e.key.is_none()) {
+            if last.parse::<usize>().ok() != Some(entries.len()) {
+                return Err(not_found(path));
+            }
+            None
+        } else {
+            Some(Key::from(*last))
+        };
+        let mut entry = match key {
+            Some(key) => format!("{} = {}", key_to_str(&key)?, value.to_str()?),
+            None => value.to_str()?,
+        };
+
+        let (range, replacement) = match entries {
+            [] => {
+                // `{ }` has no layout to follow
+                entry = format!("{{ {} }}", entry);
+                (parent.span.clone(), entry)
+            }
+            [.., last_entry] => {
+                // Separate the new entry like the last two (or the first entry from the brace)
+                let separator = match entries.len() {
+                    1 => &self.text[parent.span.start + 1..last_entry.span.start],
+                    n => &self.text[entries[n - 2].end()..last_entry.span.start],
+                };
+                match last_entry.comma {
+                    Some(comma) => (comma + 1..comma + 1, format!("{}{},", separator, entry)),
+                    None => (
+                        last_entry.span.end..last_entry.span.end,
+                        format!(",{}{}", separator, entry),
+                    ),
+                }
+            }
+        };
+        self.splice(range, &replacement)
+    }
+
+    /// Remove the entry at the given path along with its separator
+    pub fn remove(&mut self, path: &[&str]) -> Result<(), Errors> {
+        let (last, parent) = path.split_last().ok_or_else(|| not_found(path))?;
+        let parent = self.node(parent).ok_or_else(|| not_found(path))?;
+        let (index, entry) = find(parent, last).ok_or_else(|| not_found(path))?;
+        let entries = parent.entries.as_deref().unwrap_or_default();
+
+        let range = if let Some(next) = entries.get(index + 1) {
+            entry.span.start..next.span.start
+        } else if let Some(prev) = index.checked_sub(1).map(|i| &entries[i]) {
+            // Keep the comma of the previous entry only if the last one had one as well
+            match entry.comma {
+                Some(_) => prev.end()..entry.end(),
+                None => prev.span.end..entry.end(),
+            }
+        } else {
+            entry.span.start..entry.end()
+        };
+        self.splice(range, "")
+    }
+
+    fn splice(&mut self, range: Range<usize>, replacement: &str) -> Result<(), Errors> {
+        let mut text = self.text.clone();
+        text.replace_range(range, replacement);
+        *self = Self::from_string(text)?;
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Cst {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl std::str::FromStr for Cst {
+    type Err = Errors;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+fn find<'n>(node: &'n Node, segment: &str) -> Option<(usize, &'n Entry)> {
+    let entries = node.entries.as_ref()?;
+    match entries.first()?.key {
+        Some(_) => entries
+            .iter()
+            .enumerate()
+            .find(|(_, e)| e.key.as_deref() == Some(segment)),
+        None => {
+            let index = segment.parse::<usize>().ok()?;
+            Some((index, entries.get(index)?))
+        }
+    }
+}
+
+fn key_to_str(key: &Key) -> Result<String, Errors> {
+    let mut buf = Vec::new();
+    crate::composer::compose_key(key, cookie_factory::WriteContext::from(&mut buf))?;
+    Ok(String::from_utf8(buf)?)
+}
+
+fn not_found(path: &[&str]) -> Errors {
+    Errors::NotFound(path.join("/"))
+}
+
+fn invalid() -> Errors {
+    Errors::Other("Failed to index the agprefs text".into())
+}
+
+fn skip_ws(text: &str, pos: usize) -> usize {
+    let rest = &text[pos..];
+    pos + rest.len() - rest.trim_start().len()
+}
+
+/// Finds the span of the name and the value of the (already validated) assignment in `text`
+fn scan_assignment(text: &str) -> Option<(Range<usize>, Node)> {
+    let start = skip_ws(text, 0);
+    let (rest, _) = crate::parser::get_key(&text[start..]).ok()?;
+    let name_end = text.len() - rest.len();
+    let name = start..start + text[start..name_end].trim_end().len();
+    let equals = skip_ws(text, name_end);
+    let root = scan_value(text, equals + 1)?;
+    Some((name, root))
+}
+
+/// Works out the [`ComposeOptions`] that reproduce the layout of the (already validated)
+/// assignment at the start of `text`
+pub(crate) fn detect_style(text: &str) -> ComposeOptions {
+    let mut style = ComposeOptions::default().newline(match text.contains("\r\n") {
+        true => Newline::CrLf,
+        false => Newline::Lf,
+    });
+    let Some((_, root)) = scan_assignment(text) else {
+        return style;
+    };
+    style = style.final_newline(matches!(
+        text[root.span.end..].chars().next(),
+        Some('\r' | '\n')
+    ));
+    let entries = match root.entries.as_deref() {
+        Some(entries) if !entries.is_empty() => entries,
+        // All an empty table shows is whether it spans lines
+        Some(_) if text[root.span.clone()].contains('\n') => {
+            return style.layout(Layout::Multiline)
+        }
+        _ => return style,
+    };
+
+    let after_brace = &text[root.span.start + 1..entries[0].span.start];
+    let (layout, indented) = if after_brace.contains('\n') {
+        (Layout::Multiline, Some(&entries[0]))
+    } else {
+        match entries.get(1) {
+            Some(second) if text[entries[0].end()..second.span.start].contains('\n') => {
+                (Layout::Compact, Some(second))
+            }
+            Some(_) => (Layout::Inline, None),
+            None => (Layout::Compact, None),
+        }
+    };
+    style = style.layout(layout);
+
+    if let Some(entry) = indented {
+        let line_start = text[..entry.span.start].rfind('\n').map_or(0, |i| i + 1);
+        let indent = &text[line_start..entry.span.start];
+        style = style.indent(if indent.is_empty() {
+            Indent::None
+        } else if indent.starts_with('\t') {
+            Indent::Tabs
+        } else {
+            Indent::Spaces(indent.len())
+        });
+    }
+
+    let trailing = entries.last().and_then(|e| e.comma).is_some();
+    style = style.trailing_comma(match (trailing, layout) {
+        (false, _) => TrailingComma::Never,
+        (true, Layout::Multiline) => TrailingComma::Multiline,
+        (true, _) => TrailingComma::Always,
+    });
+
+    style.max_inline_entries(max_inline_entries(text, entries))
+}
+
+/// The largest nested table written on a single line
+fn max_inline_entries(text: &str, entries: &[Entry]) -> usize {
+    entries
+        .iter()
+        .filter_map(|e| {
+            let nested = e.value.entries.as_deref()?;
+            let single_line = !text[e.value.span.clone()].contains('\n');
+            let own = match nested.len() {
+                n if n >= 2 && single_line => n,
+                _ => 0,
+            };
+            Some(own.max(max_inline_entries(text, nested)))
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Finds the spans of the (already validated) value starting at `pos`
+fn scan_value(text: &str, pos: usize) -> Option<Node> {
+    let pos = skip_ws(text, pos);
+    if text[pos..].starts_with('{') {
+        return scan_table(text, pos);
+    }
+    let (rest, _) = crate::parser::get_value(&text[pos..]).ok()?;
+    let consumed = &text[pos..text.len() - rest.len()];
+    Some(Node {
+        span: pos..pos + consumed.trim_end().len(),
+        entries: None,
+    })
+}
+
+fn scan_table(text: &str, open: usize) -> Option<Node> {
+    let mut pos = skip_ws(text, open + 1);
+    let is_struct = crate::parser::get_key(&text[pos..])
+        .map(|(rest, _)| rest.starts_with('='))
+        .unwrap_or(false);
+    let mut entries = Vec::new();
+    while !text[pos..].starts_with('}') {
+        let start = pos;
+        let key = if is_struct {
+            let (rest, key) = crate::parser::get_key(&text[pos..]).ok()?;
+            pos = skip_ws(text, text.len() - rest.len() + 1);
+            Some(key.into_static())
+        } else {
+            None
+        };
+        let value = scan_value(text, pos)?;
+        pos = skip_ws(text, value.span.end);
+        let comma = text[pos..].starts_with(',').then_some(pos);
+        if comma.is_some() {
+            pos = skip_ws(text, pos + 1);
+        }
+        entries.push(Entry {
+            key,
+            span: start..value.span.end,
+            value,
+            comma,
+        });
+    }
+    Some(Node {
+        span: open..pos + 1,
+        entries: Some(entries),
+    })
+}
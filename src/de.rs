@@ -0,0 +1,454 @@
+//! Deserialize Rust types straight from agprefs text
+//!
+//! Structs and maps are read from `{ Key = value }` tables, sequences and tuples from `{ 1, 2 }`
+//! lists. Enums are either a string holding the name of a unit variant or a table with a single
+//! `Variant = value` entry. Strings without escapes are borrowed from the input. Keys are
+//! strings, but are parsed for maps with integer or bool keys like the serializer writes them.
+//! Localized strings like `ZSTR "$$$/Presets/Vivid=Vivid"` are read as their text, `Vivid`, even
+//! into a [`Value`](crate::Value). Use [`Agpref::parse`](crate::Agpref::parse) to keep them.
+//!
+//! ```
+//! #[derive(serde::Deserialize)]
+//! struct Settings<'a> {
+//!     #[serde(rename = "Exposure2012")]
+//!     exposure: f64,
+//!     #[serde(rename = "CameraProfile")]
+//!     profile: &'a str,
+//!     #[serde(rename = "ToneCurvePV2012")]
+//!     curve: Vec<u8>,
+//! }
+//! let text = "s = { CameraProfile = \"Adobe Standard\",\nExposure2012 = 0.35,\nToneCurvePV2012 = { 0, 0, 255, 255 } }\n";
+//! let settings: Settings = agprefs::from_str(text).unwrap();
+//! assert_eq!(settings.exposure, 0.35);
+//! assert_eq!(settings.profile, "Adobe Standard");
+//! assert_eq!(settings.curve, [0, 0, 255, 255]);
+//! ```
+use crate::errors::{Errors, Expected, SyntaxError};
+use crate::parser;
+use crate::types::Number;
+use serde::de::{self, DeserializeSeed, IntoDeserializer, Visitor};
+use std::borrow::Cow;
+
+/// Deserializes `T` from the value of the assignment in `s`, the name of the assignment is
+/// ignored. Anything but whitespace after the assignment is an error.
+pub fn from_str<'de, T: de::Deserialize<'de>>(s: &'de str) -> Result<T, Errors> {
+    let mut deserializer = Deserializer::new(s);
+    deserializer.parse_name()?;
+    let value = T::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+/// A deserializer reading values directly from agprefs text
+pub struct Deserializer<'de> {
+    input: &'de str,
+    rest: &'de str,
+    /// Keys and list indices leading to the current value, for errors
+    path: Vec<String>,
+}
+
+impl<'de> Deserializer<'de> {
+    /// A deserializer for the value at the start of `input`
+    pub fn new(input: &'de str) -> Self {
+        Self {
+            input,
+            rest: input,
+            path: Vec::new(),
+        }
+    }
+
+    /// Parses the `name =` of an assignment, returning the name
+    pub fn parse_name(&mut self) -> Result<&'de str, Errors> {
+        self.skip_ws();
+        let name = self
+            .run(parser::get_identifier)
+            .map_err(|_| self.error(vec![Expected::Identifier]))?;
+        self.eat("=")?;
+        self.path.push(name.to_string());
+        Ok(name)
+    }
+
+    /// Checks that there is nothing but whitespace left
+    pub fn end(&mut self) -> Result<(), Errors> {
+        self.skip_ws();
+        match self.rest.is_empty() {
+            true => Ok(()),
+            false => Err(self.error(vec![Expected::EndOfInput])),
+        }
+    }
+
+    fn error(&self, expected: Vec<Expected>) -> Errors {
+        let offset = self.input.len() - self.rest.len();
+        SyntaxError::new(self.input, offset, expected, self.path.clone()).into()
+    }
+
+    fn skip_ws(&mut self) {
+        self.rest = self.rest.trim_start();
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_ws();
+        self.rest.chars().next()
+    }
+
+    fn eat(&mut self, token: &'static str) -> Result<(), Errors> {
+        self.skip_ws();
+        match self.rest.strip_prefix(token) {
+            Some(rest) => {
+                self.rest = rest;
+                Ok(())
+            }
+            None => Err(self.error(vec![Expected::Token(token)])),
+        }
+    }
+
+    /// Runs one of the parsers of the parser module on the rest of the input
+    fn run<O>(
+        &mut self,
+        parser: impl FnOnce(&'de str) -> parser::IResult<'de, O>,
+    ) -> Result<O, Errors> {
+        match parser::finish(self.input, parser(self.rest)) {
+            Ok((rest, output)) => {
+                self.rest = rest;
+                Ok(output)
+            }
+            Err(mut e) => {
+                e.path.splice(0..0, self.path.iter().cloned());
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Parses the `,` or `}` following an entry of a table, leaving the `}`
+    fn separator(&mut self) -> Result<(), Errors> {
+        match self.peek() {
+            Some(',') => {
+                self.rest = &self.rest[1..];
+                Ok(())
+            }
+            Some('}') => Ok(()),
+            _ => Err(self.error(vec![Expected::Token(","), Expected::Token("}")])),
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Errors;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Errors> {
+        match self.peek() {
+            Some('{') => {
+                let entries = self.rest[1..].trim_start();
+                if parser::starts_struct(entries) {
+                    self.deserialize_map(visitor)
+                } else {
+                    // Lightroom writes empty structs as empty lists as well
+                    self.deserialize_seq(visitor)
+                }
+            }
+            Some('"') => match self.run(parser::string_literal)? {
+                Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
+                Cow::Owned(s) => visitor.visit_string(s),
+            },
+            Some(_) => {
+                let start = self.rest;
+                let token = self.run(parser::take_eov)?;
+                if token == "ZSTR" && self.peek() == Some('"') {
+                    // Localized strings are read as their text, like `Value::as_text`
+                    let start = |s: &str| s.find('=').map_or(0, |i| i + 1);
+                    return match self.run(parser::string_literal)? {
+                        Cow::Borrowed(s) => visitor.visit_borrowed_str(&s[start(s)..]),
+                        Cow::Owned(s) => visitor.visit_str(&s[start(&s)..]),
+                    };
+                }
+                if let Ok(b) = token.parse::<bool>() {
+                    visitor.visit_bool(b)
+                } else if let Ok(i) = token.parse::<i64>() {
+                    visitor.visit_i64(i)
+                } else if let Ok(u) = token.parse::<u64>() {
+                    visitor.visit_u64(u)
+                } else if let Some(n) = Number::parse(token) {
+                    visitor.visit_f64(n.as_f64())
+                } else {
+                    self.rest = start;
+                    Err(self.error(vec![Expected::Value]))
+                }
+            }
+            None => Err(self.error(vec![Expected::Value])),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Errors> {
+        // There is no nil, missing struct fields are handled by serde
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Errors> {
+        self.eat("{")?;
+        self.eat("}")?;
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Errors> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Errors> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Errors> {
+        self.eat("{")?;
+        let value = visitor.visit_seq(Entries::new(self))?;
+        self.eat("}")?;
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Errors> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Errors> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Errors> {
+        self.eat("{")?;
+        let value = visitor.visit_map(Entries::new(self))?;
+        self.eat("}")?;
+        Ok(value)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Errors> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Errors> {
+        match self.peek() {
+            Some('"') => {
+                let variant: Cow<'de, str> = self.run(parser::string_literal)?;
+                visitor.visit_enum(variant.into_deserializer())
+            }
+            _ => {
+                self.eat("{")?;
+                let value = visitor.visit_enum(Entries::new(self))?;
+                self.eat("}")?;
+                Ok(value)
+            }
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf identifier ignored_any
+    }
+}
+
+/// The entries of a table, either list elements or struct entries
+struct Entries<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    index: usize,
+}
+
+impl<'a, 'de> Entries<'a, 'de> {
+    fn new(de: &'a mut Deserializer<'de>) -> Self {
+        Self { de, index: 0 }
+    }
+
+    /// Finishes the value of an entry
+    fn done(&mut self) -> Result<(), Errors> {
+        self.de.path.pop();
+        self.index += 1;
+        self.de.separator()
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for Entries<'_, 'de> {
+    type Error = Errors;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Errors> {
+        if self.de.peek() == Some('}') {
+            return Ok(None);
+        }
+        self.de.path.push(self.index.to_string());
+        let value = seed.deserialize(&mut *self.de)?;
+        self.done()?;
+        Ok(Some(value))
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Entries<'_, 'de> {
+    type Error = Errors;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Errors> {
+        if self.de.peek() == Some('}') {
+            return Ok(None);
+        }
+        let key = self.de.run(parser::get_key)?.into_inner();
+        self.de.eat("=")?;
+        self.de.path.push(key.to_string());
+        seed.deserialize(KeyDeserializer(key)).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Errors> {
+        let value = seed.deserialize(&mut *self.de)?;
+        self.done()?;
+        Ok(value)
+    }
+}
+
+impl<'a, 'de> de::EnumAccess<'de> for Entries<'a, 'de> {
+    type Error = Errors;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        mut self,
+        seed: V,
+    ) -> Result<(V::Value, Self), Errors> {
+        use de::MapAccess;
+        match self.next_key_seed(seed)? {
+            Some(variant) => Ok((variant, self)),
+            None => Err(self.de.error(vec![Expected::Key])),
+        }
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Entries<'_, 'de> {
+    type Error = Errors;
+
+    fn unit_variant(mut self) -> Result<(), Errors> {
+        <() as de::Deserialize>::deserialize(&mut *self.de)?;
+        self.done()
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        mut self,
+        seed: T,
+    ) -> Result<T::Value, Errors> {
+        use de::MapAccess;
+        self.next_value_seed(seed)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        mut self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Errors> {
+        let value = de::Deserializer::deserialize_seq(&mut *self.de, visitor)?;
+        self.done()?;
+        Ok(value)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        mut self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Errors> {
+        let value = de::Deserializer::deserialize_map(&mut *self.de, visitor)?;
+        self.done()?;
+        Ok(value)
+    }
+}
+
+/// Reads a key as a string, or parses it for keys which were written from other types
+struct KeyDeserializer<'de>(Cow<'de, str>);
+
+macro_rules! parse_key {
+    ($($name:ident => $visit:ident),*) => {
+        $(
+            fn $name<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Errors> {
+                match self.0.parse() {
+                    Ok(v) => visitor.$visit(v),
+                    Err(_) => Err(de::Error::invalid_type(
+                        de::Unexpected::Str(&self.0),
+                        &visitor,
+                    )),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for KeyDeserializer<'de> {
+    type Error = Errors;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Errors> {
+        match self.0 {
+            Cow::Borrowed(key) => visitor.visit_borrowed_str(key),
+            Cow::Owned(key) => visitor.visit_string(key),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Errors> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Errors> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Errors> {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+
+    parse_key!(
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128
+    );
+
+    serde::forward_to_deserialize_any! {
+        f32 f64 char str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
@@ -0,0 +1,502 @@
+//! Typed develop settings
+//!
+//! [`DevelopSettings`] models the `s = { ... }` struct Lightroom Classic stores the develop
+//! settings of a photo or preset in. The known keys are grouped by the panel they belong to and
+//! everything else, like `Look` or keys of newer Lightroom versions, is kept in
+//! [`DevelopSettings::extra`].
+//!
+//! A known key whose value doesn't have the expected type is kept in `extra` too. Numbers which
+//! are spelled differently than they would be written, like `Exposure2012 = 1.0` which is
+//! written as `1` when set, are remembered in [`DevelopSettings::originals`], so converting to
+//! [`Agpref`] and back never changes a value.
+//!
+//! ```
+//! use agprefs::{Agpref, DevelopSettings};
+//! let x = Agpref::parse("s = { Exposure2012 = 1.05, Look = { }, Vibrance = -31 }").unwrap();
+//! let mut settings = DevelopSettings::try_from(x.clone()).unwrap();
+//! assert_eq!(settings.basic.exposure2012, Some(1.05));
+//! assert_eq!(settings.basic.vibrance, Some(-31));
+//! assert!(settings.extra.contains_key("Look"));
+//! assert_eq!(Agpref::from(settings.clone()), x);
+//!
+//! settings.basic.exposure2012 = Some(0.5);
+//! assert_eq!(Agpref::from(settings)["Exposure2012"], agprefs::Value::Float(0.5));
+//! ```
+use crate::errors::Errors;
+use crate::types::{Agpref, HashMap, Key, Value};
+use std::borrow::Cow;
+
+/// A value of a develop setting
+trait Setting: Sized {
+    fn from_value(value: &Value) -> Option<Self>;
+    fn to_value(&self) -> Value<'static>;
+}
+
+impl Setting for i32 {
+    fn from_value(value: &Value) -> Option<Self> {
+        value.get_int()?.try_into().ok()
+    }
+    fn to_value(&self) -> Value<'static> {
+        Value::Int((*self).into())
+    }
+}
+
+impl Setting for i64 {
+    fn from_value(value: &Value) -> Option<Self> {
+        value.get_int()
+    }
+    fn to_value(&self) -> Value<'static> {
+        Value::Int(*self)
+    }
+}
+
+impl Setting for f64 {
+    fn from_value(value: &Value) -> Option<Self> {
+        value.as_f64()
+    }
+    /// Whole numbers are written as integers like Lightroom does
+    fn to_value(&self) -> Value<'static> {
+        match self.fract() == 0.0 && self.abs() < (1u64 << 53) as f64 {
+            true => Value::Int(*self as i64),
+            false => Value::Float(*self),
+        }
+    }
+}
+
+impl Setting for bool {
+    fn from_value(value: &Value) -> Option<Self> {
+        value.get_bool()
+    }
+    fn to_value(&self) -> Value<'static> {
+        Value::Bool(*self)
+    }
+}
+
+impl Setting for String {
+    fn from_value(value: &Value) -> Option<Self> {
+        value.get_string().map(str::to_string)
+    }
+    fn to_value(&self) -> Value<'static> {
+        Value::String(Cow::Owned(self.clone()))
+    }
+}
+
+impl Setting for crate::ToneCurve {
+    fn from_value(value: &Value) -> Option<Self> {
+        Self::from_value(value).ok()
+    }
+    fn to_value(&self) -> Value<'static> {
+        self.into()
+    }
+}
+
+/// Whether two values are the same setting, numbers compare by value whatever their type
+fn same(a: &Value, b: &Value) -> bool {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Removes `key` from `values` if its value can be converted to `T` and back without changes,
+/// keeping it in `originals` if it is written differently
+fn take<'a, T: Setting>(
+    values: &mut HashMap<Key<'a>, Value<'a>>,
+    originals: &mut HashMap<Key<'a>, Value<'a>>,
+    key: &str,
+) -> Option<T> {
+    let value = values.get(key)?;
+    let setting = T::from_value(value).filter(|s| same(&s.to_value(), value))?;
+    let exact = setting.to_value() == *value && !matches!(value, Value::Number(_));
+    let (key, value) = values.shift_remove_entry(key)?;
+    if !exact {
+        originals.insert(key, value);
+    }
+    Some(setting)
+}
+
+macro_rules! settings {
+    ($(#[$meta:meta])* $name:ident { $($field:ident: $ty:ty = $key:literal,)* }) => {
+        $(#[$meta])*
+        #[derive(Debug, Default, Clone, PartialEq)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        pub struct $name {
+            $(
+                #[doc = concat!("`", $key, "`")]
+                #[cfg_attr(
+                    feature = "serde",
+                    serde(rename = $key, default, skip_serializing_if = "Option::is_none")
+                )]
+                pub $field: Option<$ty>,
+            )*
+        }
+
+        impl $name {
+            /// The keys of the settings in this group
+            pub const KEYS: &'static [&'static str] = &[$($key),*];
+
+            fn take<'a>(
+                values: &mut HashMap<Key<'a>, Value<'a>>,
+                originals: &mut HashMap<Key<'a>, Value<'a>>,
+            ) -> Self {
+                Self {
+                    $($field: take(values, originals, $key),)*
+                }
+            }
+
+            fn put(&self, values: &mut Vec<(Key<'static>, Value<'static>)>) {
+                $(
+                    if let Some(setting) = &self.$field {
+                        values.push((Key::from($key), setting.to_value()));
+                    }
+                )*
+            }
+        }
+    };
+}
+
+/// The develop settings of a photo or preset
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DevelopSettings<'a> {
+    pub basic: Basic,
+    pub tone_curve: ToneCurve,
+    pub hsl: Hsl,
+    pub color_grading: ColorGrading,
+    pub detail: Detail,
+    pub lens_corrections: LensCorrections,
+    pub effects: Effects,
+    pub calibration: Calibration,
+    pub transform: Transform,
+    /// All other keys in the order they were read
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub extra: HashMap<Key<'a>, Value<'a>>,
+    /// The keys in the order they were read, which [`DevelopSettings::to_value`] keeps
+    #[cfg_attr(
+        feature = "serde",
+        serde(borrow, default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub order: Vec<Key<'a>>,
+    /// The settings above which were read with another spelling than they are written with,
+    /// like `Exposure2012 = 1.0`. They are written back as they were read while they hold the
+    /// same number.
+    #[cfg_attr(
+        feature = "serde",
+        serde(borrow, default, skip_serializing_if = "HashMap::is_empty")
+    )]
+    pub originals: HashMap<Key<'a>, Value<'a>>,
+}
+
+impl<'a> DevelopSettings<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the settings from a struct, an empty table is empty settings
+    pub fn from_value(value: Value<'a>) -> Result<Self, Errors> {
+        let mut values = match value {
+            Value::Struct(values) => values,
+            Value::Unit => HashMap::new(),
+            Value::Values(v) if v.is_empty() => HashMap::new(),
+            _ => return Err(Errors::Other("Develop settings must be a struct".into())),
+        };
+        let order = values.keys().cloned().collect();
+        let mut originals = HashMap::new();
+        Ok(Self {
+            basic: Basic::take(&mut values, &mut originals),
+            tone_curve: ToneCurve::take(&mut values, &mut originals),
+            hsl: Hsl::take(&mut values, &mut originals),
+            color_grading: ColorGrading::take(&mut values, &mut originals),
+            detail: Detail::take(&mut values, &mut originals),
+            lens_corrections: LensCorrections::take(&mut values, &mut originals),
+            effects: Effects::take(&mut values, &mut originals),
+            calibration: Calibration::take(&mut values, &mut originals),
+            transform: Transform::take(&mut values, &mut originals),
+            extra: values,
+            order,
+            originals,
+        })
+    }
+
+    /// The settings as a struct with the keys in the order they were read. Keys which weren't
+    /// read are sorted in like Lightroom writes them. The typed settings take precedence over
+    /// entries of `extra` with the same key.
+    pub fn to_value(&self) -> Value<'a> {
+        let mut values = Vec::new();
+        self.basic.put(&mut values);
+        self.tone_curve.put(&mut values);
+        self.hsl.put(&mut values);
+        self.color_grading.put(&mut values);
+        self.detail.put(&mut values);
+        self.lens_corrections.put(&mut values);
+        self.effects.put(&mut values);
+        self.calibration.put(&mut values);
+        self.transform.put(&mut values);
+        let typed: Vec<(Key<'a>, Value<'a>)> = values
+            .into_iter()
+            .map(|(key, value)| match self.originals.get(&key) {
+                Some(original) if same(original, &value) => (key, original.clone()),
+                _ => (key, value),
+            })
+            .collect();
+        let extra: Vec<_> = self
+            .extra
+            .iter()
+            .filter(|(key, _)| !typed.iter().any(|(k, _)| k == *key))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        let mut values: Vec<_> = typed.into_iter().chain(extra).collect();
+        values.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+        let (mut ordered, added): (Vec<_>, Vec<_>) = values
+            .into_iter()
+            .partition(|(key, _)| self.order.contains(key));
+        ordered.sort_by_key(|(key, _)| self.order.iter().position(|k| k == key));
+        for (key, value) in added {
+            let index = ordered
+                .iter()
+                .position(|(k, _)| k.as_str() > key.as_str())
+                .unwrap_or(ordered.len());
+            ordered.insert(index, (key, value));
+        }
+        Value::Struct(ordered.into_iter().collect())
+    }
+
+    /// The settings as the assignment `name = { ... }`
+    pub fn to_agpref(&self, name: impl Into<Cow<'a, str>>) -> Agpref<'a> {
+        Agpref {
+            name: name.into(),
+            values: self.to_value(),
+            ..Default::default()
+        }
+    }
+}
+
+impl<'a> TryFrom<Agpref<'a>> for DevelopSettings<'a> {
+    type Error = Errors;
+
+    /// The name of the agpref is ignored
+    fn try_from(agpref: Agpref<'a>) -> Result<Self, Errors> {
+        Self::from_value(agpref.values)
+    }
+}
+
+impl<'a> TryFrom<Value<'a>> for DevelopSettings<'a> {
+    type Error = Errors;
+
+    fn try_from(value: Value<'a>) -> Result<Self, Errors> {
+        Self::from_value(value)
+    }
+}
+
+impl<'a> From<DevelopSettings<'a>> for Agpref<'a> {
+    /// The settings as `s = { ... }`, the way Lightroom stores them
+    fn from(settings: DevelopSettings<'a>) -> Self {
+        settings.to_agpref("s")
+    }
+}
+
+settings! {
+    /// The Basic panel, along with the process version and the settings of older process versions
+    Basic {
+        process_version: String = "ProcessVersion",
+        white_balance: String = "WhiteBalance",
+        temperature: f64 = "Temperature",
+        tint: f64 = "Tint",
+        custom_temperature: f64 = "CustomTemperature",
+        custom_tint: f64 = "CustomTint",
+        exposure2012: f64 = "Exposure2012",
+        contrast2012: i32 = "Contrast2012",
+        highlights2012: i32 = "Highlights2012",
+        shadows2012: i32 = "Shadows2012",
+        whites2012: i32 = "Whites2012",
+        blacks2012: i32 = "Blacks2012",
+        texture: i32 = "Texture",
+        clarity2012: i32 = "Clarity2012",
+        dehaze: i32 = "Dehaze",
+        vibrance: i32 = "Vibrance",
+        saturation: i32 = "Saturation",
+        convert_to_grayscale: bool = "ConvertToGrayscale",
+        camera_profile: String = "CameraProfile",
+        camera_profile_digest: String = "CameraProfileDigest",
+        exposure: f64 = "Exposure",
+        brightness: i32 = "Brightness",
+        contrast: i32 = "Contrast",
+        shadows: i32 = "Shadows",
+        clarity: i32 = "Clarity",
+        highlight_recovery: i32 = "HighlightRecovery",
+        fill_light: i32 = "FillLight",
+    }
+}
+
+settings! {
+    /// The Tone Curve panel
+    ToneCurve {
+        tone_curve_name2012: String = "ToneCurveName2012",
+        tone_curve_pv2012: crate::ToneCurve = "ToneCurvePV2012",
+        tone_curve_pv2012_red: crate::ToneCurve = "ToneCurvePV2012Red",
+        tone_curve_pv2012_green: crate::ToneCurve = "ToneCurvePV2012Green",
+        tone_curve_pv2012_blue: crate::ToneCurve = "ToneCurvePV2012Blue",
+        extended_tone_curve_pv2012: crate::ToneCurve = "ExtendedToneCurvePV2012",
+        extended_tone_curve_pv2012_red: crate::ToneCurve = "ExtendedToneCurvePV2012Red",
+        extended_tone_curve_pv2012_green: crate::ToneCurve = "ExtendedToneCurvePV2012Green",
+        extended_tone_curve_pv2012_blue: crate::ToneCurve = "ExtendedToneCurvePV2012Blue",
+        parametric_shadows: i32 = "ParametricShadows",
+        parametric_darks: i32 = "ParametricDarks",
+        parametric_lights: i32 = "ParametricLights",
+        parametric_highlights: i32 = "ParametricHighlights",
+        parametric_shadow_split: i32 = "ParametricShadowSplit",
+        parametric_midtone_split: i32 = "ParametricMidtoneSplit",
+        parametric_highlight_split: i32 = "ParametricHighlightSplit",
+        curve_refine_saturation: i32 = "CurveRefineSaturation",
+    }
+}
+
+settings! {
+    /// The HSL / B&W panel
+    Hsl {
+        hue_adjustment_red: i32 = "HueAdjustmentRed",
+        hue_adjustment_orange: i32 = "HueAdjustmentOrange",
+        hue_adjustment_yellow: i32 = "HueAdjustmentYellow",
+        hue_adjustment_green: i32 = "HueAdjustmentGreen",
+        hue_adjustment_aqua: i32 = "HueAdjustmentAqua",
+        hue_adjustment_blue: i32 = "HueAdjustmentBlue",
+        hue_adjustment_purple: i32 = "HueAdjustmentPurple",
+        hue_adjustment_magenta: i32 = "HueAdjustmentMagenta",
+        saturation_adjustment_red: i32 = "SaturationAdjustmentRed",
+        saturation_adjustment_orange: i32 = "SaturationAdjustmentOrange",
+        saturation_adjustment_yellow: i32 = "SaturationAdjustmentYellow",
+        saturation_adjustment_green: i32 = "SaturationAdjustmentGreen",
+        saturation_adjustment_aqua: i32 = "SaturationAdjustmentAqua",
+        saturation_adjustment_blue: i32 = "SaturationAdjustmentBlue",
+        saturation_adjustment_purple: i32 = "SaturationAdjustmentPurple",
+        saturation_adjustment_magenta: i32 = "SaturationAdjustmentMagenta",
+        luminance_adjustment_red: i32 = "LuminanceAdjustmentRed",
+        luminance_adjustment_orange: i32 = "LuminanceAdjustmentOrange",
+        luminance_adjustment_yellow: i32 = "LuminanceAdjustmentYellow",
+        luminance_adjustment_green: i32 = "LuminanceAdjustmentGreen",
+        luminance_adjustment_aqua: i32 = "LuminanceAdjustmentAqua",
+        luminance_adjustment_blue: i32 = "LuminanceAdjustmentBlue",
+        luminance_adjustment_purple: i32 = "LuminanceAdjustmentPurple",
+        luminance_adjustment_magenta: i32 = "LuminanceAdjustmentMagenta",
+        gray_mixer_red: i32 = "GrayMixerRed",
+        gray_mixer_orange: i32 = "GrayMixerOrange",
+        gray_mixer_yellow: i32 = "GrayMixerYellow",
+        gray_mixer_green: i32 = "GrayMixerGreen",
+        gray_mixer_aqua: i32 = "GrayMixerAqua",
+        gray_mixer_blue: i32 = "GrayMixerBlue",
+        gray_mixer_purple: i32 = "GrayMixerPurple",
+        gray_mixer_magenta: i32 = "GrayMixerMagenta",
+    }
+}
+
+settings! {
+    /// The Color Grading panel and the Split Toning settings it replaced
+    ColorGrading {
+        color_grade_blending: i32 = "ColorGradeBlending",
+        color_grade_global_hue: i32 = "ColorGradeGlobalHue",
+        color_grade_global_sat: i32 = "ColorGradeGlobalSat",
+        color_grade_global_lum: i32 = "ColorGradeGlobalLum",
+        color_grade_highlight_lum: i32 = "ColorGradeHighlightLum",
+        color_grade_midtone_hue: i32 = "ColorGradeMidtoneHue",
+        color_grade_midtone_sat: i32 = "ColorGradeMidtoneSat",
+        color_grade_midtone_lum: i32 = "ColorGradeMidtoneLum",
+        color_grade_shadow_lum: i32 = "ColorGradeShadowLum",
+        split_toning_shadow_hue: i32 = "SplitToningShadowHue",
+        split_toning_shadow_saturation: i32 = "SplitToningShadowSaturation",
+        split_toning_highlight_hue: i32 = "SplitToningHighlightHue",
+        split_toning_highlight_saturation: i32 = "SplitToningHighlightSaturation",
+        split_toning_balance: i32 = "SplitToningBalance",
+    }
+}
+
+settings! {
+    /// The Detail panel
+    Detail {
+        sharpness: i32 = "Sharpness",
+        sharpen_radius: f64 = "SharpenRadius",
+        sharpen_detail: i32 = "SharpenDetail",
+        sharpen_edge_masking: i32 = "SharpenEdgeMasking",
+        luminance_smoothing: i32 = "LuminanceSmoothing",
+        luminance_noise_reduction_detail: i32 = "LuminanceNoiseReductionDetail",
+        luminance_noise_reduction_contrast: i32 = "LuminanceNoiseReductionContrast",
+        color_noise_reduction: i32 = "ColorNoiseReduction",
+        color_noise_reduction_detail: i32 = "ColorNoiseReductionDetail",
+        color_noise_reduction_smoothness: i32 = "ColorNoiseReductionSmoothness",
+    }
+}
+
+settings! {
+    /// The Lens Corrections panel
+    LensCorrections {
+        lens_profile_enable: i32 = "LensProfileEnable",
+        lens_profile_setup: String = "LensProfileSetup",
+        lens_profile_name: String = "LensProfileName",
+        lens_profile_filename: String = "LensProfileFilename",
+        lens_profile_digest: String = "LensProfileDigest",
+        lens_profile_is_embedded: bool = "LensProfileIsEmbedded",
+        lens_profile_distortion_scale: i32 = "LensProfileDistortionScale",
+        lens_profile_vignetting_scale: i32 = "LensProfileVignettingScale",
+        lens_manual_distortion_amount: i32 = "LensManualDistortionAmount",
+        auto_lateral_ca: i32 = "AutoLateralCA",
+        vignette_amount: i32 = "VignetteAmount",
+        vignette_midpoint: i32 = "VignetteMidpoint",
+        defringe_purple_amount: i32 = "DefringePurpleAmount",
+        defringe_purple_hue_lo: i32 = "DefringePurpleHueLo",
+        defringe_purple_hue_hi: i32 = "DefringePurpleHueHi",
+        defringe_green_amount: i32 = "DefringeGreenAmount",
+        defringe_green_hue_lo: i32 = "DefringeGreenHueLo",
+        defringe_green_hue_hi: i32 = "DefringeGreenHueHi",
+    }
+}
+
+settings! {
+    /// The Effects panel
+    Effects {
+        post_crop_vignette_amount: i32 = "PostCropVignetteAmount",
+        post_crop_vignette_midpoint: i32 = "PostCropVignetteMidpoint",
+        post_crop_vignette_feather: i32 = "PostCropVignetteFeather",
+        post_crop_vignette_roundness: i32 = "PostCropVignetteRoundness",
+        post_crop_vignette_style: i32 = "PostCropVignetteStyle",
+        post_crop_vignette_highlight_contrast: i32 = "PostCropVignetteHighlightContrast",
+        override_look_vignette: bool = "OverrideLookVignette",
+        grain_amount: i32 = "GrainAmount",
+        grain_size: i32 = "GrainSize",
+        grain_frequency: i32 = "GrainFrequency",
+        grain_seed: i64 = "GrainSeed",
+    }
+}
+
+settings! {
+    /// The Calibration panel
+    Calibration {
+        shadow_tint: i32 = "ShadowTint",
+        red_hue: i32 = "RedHue",
+        red_saturation: i32 = "RedSaturation",
+        green_hue: i32 = "GreenHue",
+        green_saturation: i32 = "GreenSaturation",
+        blue_hue: i32 = "BlueHue",
+        blue_saturation: i32 = "BlueSaturation",
+    }
+}
+
+settings! {
+    /// The Transform panel
+    Transform {
+        perspective_vertical: f64 = "PerspectiveVertical",
+        perspective_horizontal: f64 = "PerspectiveHorizontal",
+        perspective_rotate: f64 = "PerspectiveRotate",
+        perspective_scale: f64 = "PerspectiveScale",
+        perspective_aspect: f64 = "PerspectiveAspect",
+        perspective_x: f64 = "PerspectiveX",
+        perspective_y: f64 = "PerspectiveY",
+        upright_version: i64 = "UprightVersion",
+        upright_center_mode: i32 = "UprightCenterMode",
+        upright_center_norm_x: f64 = "UprightCenterNormX",
+        upright_center_norm_y: f64 = "UprightCenterNormY",
+        upright_focal_mode: i32 = "UprightFocalMode",
+        upright_focal_length35mm: f64 = "UprightFocalLength35mm",
+        upright_preview: bool = "UprightPreview",
+        upright_transform_count: i32 = "UprightTransformCount",
+        upright_four_segments_count: i32 = "UprightFourSegmentsCount",
+    }
+}
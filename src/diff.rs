@@ -0,0 +1,402 @@
+//! Structural differences between two values
+//!
+//! Changes are keyed by the path of the value they are about, in the syntax of
+//! [`Value::pointer`]. Numbers compare by value whether they are written as integers or floats,
+//! embedded agprefs are compared by the values they hold and `{ }` is equal to any other empty
+//! table.
+use crate::pointer::join_path;
+use crate::types::{HashMap, Key, Value};
+use std::fmt;
+
+/// How lists are compared
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ListComparison {
+    /// Element by element, like tone curves
+    #[default]
+    Ordered,
+    /// As multisets, ignoring the order of the elements. Elements only in one of the lists are
+    /// reported at their index in that list.
+    Set,
+}
+
+/// Options to control what counts as a change
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DiffOptions {
+    /// Numbers which differ by at most this much are equal
+    pub float_tolerance: f64,
+    pub lists: ListComparison,
+}
+
+impl DiffOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn float_tolerance(mut self, float_tolerance: f64) -> Self {
+        self.float_tolerance = float_tolerance;
+        self
+    }
+
+    pub fn lists(mut self, lists: ListComparison) -> Self {
+        self.lists = lists;
+        self
+    }
+}
+
+/// A single difference between two values
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(tag = "change", rename_all = "snake_case")
+)]
+pub enum Change<'v> {
+    /// The value only exists in the second value
+    Added { path: String, value: Value<'v> },
+    /// The value only exists in the first value
+    Removed { path: String, value: Value<'v> },
+    /// The value changed but kept its type, `delta` is `to - from` for numbers
+    Modified {
+        path: String,
+        from: Value<'v>,
+        to: Value<'v>,
+        delta: Option<f64>,
+    },
+    /// The value changed to a different type, like a number to a string
+    TypeChanged {
+        path: String,
+        from: Value<'v>,
+        to: Value<'v>,
+    },
+}
+
+impl Change<'_> {
+    pub fn path(&self) -> &str {
+        match self {
+            Change::Added { path, .. }
+            | Change::Removed { path, .. }
+            | Change::Modified { path, .. }
+            | Change::TypeChanged { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for Change<'_> {
+    /// One line of the report, like `~ Look/Amount: 1 -> 0.5 (-0.5)`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = match self.path() {
+            "" => "/",
+            path => path,
+        };
+        match self {
+            Change::Added { value, .. } => write!(f, "+ {} = {}", path, Inline(value)),
+            Change::Removed { value, .. } => write!(f, "- {} = {}", path, Inline(value)),
+            Change::Modified {
+                from, to, delta, ..
+            } => {
+                write!(f, "~ {}: {} -> {}", path, Inline(from), Inline(to))?;
+                match delta {
+                    // Rounded to hide the noise of subtracting floats like 0.5 - 1.05
+                    Some(delta) => write!(f, " ({:+})", (delta * 1e6).round() / 1e6),
+                    None => Ok(()),
+                }
+            }
+            Change::TypeChanged { from, to, .. } => write!(
+                f,
+                "! {}: {} -> {} ({} -> {})",
+                path,
+                Inline(from),
+                Inline(to),
+                Shape::of(from).name(),
+                Shape::of(to).name()
+            ),
+        }
+    }
+}
+
+/// All the changes from one value to another
+///
+/// Displays as a report with a line per change.
+///
+/// ```
+/// use agprefs::{diff, Agpref};
+/// let a = Agpref::parse("s = { Exposure2012 = 1.05, Look = { Name = \"Adobe Color\" } }").unwrap();
+/// let b = Agpref::parse("s = { Exposure2012 = 0.5, Texture = 10, Look = { } }").unwrap();
+/// assert_eq!(
+///     diff(&a, &b).to_string(),
+///     "~ Exposure2012: 1.05 -> 0.5 (-0.55)\n\
+///      - Look/Name = \"Adobe Color\"\n\
+///      + Texture = 10\n"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(transparent))]
+pub struct Diff<'v> {
+    pub changes: Vec<Change<'v>>,
+}
+
+impl<'v> Diff<'v> {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// The change at the given path
+    pub fn get(&self, path: &str) -> Option<&Change<'v>> {
+        self.changes.iter().find(|c| c.path() == path)
+    }
+}
+
+impl<'v> std::ops::Deref for Diff<'v> {
+    type Target = [Change<'v>];
+    fn deref(&self) -> &Self::Target {
+        &self.changes
+    }
+}
+
+impl<'v> IntoIterator for Diff<'v> {
+    type Item = Change<'v>;
+    type IntoIter = std::vec::IntoIter<Change<'v>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.changes.into_iter()
+    }
+}
+
+impl fmt::Display for Diff<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+/// The changes from `a` to `b`
+pub fn diff<'v>(a: &Value<'v>, b: &Value<'v>) -> Diff<'v> {
+    diff_with(a, b, DiffOptions::default())
+}
+
+/// The changes from `a` to `b` with the given options
+pub fn diff_with<'v>(a: &Value<'v>, b: &Value<'v>, options: DiffOptions) -> Diff<'v> {
+    let mut changes = Vec::new();
+    compare(String::new(), a, b, &options, &mut changes);
+    Diff { changes }
+}
+
+/// What a value is, looking through embedded agprefs
+enum Shape<'a, 'v> {
+    Empty,
+    Number(f64),
+    Bool(bool),
+    String(&'a str),
+    Zstr(&'a str),
+    List(&'a [Value<'v>]),
+    Struct(&'a HashMap<Key<'v>, Value<'v>>),
+}
+
+impl<'a, 'v> Shape<'a, 'v> {
+    fn of(value: &'a Value<'v>) -> Self {
+        match value {
+            Value::Unit => Shape::Empty,
+            Value::Values(v) if v.is_empty() => Shape::Empty,
+            Value::Struct(s) if s.is_empty() => Shape::Empty,
+            Value::Int(i) => Shape::Number(*i as f64),
+            Value::Float(f) => Shape::Number(*f),
+            Value::Number(n) => Shape::Number(n.as_f64()),
+            Value::Bool(b) => Shape::Bool(*b),
+            Value::String(s) => Shape::String(s),
+            Value::Zstr(s) => Shape::Zstr(s),
+            Value::Values(v) => Shape::List(v),
+            Value::Struct(s) => Shape::Struct(s),
+            Value::Embedded(e) => Shape::of(&e.values),
+            #[cfg(feature = "namedlist")]
+            Value::NamedList(n) if n.is_empty() => Shape::Empty,
+            #[cfg(feature = "namedlist")]
+            Value::NamedList(n) => Shape::List(&n.values),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Shape::Empty => "empty table",
+            Shape::Number(_) => "number",
+            Shape::Bool(_) => "bool",
+            Shape::String(_) => "string",
+            Shape::Zstr(_) => "localized string",
+            Shape::List(_) => "list",
+            Shape::Struct(_) => "struct",
+        }
+    }
+}
+
+fn compare<'v>(
+    path: String,
+    a: &Value<'v>,
+    b: &Value<'v>,
+    options: &DiffOptions,
+    changes: &mut Vec<Change<'v>>,
+) {
+    let modified = |delta| Change::Modified {
+        path: path.clone(),
+        from: a.clone(),
+        to: b.clone(),
+        delta,
+    };
+    match (Shape::of(a), Shape::of(b)) {
+        (Shape::Empty, Shape::Empty) => {}
+        (Shape::Number(x), Shape::Number(y)) => {
+            if (y - x).abs() > options.float_tolerance || x.is_nan() != y.is_nan() {
+                changes.push(modified(Some(y - x)));
+            }
+        }
+        (Shape::Bool(x), Shape::Bool(y)) if x != y => changes.push(modified(None)),
+        (Shape::String(x), Shape::String(y)) | (Shape::Zstr(x), Shape::Zstr(y)) if x != y => {
+            changes.push(modified(None))
+        }
+        (Shape::Bool(_), Shape::Bool(_))
+        | (Shape::String(_), Shape::String(_))
+        | (Shape::Zstr(_), Shape::Zstr(_)) => {}
+        (Shape::List(x), Shape::List(y)) => compare_lists(&path, x, y, options, changes),
+        (Shape::List(x), Shape::Empty) => compare_lists(&path, x, &[], options, changes),
+        (Shape::Empty, Shape::List(y)) => compare_lists(&path, &[], y, options, changes),
+        (Shape::Struct(x), Shape::Struct(y)) => compare_structs(&path, x, y, options, changes),
+        (Shape::Struct(x), Shape::Empty) => {
+            compare_structs(&path, x, &HashMap::new(), options, changes)
+        }
+        (Shape::Empty, Shape::Struct(y)) => {
+            compare_structs(&path, &HashMap::new(), y, options, changes)
+        }
+        _ => changes.push(Change::TypeChanged {
+            path,
+            from: a.clone(),
+            to: b.clone(),
+        }),
+    }
+}
+
+fn compare_structs<'v>(
+    path: &str,
+    a: &HashMap<Key<'v>, Value<'v>>,
+    b: &HashMap<Key<'v>, Value<'v>>,
+    options: &DiffOptions,
+    changes: &mut Vec<Change<'v>>,
+) {
+    for (key, x) in a {
+        let path = join_path(path, key.as_str());
+        match b.get(key) {
+            Some(y) => compare(path, x, y, options, changes),
+            None => changes.push(Change::Removed {
+                path,
+                value: x.clone(),
+            }),
+        }
+    }
+    for (key, y) in b.iter().filter(|(key, _)| !a.contains_key(*key)) {
+        changes.push(Change::Added {
+            path: join_path(path, key.as_str()),
+            value: y.clone(),
+        });
+    }
+}
+
+fn compare_lists<'v>(
+    path: &str,
+    a: &[Value<'v>],
+    b: &[Value<'v>],
+    options: &DiffOptions,
+    changes: &mut Vec<Change<'v>>,
+) {
+    let removed = |i: usize| Change::Removed {
+        path: join_path(path, &i.to_string()),
+        value: a[i].clone(),
+    };
+    let added = |i: usize| Change::Added {
+        path: join_path(path, &i.to_string()),
+        value: b[i].clone(),
+    };
+    match options.lists {
+        ListComparison::Ordered => {
+            for i in 0..a.len().max(b.len()) {
+                match (a.get(i), b.get(i)) {
+                    (Some(x), Some(y)) => {
+                        compare(join_path(path, &i.to_string()), x, y, options, changes)
+                    }
+                    (Some(_), None) => changes.push(removed(i)),
+                    (None, Some(_)) => changes.push(added(i)),
+                    (None, None) => unreachable!(),
+                }
+            }
+        }
+        ListComparison::Set => {
+            let mut matched = vec![false; b.len()];
+            for (i, x) in a.iter().enumerate() {
+                let found = (0..b.len()).find(|&j| !matched[j] && equal(x, &b[j], options));
+                match found {
+                    Some(j) => matched[j] = true,
+                    None => changes.push(removed(i)),
+                }
+            }
+            for j in (0..b.len()).filter(|&j| !matched[j]) {
+                changes.push(added(j));
+            }
+        }
+    }
+}
+
+fn equal<'v>(a: &Value<'v>, b: &Value<'v>, options: &DiffOptions) -> bool {
+    let mut changes = Vec::new();
+    compare(String::new(), a, b, options, &mut changes);
+    changes.is_empty()
+}
+
+/// A value written on a single line for the report
+pub(crate) struct Inline<'a, 'v>(pub(crate) &'a Value<'v>);
+
+impl fmt::Display for Inline<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Value::Unit => f.write_str("{ }"),
+            Value::Int(i) => write!(f, "{}", i),
+            Value::Float(x) => write!(f, "{}", x),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::String(s) => write!(f, "{:?}", s),
+            Value::Zstr(s) => write!(f, "ZSTR {:?}", s),
+            Value::Values(v) => write_list(f, v),
+            Value::Struct(s) if s.is_empty() => f.write_str("{ }"),
+            Value::Struct(s) => {
+                f.write_str("{ ")?;
+                for (i, (key, value)) in s.iter().enumerate() {
+                    let separator = if i == 0 { "" } else { ", " };
+                    match key.needs_brackets() {
+                        true => write!(f, "{}[{:?}] = {}", separator, key.as_str(), Inline(value))?,
+                        false => write!(f, "{}{} = {}", separator, key, Inline(value))?,
+                    }
+                }
+                f.write_str(" }")
+            }
+            Value::Embedded(e) => write!(f, "{} = {}", e.name, Inline(&e.values)),
+            #[cfg(feature = "namedlist")]
+            Value::NamedList(n) => {
+                write!(f, "{} = ", n.name)?;
+                write_list(f, &n.values)
+            }
+        }
+    }
+}
+
+fn write_list(f: &mut fmt::Formatter<'_>, values: &[Value]) -> fmt::Result {
+    if values.is_empty() {
+        return f.write_str("{ }");
+    }
+    f.write_str("{ ")?;
+    for (i, value) in values.iter().enumerate() {
+        let separator = if i == 0 { "" } else { ", " };
+        write!(f, "{}{}", separator, Inline(value))?;
+    }
+    f.write_str(" }")
+}
@@ -0,0 +1,198 @@
+//! Finding the preferences of Lightroom on disk
+//!
+//! Lightroom keeps its preferences in a file named after the version that introduced it,
+//! `Lightroom Classic CC 7 Preferences.agprefs` for every version of Lightroom Classic and
+//! `Lightroom 6 Preferences.agprefs` and so on for the versions before it. [`PrefsLocator`] lists
+//! where those files are for an operating system and picks the newest one which exists.
+//!
+//! The filesystem root is a parameter so the layout of another operating system can be searched,
+//! like a mounted disk or a test directory:
+//!
+//! ```no_run
+//! use agprefs::discovery::{Platform, PrefsLocator};
+//! let locator = PrefsLocator::new("Users/me", Platform::Windows).root("/mnt/c");
+//! if let Some(found) = locator.find() {
+//!     println!("Lightroom {} preferences at {}", found.version, found.path.display());
+//! }
+//! ```
+use std::path::{Component, PathBuf};
+
+/// The preference files of each version of Lightroom, newest first
+const FILES: &[(u32, &str)] = &[
+    (7, "Lightroom Classic CC 7 Preferences.agprefs"),
+    (6, "Lightroom 6 Preferences.agprefs"),
+    (5, "Lightroom 5 Preferences.agprefs"),
+    (4, "Lightroom 4 Preferences.agprefs"),
+    (3, "Lightroom 3 Preferences.agprefs"),
+];
+
+/// An operating system Lightroom runs on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Platform {
+    MacOs,
+    Windows,
+}
+
+impl Platform {
+    /// The platform this was compiled for, `None` where there is no Lightroom
+    pub fn current() -> Option<Self> {
+        if cfg!(target_os = "macos") {
+            Some(Self::MacOs)
+        } else if cfg!(windows) {
+            Some(Self::Windows)
+        } else {
+            None
+        }
+    }
+
+    /// The directories holding the preferences, relative to the home directory
+    fn directories(self) -> &'static [&'static [&'static str]] {
+        match self {
+            // Next to `com.adobe.LightroomClassicCC7.plist` and in the Lightroom settings
+            Self::MacOs => &[
+                &["Library", "Preferences"],
+                &[
+                    "Library",
+                    "Application Support",
+                    "Adobe",
+                    "Lightroom",
+                    "Preferences",
+                ],
+            ],
+            // %APPDATA%\Adobe\Lightroom\Preferences
+            Self::Windows => &[&["AppData", "Roaming", "Adobe", "Lightroom", "Preferences"]],
+        }
+    }
+}
+
+/// A preference file of Lightroom
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefsFile {
+    /// The major version of Lightroom which introduced the file, `7` for Lightroom Classic
+    pub version: u32,
+    pub path: PathBuf,
+}
+
+/// Lists where the preferences of Lightroom are kept on a platform
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefsLocator {
+    root: PathBuf,
+    home: PathBuf,
+    platform: Platform,
+}
+
+impl PrefsLocator {
+    /// Searches the `home` directory of a user on `platform`. The home directory is taken
+    /// relative to the root of the filesystem, which is `/` unless changed with
+    /// [`PrefsLocator::root`].
+    pub fn new(home: impl Into<PathBuf>, platform: Platform) -> Self {
+        Self {
+            root: PathBuf::from("/"),
+            home: home.into(),
+            platform,
+        }
+    }
+
+    /// Searches the home directory of the current user, from `HOME` or `USERPROFILE`
+    pub fn from_env() -> Option<Self> {
+        let platform = Platform::current()?;
+        let home = match platform {
+            Platform::MacOs => std::env::var_os("HOME")?,
+            Platform::Windows => std::env::var_os("USERPROFILE")?,
+        };
+        Some(Self::from_home(home, platform))
+    }
+
+    /// Searches the absolute `home` directory of a user on `platform` on this filesystem. The
+    /// drive or network share of a Windows home, like `C:` or `\\server\share`, is the root.
+    pub fn from_home(home: impl Into<PathBuf>, platform: Platform) -> Self {
+        let home = home.into();
+        let prefix = match platform {
+            Platform::MacOs => String::new(),
+            Platform::Windows => windows_prefix(&home.to_string_lossy()).to_string(),
+        };
+        let locator = Self::new(home, platform);
+        if prefix.is_empty() {
+            locator
+        } else {
+            locator.root(prefix + "\\")
+        }
+    }
+
+    /// The directory the home directory is resolved in
+    pub fn root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = root.into();
+        self
+    }
+
+    /// Every path the preferences could be at, newest version first
+    pub fn candidates(&self) -> Vec<PrefsFile> {
+        let mut home = self.root.clone();
+        home.extend(self.home_directories());
+        FILES
+            .iter()
+            .flat_map(|&(version, file)| {
+                self.platform
+                    .directories()
+                    .iter()
+                    .map(move |directory| (version, directory, file))
+            })
+            .map(|(version, directory, file)| {
+                let mut path = home.clone();
+                path.extend(directory.iter());
+                path.push(file);
+                PrefsFile { version, path }
+            })
+            .collect()
+    }
+
+    /// The directories of the home directory, without its drive or root so it stays inside
+    /// `root`. A Windows home is split on both kinds of slashes whatever the current platform.
+    fn home_directories(&self) -> Vec<PathBuf> {
+        match self.platform {
+            Platform::MacOs => self
+                .home
+                .components()
+                .filter_map(|c| match c {
+                    Component::Normal(c) => Some(PathBuf::from(c)),
+                    _ => None,
+                })
+                .collect(),
+            Platform::Windows => {
+                let home = self.home.to_string_lossy();
+                home[windows_prefix(&home).len()..]
+                    .split(['\\', '/'])
+                    .filter(|c| !matches!(*c, "" | "." | ".."))
+                    .map(PathBuf::from)
+                    .collect()
+            }
+        }
+    }
+
+    /// The preferences of the newest version of Lightroom which exist
+    pub fn find(&self) -> Option<PrefsFile> {
+        self.candidates().into_iter().find(|c| c.path.is_file())
+    }
+}
+
+/// The drive or share a Windows path starts with, like `C:`, `\\server\share` or `\\?\C:`
+fn windows_prefix(path: &str) -> &str {
+    let parts: Vec<&str> = path.split(['\\', '/']).collect();
+    let count = match parts.as_slice() {
+        ["", "", "?" | ".", "UNC", _, _, ..] => 6,
+        ["", "", _, _, ..] => 4,
+        [drive, ..] if is_drive(drive) => 1,
+        _ => 0,
+    };
+    let len: usize = parts[..count].iter().map(|p| p.len() + 1).sum();
+    &path[..len.saturating_sub(1)]
+}
+
+/// Whether a part of a Windows path is a drive like `C:`
+fn is_drive(part: &str) -> bool {
+    let mut chars = part.chars();
+    matches!(
+        (chars.next(), chars.next(), chars.next()),
+        (Some(letter), Some(':'), None) if letter.is_ascii_alphabetic()
+    )
+}
@@ -9,10 +9,21 @@ pub enum Errors {
     #[error("Failed to parse")]
     Nom(String),
     #[error("{0}")]
+    Syntax(#[from] SyntaxError),
+    #[error("No value at `{0}`")]
+    NotFound(String),
+    #[error("The value at `{0}` doesn't match the test")]
+    TestFailed(String),
+    #[error("{0}")]
+    Conversion(#[from] ConversionError),
+    #[error("{0}")]
     Other(String),
     #[cfg(feature = "composer")]
     #[error("{0}")]
     Compose(#[from] cookie_factory::GenError),
+    #[cfg(feature = "xmp")]
+    #[error("{0}")]
+    Xml(#[from] roxmltree::Error),
 }
 
 impl From<nom::Err<nom::error::Error<&str>>> for Errors {
@@ -20,3 +31,200 @@ impl From<nom::Err<nom::error::Error<&str>>> for Errors {
         Errors::Nom(e.to_string())
     }
 }
+
+#[cfg(feature = "serde")]
+impl serde::de::Error for Errors {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Errors::Other(msg.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::ser::Error for Errors {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Errors::Other(msg.to_string())
+    }
+}
+
+/// Something the parser expected to find at the position of a [`SyntaxError`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Expected {
+    /// A literal token like `=`, `,` or `}`
+    Token(&'static str),
+    /// An identifier like `s` or `prefs`
+    Identifier,
+    /// A struct key, either an identifier or `["bracketed"]`
+    Key,
+    /// A double quoted string
+    String,
+    /// Any value
+    Value,
+    /// Nothing but whitespace
+    EndOfInput,
+}
+
+impl std::fmt::Display for Expected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expected::Token(t) => write!(f, "`{}`", t),
+            Expected::Identifier => write!(f, "an identifier"),
+            Expected::Key => write!(f, "a key"),
+            Expected::String => write!(f, "a string"),
+            Expected::Value => write!(f, "a value"),
+            Expected::EndOfInput => write!(f, "end of input"),
+        }
+    }
+}
+
+/// A positioned error for input which isn't a valid agprefs document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    /// Byte offset of the error in the input
+    pub offset: usize,
+    /// 1 based line of the error
+    pub line: usize,
+    /// 1 based column of the error, counted in characters
+    pub column: usize,
+    /// The set of tokens which would have been valid at the error
+    pub expected: Vec<Expected>,
+    /// Keys and list indices leading to the value which failed to parse, outermost first
+    pub path: Vec<String>,
+    /// The line containing the error followed by a line with a caret pointing at it
+    pub snippet: String,
+}
+
+impl SyntaxError {
+    pub(crate) fn new(
+        input: &str,
+        offset: usize,
+        expected: Vec<Expected>,
+        path: Vec<String>,
+    ) -> Self {
+        let line_start = input[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line_end = input[offset..]
+            .find('\n')
+            .map(|i| i + offset)
+            .unwrap_or(input.len());
+        let prefix = &input[line_start..offset];
+        let text = input[line_start..line_end].trim_end_matches('\r');
+        // Keep the tabs so the caret lines up with the text above it
+        let caret: String = prefix
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .chain(std::iter::once('^'))
+            .collect();
+        Self {
+            offset,
+            line: input[..offset].matches('\n').count() + 1,
+            column: prefix.chars().count() + 1,
+            expected,
+            path,
+            snippet: format!("{}\n{}", text, caret),
+        }
+    }
+
+    /// The key path joined with `.`, e.g. `s.Look.Parameters`
+    pub fn key_path(&self) -> String {
+        self.path.join(".")
+    }
+}
+
+impl std::fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "expected ")?;
+        match self.expected.as_slice() {
+            [] => write!(f, "valid input")?,
+            [only] => write!(f, "{}", only)?,
+            [init @ .., last] => {
+                for (i, e) in init.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", e)?;
+                }
+                write!(f, " or {}", last)?;
+            }
+        }
+        write!(f, " at line {}, column {}", self.line, self.column)?;
+        if !self.path.is_empty() {
+            write!(f, " in `{}`", self.key_path())?;
+        }
+        let gutter = self.line.to_string().len();
+        let mut lines = self.snippet.lines();
+        if let (Some(text), Some(caret)) = (lines.next(), lines.next()) {
+            write!(f, "\n{:gutter$} |", "")?;
+            write!(f, "\n{} | {}", self.line, text)?;
+            write!(f, "\n{:gutter$} | {}", "", caret)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+/// A short description of a value for errors, like `float 1.5` or `struct`
+pub(crate) fn describe(value: &crate::Value) -> String {
+    use crate::Value;
+    match value {
+        Value::Unit => "nil".to_string(),
+        Value::Int(i) => format!("integer {}", i),
+        Value::Float(f) => format!("float {}", f),
+        Value::Number(n) => format!("number {}", n),
+        Value::Bool(b) => format!("bool {}", b),
+        Value::String(s) => format!("string {:?}", s),
+        Value::Values(_) => "list".to_string(),
+        Value::Struct(_) => "struct".to_string(),
+        Value::Embedded(_) => "embedded agpref".to_string(),
+        Value::Zstr(s) => format!("localized string {:?}", s),
+        #[cfg(feature = "namedlist")]
+        Value::NamedList(_) => "named list".to_string(),
+    }
+}
+
+/// A value which couldn't be converted to the requested type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversionError {
+    /// The path of the value in the syntax of [`Value::pointer`](crate::Value::pointer), relative
+    /// to the value the conversion started at
+    pub path: String,
+    /// The type the value should have been converted to, like `u8` or `list`
+    pub expected: &'static str,
+    /// The value which was found, like `float 1.5` or `struct`
+    pub found: String,
+}
+
+impl ConversionError {
+    pub(crate) fn new(expected: &'static str, found: &crate::Value) -> Self {
+        Self {
+            path: String::new(),
+            expected,
+            found: describe(found),
+        }
+    }
+
+    /// Moves the error into the entry `segment` of the value being converted
+    pub(crate) fn within(mut self, segment: &str) -> Self {
+        let path = crate::pointer::join_path("", segment);
+        self.path = match self.path.is_empty() {
+            true => path,
+            false => format!("{}/{}", path, self.path),
+        };
+        self
+    }
+}
+
+impl std::fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = match self.path.as_str() {
+            "" => "/",
+            path => path,
+        };
+        write!(
+            f,
+            "expected {} at `{}`, found {}",
+            self.expected, path, self.found
+        )
+    }
+}
+
+impl std::error::Error for ConversionError {}
@@ -1,11 +1,46 @@
 #[cfg(feature = "composer")]
 mod composer;
+mod convert;
+#[cfg(feature = "composer")]
+pub mod cst;
+#[cfg(feature = "serde")]
+pub mod de;
+pub mod develop;
+mod diff;
+pub mod discovery;
 mod errors;
+pub mod lightroom;
+mod macros;
+mod merge;
 mod parser;
+mod patch;
+mod pointer;
+mod preset;
+mod schema;
+#[cfg(all(feature = "serde", feature = "composer"))]
+pub mod ser;
+mod style;
+mod tone_curve;
 mod types;
+#[cfg(feature = "xmp")]
+pub mod xmp;
 
-pub use errors::Errors;
+#[cfg(feature = "serde")]
+pub use de::{from_str, Deserializer};
+pub use develop::DevelopSettings;
+pub use diff::{diff, diff_with, Change, Diff, DiffOptions, ListComparison};
+pub use errors::{ConversionError, Errors, Expected, SyntaxError};
+pub use lightroom::{CatalogUpgrade, LightroomPrefs};
+pub use merge::{merge, merge_with, Conflict, Resolution, Resolver, Strategy};
+pub use parser::ParseOptions;
+pub use patch::{Operation, Patch};
+pub use preset::DevelopPreset;
+pub use schema::{Problem, Schema, StructSchema, Violation};
+#[cfg(all(feature = "serde", feature = "composer"))]
+pub use ser::{to_string, to_string_with, Serializer};
+pub use style::{ComposeOptions, Indent, Layout, Newline, TrailingComma};
+pub use tone_curve::ToneCurve;
 #[cfg(feature = "namedlist")]
 #[cfg_attr(docsrs, doc(cfg(feature = "namedlist")))]
 pub use types::NamedList;
-pub use types::{Agpref, Value};
+pub use types::{is_identifier, Agpref, Document, Key, Number, Value};
@@ -0,0 +1,364 @@
+//! The preferences of Lightroom Classic
+//!
+//! [`LightroomPrefs`] reads and edits `Lightroom Classic CC 7 Preferences.agprefs`, where the
+//! recent catalogs are stored as agprefs nested in strings:
+//!
+//! ```text
+//! prefs = {
+//! \tlibraryToLoad20 = "/Users/me/Pictures/Lightroom/Lightroom Catalog.lrcat",
+//! \trecentLibraries20 = "recentLibraries = {\
+//! \t\"/Users/me/Pictures/Lightroom/Lightroom Catalog.lrcat\",\
+//! }\
+//! ",
+//! \trecentLibraries20_missing = "recentMissingLibraries = {\
+//! }\
+//! ",
+//! }
+//! ```
+//!
+//! When Lightroom upgrades a catalog to a new version it records it in the preferences as well,
+//! see [`CatalogUpgrade`].
+use crate::errors::{ConversionError, Errors};
+use crate::parser::ParseOptions;
+use crate::types::{Agpref, Value};
+
+const LIBRARY_TO_LOAD: &str = "libraryToLoad20";
+const RECENT: (&str, &str) = ("recentLibraries20", "recentLibraries");
+const MISSING: (&str, &str) = ("recentLibraries20_missing", "recentMissingLibraries");
+const UPGRADES: &str = "Adobe_successfulUpgrades";
+
+/// The catalogs in the preferences of Lightroom Classic
+///
+/// Recent catalogs are listed most recent first. Everything else in the preferences is kept
+/// and written back as it was.
+///
+/// ```
+/// use agprefs::LightroomPrefs;
+/// let input = r#"prefs = {
+///     libraryToLoad20 = "/Catalogs/A.lrcat",
+///     recentLibraries20 = "recentLibraries = {\
+///     \"/Catalogs/A.lrcat\",\
+/// }\
+/// ",
+/// }
+/// "#;
+/// let mut prefs = LightroomPrefs::parse(input).unwrap();
+/// assert_eq!(prefs.recent_catalogs(), ["/Catalogs/A.lrcat"]);
+/// assert!(prefs.missing_catalogs().is_empty());
+///
+/// prefs.add_recent("/Catalogs/B.lrcat");
+/// prefs.set_library_to_load("/Catalogs/B.lrcat");
+/// assert_eq!(prefs.recent_catalogs(), ["/Catalogs/B.lrcat", "/Catalogs/A.lrcat"]);
+/// # #[cfg(feature = "composer")]
+/// assert!(prefs.to_str().unwrap().contains("\\\"/Catalogs/B.lrcat\\\",\\\n"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightroomPrefs<'a> {
+    prefs: Agpref<'a>,
+}
+
+impl<'a> LightroomPrefs<'a> {
+    /// Parses the preferences, keeping their formatting
+    pub fn parse(input: &'a str) -> Result<Self, Errors> {
+        let options = ParseOptions::new().detect_style(true).decode_embedded(true);
+        Self::from_agpref(Agpref::parse_with(input, options)?)
+    }
+
+    /// Wraps parsed preferences, decoding the lists of catalogs and the upgrades if they are
+    /// still strings
+    pub fn from_agpref(mut prefs: Agpref<'a>) -> Result<Self, Errors> {
+        match &prefs.values {
+            Value::Struct(_) => {}
+            Value::Unit => prefs.values = Value::Struct(Default::default()),
+            Value::Values(v) if v.is_empty() => prefs.values = Value::Struct(Default::default()),
+            _ => return Err(Errors::Other("The preferences must be a struct".into())),
+        }
+        let entries = prefs.get_mut_struct().into_iter().flatten();
+        for (key, value) in entries {
+            let nested = [RECENT.0, MISSING.0].contains(&key.as_str()) || key.starts_with(UPGRADES);
+            if let (true, Value::String(s)) = (nested, &*value) {
+                let options = ParseOptions::new()
+                    .strict(true)
+                    .detect_style(true)
+                    .decode_embedded(true);
+                // Upgrades which can't be parsed stay strings for `upgrade_errors` to report
+                let decoded = match Agpref::parse_with(s, options) {
+                    Ok(decoded) => decoded.into_static(),
+                    Err(_) if key.starts_with(UPGRADES) => continue,
+                    Err(e) => return Err(e),
+                };
+                *value = Value::Embedded(Box::new(decoded));
+            }
+        }
+        Ok(Self { prefs })
+    }
+
+    pub fn agpref(&self) -> &Agpref<'a> {
+        &self.prefs
+    }
+
+    pub fn into_agpref(self) -> Agpref<'a> {
+        self.prefs
+    }
+
+    /// The catalog Lightroom opens on start
+    pub fn library_to_load(&self) -> Option<&str> {
+        self.prefs.get_struct()?.get(LIBRARY_TO_LOAD)?.get_string()
+    }
+
+    pub fn set_library_to_load(&mut self, path: impl Into<String>) {
+        if let Some(s) = self.prefs.get_mut_struct() {
+            s.insert(LIBRARY_TO_LOAD.into(), Value::from(path.into()));
+        }
+    }
+
+    /// The recently opened catalogs, most recent first
+    pub fn recent_catalogs(&self) -> Vec<&str> {
+        self.catalogs(RECENT.0)
+    }
+
+    /// Catalogs Lightroom couldn't find the last time it looked for them
+    pub fn missing_catalogs(&self) -> Vec<&str> {
+        self.catalogs(MISSING.0)
+    }
+
+    /// Adds a catalog as the most recent one, moving it to the front if it is listed already
+    pub fn add_recent(&mut self, path: impl Into<String>) {
+        let path = path.into();
+        self.remove_recent(&path);
+        self.list_mut(RECENT).insert(0, Value::from(path));
+    }
+
+    /// Removes a catalog from the recent ones, returning whether it was listed
+    pub fn remove_recent(&mut self, path: &str) -> bool {
+        remove(self.list_mut(RECENT), path)
+    }
+
+    /// Moves a recent catalog to `index`, failing if it isn't listed or `index` is out of range
+    pub fn move_recent(&mut self, path: &str, index: usize) -> Result<(), Errors> {
+        let list = self.list_mut(RECENT);
+        let from = list
+            .iter()
+            .position(|v| v.get_string() == Some(path))
+            .ok_or_else(|| Errors::NotFound(path.to_string()))?;
+        if index >= list.len() {
+            return Err(Errors::Other(format!(
+                "Cannot move `{}` to {} of {} recent catalogs",
+                path,
+                index,
+                list.len()
+            )));
+        }
+        let value = list.remove(from);
+        list.insert(index, value);
+        Ok(())
+    }
+
+    /// Replaces the recent catalogs, most recent first
+    pub fn set_recent_catalogs<S: Into<String>>(&mut self, paths: impl IntoIterator<Item = S>) {
+        *self.list_mut(RECENT) = paths.into_iter().map(|p| Value::from(p.into())).collect();
+    }
+
+    /// Adds a catalog to the missing ones if it isn't listed already
+    pub fn add_missing(&mut self, path: impl Into<String>) {
+        let path = path.into();
+        if !self.missing_catalogs().contains(&path.as_str()) {
+            self.list_mut(MISSING).push(Value::from(path));
+        }
+    }
+
+    /// Removes a catalog from the missing ones, returning whether it was listed
+    pub fn remove_missing(&mut self, path: &str) -> bool {
+        remove(self.list_mut(MISSING), path)
+    }
+
+    /// The catalogs Lightroom upgraded, in the order they are stored. Entries which can't be
+    /// read are skipped, see [`upgrade_errors`](Self::upgrade_errors).
+    pub fn upgrades(&self) -> Vec<CatalogUpgrade> {
+        self.read_upgrades().filter_map(Result::ok).collect()
+    }
+
+    /// Why the entries [`upgrades`](Self::upgrades) skipped couldn't be read, like a key without
+    /// a version or an entry missing its `upgraded` path
+    pub fn upgrade_errors(&self) -> Vec<Errors> {
+        self.read_upgrades().filter_map(Result::err).collect()
+    }
+
+    /// Follows the upgrades of the catalog at `path` to the newest version of it. Paths of
+    /// catalogs which weren't upgraded are returned as they are.
+    pub fn resolve_catalog(&self, path: &str) -> String {
+        CatalogUpgrade::resolve(&self.upgrades(), path).to_string()
+    }
+
+    fn read_upgrades(&self) -> impl Iterator<Item = Result<CatalogUpgrade, Errors>> + '_ {
+        let entries = self.prefs.get_struct().into_iter().flatten();
+        entries
+            .flat_map(|(key, value)| {
+                let version = key.strip_prefix(UPGRADES)?;
+                let pickle = match version.parse() {
+                    Ok(version) => CatalogUpgrade::read_pickle(version, value)
+                        .into_iter()
+                        .map(|u| u.map_err(|e| Errors::from(e.within(key.as_str()))))
+                        .collect(),
+                    Err(_) => vec![Err(Errors::Other(format!(
+                        "`{}` doesn't end in a version",
+                        key
+                    )))],
+                };
+                Some(pickle)
+            })
+            .flatten()
+    }
+
+    fn catalogs(&self, key: &str) -> Vec<&str> {
+        let list = match self.prefs.get_struct().and_then(|s| s.get(key)) {
+            Some(Value::Embedded(e)) => e.get_values(),
+            #[cfg(feature = "namedlist")]
+            Some(Value::NamedList(n)) => Some(n.values.as_slice()),
+            _ => None,
+        };
+        list.unwrap_or_default()
+            .iter()
+            .filter_map(Value::get_string)
+            .collect()
+    }
+
+    /// The list stored at `key`, created as an empty one named `name` if there is none
+    fn list_mut(&mut self, (key, name): (&str, &str)) -> &mut Vec<Value<'a>> {
+        let s = self
+            .prefs
+            .get_mut_struct()
+            .expect("The preferences are a struct");
+        let value = s.entry(key.to_string().into()).or_default();
+        let is_list = match value {
+            Value::Embedded(e) => matches!(e.values, Value::Values(_)),
+            #[cfg(feature = "namedlist")]
+            Value::NamedList(_) => true,
+            _ => false,
+        };
+        if !is_list {
+            let mut list = Agpref::with_name(name.to_string());
+            list.values = Value::Values(Vec::new());
+            *value = Value::Embedded(Box::new(list));
+        }
+        match value {
+            Value::Embedded(e) => e.values.get_mut_values().expect("A list"),
+            #[cfg(feature = "namedlist")]
+            Value::NamedList(n) => &mut n.values,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// A catalog Lightroom upgraded to a newer catalog version
+///
+/// Lightroom stores the upgrades each version did as a nested agpref in a key like
+/// `Adobe_successfulUpgrades1100000`:
+///
+/// ```text
+/// pickle = {
+/// \t["/Catalogs/fivek.lrcat"] = {
+/// \t\tcatalogType = "lr",
+/// \t\tid = "70933BD8-9D87-4D87-8541-6A2BFE2979A7",
+/// \t\tupgraded = "/Catalogs/fivek-v11.lrcat",
+/// \t},
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CatalogUpgrade {
+    /// The path of the catalog which was upgraded
+    pub from: String,
+    /// The path of the upgraded copy
+    pub to: String,
+    /// `catalogType`, `lr` for Lightroom Classic catalogs
+    pub catalog_type: String,
+    /// `id`, the UUID of the catalog
+    pub id: String,
+    /// The version of Lightroom which did the upgrade, from the suffix of the key like
+    /// `1100000` for Lightroom Classic 11
+    pub version: u32,
+}
+
+impl CatalogUpgrade {
+    /// The major version of Lightroom which did the upgrade, like `11`
+    pub fn major_version(&self) -> u32 {
+        self.version / 100_000
+    }
+
+    /// Reads the upgrades of the pickle stored in a `Adobe_successfulUpgrades` key by the
+    /// given Lightroom `version`
+    pub fn from_pickle(version: u32, pickle: &Value) -> Result<Vec<Self>, ConversionError> {
+        Self::read_pickle(version, pickle).into_iter().collect()
+    }
+
+    /// Reads each entry of a pickle on its own, so one malformed entry doesn't hide the others
+    fn read_pickle(version: u32, pickle: &Value) -> Vec<Result<Self, ConversionError>> {
+        let entries = match pickle {
+            Value::Embedded(e) => &e.values,
+            pickle => pickle,
+        };
+        let entries = match entries {
+            Value::Struct(s) => s,
+            Value::Unit => return Vec::new(),
+            Value::Values(v) if v.is_empty() => return Vec::new(),
+            entries => return vec![Err(ConversionError::new("struct", entries))],
+        };
+        let field = |entry: &Value, name: &str| {
+            let value = entry.get_struct().and_then(|s| s.get(name));
+            String::try_from(value.unwrap_or(&Value::Unit)).map_err(|e| e.within(name))
+        };
+        entries
+            .iter()
+            .map(|(from, entry)| {
+                let upgrade = || {
+                    Ok(Self {
+                        from: from.to_string(),
+                        to: field(entry, "upgraded")?,
+                        catalog_type: field(entry, "catalogType")?,
+                        id: field(entry, "id")?,
+                        version,
+                    })
+                };
+                upgrade().map_err(|e: ConversionError| e.within(from.as_str()))
+            })
+            .collect()
+    }
+
+    /// Follows the upgrades of the catalog at `path` to the newest version of it, taking the
+    /// upgrade of the newest Lightroom where there are several of the same catalog
+    pub fn resolve<'u>(upgrades: &'u [CatalogUpgrade], path: &'u str) -> &'u str {
+        let mut current = path;
+        let mut seen = vec![path];
+        while let Some(upgrade) = upgrades
+            .iter()
+            .filter(|u| u.from == current)
+            .max_by_key(|u| u.version)
+        {
+            if seen.contains(&upgrade.to.as_str()) {
+                break;
+            }
+            current = &upgrade.to;
+            seen.push(current);
+        }
+        current
+    }
+}
+
+fn remove(list: &mut Vec<Value>, path: &str) -> bool {
+    let len = list.len();
+    list.retain(|v| v.get_string() != Some(path));
+    list.len() != len
+}
+
+#[cfg(feature = "composer")]
+impl LightroomPrefs<'_> {
+    /// Writes the preferences back in the formatting they were parsed with
+    pub fn to_str(&self) -> Result<String, Errors> {
+        self.prefs.to_str()
+    }
+
+    pub fn write<W: std::io::Write>(&self, w: W) -> Result<(), Errors> {
+        self.prefs.write(w)
+    }
+}
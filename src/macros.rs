@@ -0,0 +1,122 @@
+/// Builds a [`Value`](crate::Value) or [`Agpref`](crate::Agpref) from agprefs syntax
+///
+/// Tables whose first entry is `Key = ...` or `["key"] = ...` become structs, other tables
+/// become lists and `{ }` is an empty table like the parser produces. Entries keep their order.
+/// Anything else is a Rust expression converted with [`Value::from`](crate::Value), the keys in
+/// brackets are expressions as well. Starting with `name =` produces an agpref.
+///
+/// Every two entries of a table take a level of macro recursion, so tables with up to about 250
+/// entries fit in the default `recursion_limit`. Larger ones need `#![recursion_limit = "512"]`
+/// in the crate using the macro.
+///
+/// ```
+/// use agprefs::{agprefs, Agpref, Value};
+/// let exposure = 0.35;
+/// let name = "Profiles";
+/// let x = agprefs!(s = {
+///     Exposure2012 = exposure,
+///     Look = {
+///         Group = { ["x-default"] = name },
+///         Parameters = { ToneCurvePV2012 = { 0, 0, 255, 255 } },
+///     },
+///     Vibrance = -31,
+/// });
+/// assert_eq!(x.name, "s");
+/// assert_eq!(x["Exposure2012"], Value::Float(0.35));
+/// assert_eq!(x["Look"]["Group"]["x-default"], Value::from("Profiles"));
+/// let parsed = Agpref::parse(
+///     r#"s = { Exposure2012 = 0.35, Look = { Group = { ["x-default"] = "Profiles" },
+///         Parameters = { ToneCurvePV2012 = { 0, 0, 255, 255 } } }, Vibrance = -31 }"#,
+/// );
+/// assert_eq!(x, parsed.unwrap());
+///
+/// let curve: Value = agprefs!({ 0, 0, 255, 255 });
+/// assert_eq!(curve, Value::from(vec![0i64, 0, 255, 255]));
+/// ```
+#[macro_export]
+macro_rules! agprefs {
+    // The entries of a struct, the finished ones are collected in the brackets. A value is a
+    // single token tree, like a table or a literal, or else an expression. The entries are taken
+    // two at a time to need fewer levels of recursion.
+    (@struct [$($out:tt)*]) => {
+        $crate::Value::Struct(::core::iter::FromIterator::from_iter([$($out)*]))
+    };
+    (@struct [$($out:tt)*] $k1:tt = $v1:tt, $k2:tt = $v2:tt $(, $($rest:tt)*)?) => {
+        $crate::agprefs!(@struct [$($out)* $crate::agprefs!(@entry $k1 $v1), $crate::agprefs!(@entry $k2 $v2),] $($($rest)*)?)
+    };
+    (@struct [$($out:tt)*] $k1:tt = $v1:tt, $k2:tt = $v2:expr $(, $($rest:tt)*)?) => {
+        $crate::agprefs!(@struct [$($out)* $crate::agprefs!(@entry $k1 $v1), $crate::agprefs!(@entry $k2 $v2),] $($($rest)*)?)
+    };
+    (@struct [$($out:tt)*] $key:tt = $value:tt $(,)?) => {
+        $crate::agprefs!(@struct [$($out)* $crate::agprefs!(@entry $key $value),])
+    };
+    (@struct [$($out:tt)*] $k1:tt = $v1:expr, $k2:tt = $v2:tt $(, $($rest:tt)*)?) => {
+        $crate::agprefs!(@struct [$($out)* $crate::agprefs!(@entry $k1 $v1), $crate::agprefs!(@entry $k2 $v2),] $($($rest)*)?)
+    };
+    (@struct [$($out:tt)*] $k1:tt = $v1:expr, $k2:tt = $v2:expr $(, $($rest:tt)*)?) => {
+        $crate::agprefs!(@struct [$($out)* $crate::agprefs!(@entry $k1 $v1), $crate::agprefs!(@entry $k2 $v2),] $($($rest)*)?)
+    };
+    (@struct [$($out:tt)*] $key:tt = $value:expr $(,)?) => {
+        $crate::agprefs!(@struct [$($out)* $crate::agprefs!(@entry $key $value),])
+    };
+    // The elements of a list, taken two at a time like the entries of a struct
+    (@list [$($out:tt)*]) => {
+        $crate::Value::Values(::std::vec![$($out)*])
+    };
+    (@list [$($out:tt)*] $v1:tt, $v2:tt $(, $($rest:tt)*)?) => {
+        $crate::agprefs!(@list [$($out)* $crate::agprefs!(@value $v1), $crate::agprefs!(@value $v2),] $($($rest)*)?)
+    };
+    (@list [$($out:tt)*] $v1:tt, $v2:expr $(, $($rest:tt)*)?) => {
+        $crate::agprefs!(@list [$($out)* $crate::agprefs!(@value $v1), $crate::agprefs!(@value $v2),] $($($rest)*)?)
+    };
+    (@list [$($out:tt)*] $value:tt $(,)?) => {
+        $crate::agprefs!(@list [$($out)* $crate::agprefs!(@value $value),])
+    };
+    (@list [$($out:tt)*] $v1:expr, $v2:tt $(, $($rest:tt)*)?) => {
+        $crate::agprefs!(@list [$($out)* $crate::agprefs!(@value $v1), $crate::agprefs!(@value $v2),] $($($rest)*)?)
+    };
+    (@list [$($out:tt)*] $v1:expr, $v2:expr $(, $($rest:tt)*)?) => {
+        $crate::agprefs!(@list [$($out)* $crate::agprefs!(@value $v1), $crate::agprefs!(@value $v2),] $($($rest)*)?)
+    };
+    (@list [$($out:tt)*] $value:expr $(,)?) => {
+        $crate::agprefs!(@list [$($out)* $crate::agprefs!(@value $value),])
+    };
+    // A single entry or value
+    (@entry [$key:expr] $value:tt) => {
+        (
+            $crate::Key::Quoted(::std::borrow::Cow::from($key)),
+            $crate::agprefs!(@value $value),
+        )
+    };
+    (@entry $key:ident $value:tt) => {
+        ($crate::Key::from(stringify!($key)), $crate::agprefs!(@value $value))
+    };
+    (@value { $($table:tt)* }) => {
+        $crate::agprefs!({ $($table)* })
+    };
+    (@value $value:expr) => {
+        $crate::Value::from($value)
+    };
+
+    ($name:ident = $($value:tt)+) => {
+        $crate::Agpref {
+            values: $crate::agprefs!($($value)+),
+            ..$crate::Agpref::with_name(stringify!($name))
+        }
+    };
+    ({}) => {
+        $crate::Value::Values(::std::vec::Vec::new())
+    };
+    ({ $key:ident = $($rest:tt)* }) => {
+        $crate::agprefs!(@struct [] $key = $($rest)*)
+    };
+    ({ [$key:expr] = $($rest:tt)* }) => {
+        $crate::agprefs!(@struct [] [$key] = $($rest)*)
+    };
+    ({ $($rest:tt)+ }) => {
+        $crate::agprefs!(@list [] $($rest)+)
+    };
+    ($value:expr) => {
+        $crate::Value::from($value)
+    };
+}
@@ -7,6 +7,8 @@ fn main() -> Result<()> {
         let agprefs = Agpref::parse(&s)?;
         #[cfg(feature = "composer")]
         println!("{}", agprefs.to_str()?);
+        #[cfg(not(feature = "composer"))]
+        println!("{:#?}", agprefs);
     }
     Ok(())
 }
@@ -0,0 +1,254 @@
+//! Three-way merges of values
+//!
+//! Merging takes the common `base` and two edited versions of it, `ours` and `theirs`. Where only
+//! one side changed a value that change wins, where both made the same change it is kept. Keys
+//! of structs merge independently, recursing into nested structs like `Look/Parameters`, while
+//! lists like tone curves are merged as a whole. Anything else changed on both sides is a
+//! [`Conflict`] which a [`Resolver`] decides or which is reported back.
+use crate::diff::Inline;
+use crate::pointer::join_path;
+use crate::types::{Agpref, HashMap, Key, Value};
+use std::borrow::Cow;
+use std::fmt;
+
+/// A value changed differently on both sides, `None` where the value doesn't exist
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Conflict<'v> {
+    /// The path of the value in the syntax of [`Value::pointer`]
+    pub path: String,
+    pub base: Option<Value<'v>>,
+    pub ours: Option<Value<'v>>,
+    pub theirs: Option<Value<'v>>,
+}
+
+impl fmt::Display for Conflict<'_> {
+    /// Like `Exposure2012: base 1.05, ours 0.5, theirs missing`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = match self.path.as_str() {
+            "" => "/",
+            path => path,
+        };
+        write!(f, "{}:", path)?;
+        for (side, value) in [
+            ("base", &self.base),
+            ("ours", &self.ours),
+            ("theirs", &self.theirs),
+        ] {
+            let separator = if side == "base" { " " } else { ", " };
+            match value {
+                Some(value) => write!(f, "{}{} {}", separator, side, Inline(value))?,
+                None => write!(f, "{}{} missing", separator, side)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What to do about a conflict
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution<'v> {
+    /// Keep our side, removing the value if we removed it
+    Ours,
+    /// Keep their side, removing the value if they removed it
+    Theirs,
+    /// Keep the value of the base
+    Base,
+    /// Use this value instead
+    Value(Value<'v>),
+    /// Remove the value
+    Remove,
+    /// Report the conflict, making the merge fail
+    Unresolved,
+}
+
+/// Decides conflicts of a merge
+///
+/// Closures taking a [`Conflict`] and returning a [`Resolution`] are resolvers as well.
+pub trait Resolver<'v> {
+    fn resolve(&mut self, conflict: &Conflict<'v>) -> Resolution<'v>;
+}
+
+impl<'v, F: FnMut(&Conflict<'v>) -> Resolution<'v>> Resolver<'v> for F {
+    fn resolve(&mut self, conflict: &Conflict<'v>) -> Resolution<'v> {
+        self(conflict)
+    }
+}
+
+/// The built in resolvers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Strategy {
+    /// Leave every conflict unresolved
+    #[default]
+    Report,
+    /// Our side wins every conflict
+    Ours,
+    /// Their side wins every conflict
+    Theirs,
+}
+
+impl<'v> Resolver<'v> for Strategy {
+    fn resolve(&mut self, _conflict: &Conflict<'v>) -> Resolution<'v> {
+        match self {
+            Strategy::Report => Resolution::Unresolved,
+            Strategy::Ours => Resolution::Ours,
+            Strategy::Theirs => Resolution::Theirs,
+        }
+    }
+}
+
+/// Merges the changes `ours` and `theirs` made to `base`, failing with every conflict
+///
+/// ```
+/// use agprefs::{merge, Agpref, Value};
+/// let base = Agpref::parse("s = { Exposure2012 = 0, Texture = 0, Vibrance = 0 }").unwrap();
+/// let ours = Agpref::parse("s = { Exposure2012 = 0.5, Texture = 0, Vibrance = 10 }").unwrap();
+/// let theirs = Agpref::parse("s = { Exposure2012 = 0, Texture = 20, Vibrance = -10 }").unwrap();
+/// let conflicts = merge(&base, &ours, &theirs).unwrap_err();
+/// assert_eq!(conflicts[0].to_string(), "Vibrance: base 0, ours 10, theirs -10");
+///
+/// let merged = base.merge_with(&ours, &theirs, agprefs::Strategy::Theirs).unwrap();
+/// assert_eq!(merged["Exposure2012"], Value::Float(0.5));
+/// assert_eq!(merged["Texture"], Value::Int(20));
+/// assert_eq!(merged["Vibrance"], Value::Int(-10));
+/// ```
+pub fn merge<'v>(
+    base: &Value<'v>,
+    ours: &Value<'v>,
+    theirs: &Value<'v>,
+) -> Result<Value<'v>, Vec<Conflict<'v>>> {
+    merge_with(base, ours, theirs, Strategy::Report)
+}
+
+/// Merges the changes `ours` and `theirs` made to `base`, letting `resolver` decide conflicts.
+/// Fails with the conflicts it left unresolved.
+pub fn merge_with<'v>(
+    base: &Value<'v>,
+    ours: &Value<'v>,
+    theirs: &Value<'v>,
+    mut resolver: impl Resolver<'v>,
+) -> Result<Value<'v>, Vec<Conflict<'v>>> {
+    let mut merger = Merger {
+        resolver: &mut resolver,
+        conflicts: Vec::new(),
+    };
+    let merged = merger.merge(String::new(), Some(base), Some(ours), Some(theirs));
+    match merger.conflicts.is_empty() {
+        true => Ok(merged.unwrap_or_default()),
+        false => Err(merger.conflicts),
+    }
+}
+
+impl<'a> Agpref<'a> {
+    /// Merges the changes `ours` and `theirs` made to this agpref, keeping the name and style of
+    /// `ours`. See [`merge`](crate::merge).
+    pub fn merge(
+        &self,
+        ours: &Agpref<'a>,
+        theirs: &Agpref<'a>,
+    ) -> Result<Agpref<'a>, Vec<Conflict<'a>>> {
+        self.merge_with(ours, theirs, Strategy::Report)
+    }
+
+    /// Merges the changes `ours` and `theirs` made to this agpref, letting `resolver` decide
+    /// conflicts. See [`merge_with`](crate::merge_with).
+    pub fn merge_with(
+        &self,
+        ours: &Agpref<'a>,
+        theirs: &Agpref<'a>,
+        resolver: impl Resolver<'a>,
+    ) -> Result<Agpref<'a>, Vec<Conflict<'a>>> {
+        Ok(Agpref {
+            values: merge_with(&self.values, &ours.values, &theirs.values, resolver)?,
+            ..ours.clone()
+        })
+    }
+}
+
+struct Merger<'r, 'v> {
+    resolver: &'r mut dyn Resolver<'v>,
+    conflicts: Vec<Conflict<'v>>,
+}
+
+/// The entries of a struct, empty tables count as structs without entries
+fn fields<'a, 'v>(value: &'a Value<'v>) -> Option<Cow<'a, HashMap<Key<'v>, Value<'v>>>> {
+    match value {
+        Value::Struct(s) => Some(Cow::Borrowed(s)),
+        Value::Unit => Some(Cow::Owned(HashMap::new())),
+        Value::Values(v) if v.is_empty() => Some(Cow::Owned(HashMap::new())),
+        _ => None,
+    }
+}
+
+impl<'v> Merger<'_, 'v> {
+    /// The merged value, `None` if it was removed
+    fn merge(
+        &mut self,
+        path: String,
+        base: Option<&Value<'v>>,
+        ours: Option<&Value<'v>>,
+        theirs: Option<&Value<'v>>,
+    ) -> Option<Value<'v>> {
+        if ours == theirs || base == theirs {
+            return ours.cloned();
+        }
+        if base == ours {
+            return theirs.cloned();
+        }
+        if let (Some(o), Some(t)) = (ours.and_then(fields), theirs.and_then(fields)) {
+            let b = base.and_then(fields).unwrap_or_default();
+            return Some(Value::Struct(self.merge_structs(&path, &b, &o, &t)));
+        }
+
+        let conflict = Conflict {
+            path,
+            base: base.cloned(),
+            ours: ours.cloned(),
+            theirs: theirs.cloned(),
+        };
+        match self.resolver.resolve(&conflict) {
+            Resolution::Ours => ours.cloned(),
+            Resolution::Theirs => theirs.cloned(),
+            Resolution::Base => base.cloned(),
+            Resolution::Value(value) => Some(value),
+            Resolution::Remove => None,
+            Resolution::Unresolved => {
+                self.conflicts.push(conflict);
+                ours.cloned()
+            }
+        }
+    }
+
+    fn merge_structs(
+        &mut self,
+        path: &str,
+        base: &HashMap<Key<'v>, Value<'v>>,
+        ours: &HashMap<Key<'v>, Value<'v>>,
+        theirs: &HashMap<Key<'v>, Value<'v>>,
+    ) -> HashMap<Key<'v>, Value<'v>> {
+        // Our order, with keys only they have placed after the key they follow in theirs
+        let mut keys: Vec<&Key<'v>> = ours.keys().collect();
+        let mut previous = None;
+        for key in theirs.keys() {
+            if !ours.contains_key(key) {
+                let at = previous.map_or(0, |i| i + 1);
+                keys.insert(at, key);
+            }
+            previous = keys.iter().position(|k| *k == key);
+        }
+
+        let mut merged = HashMap::new();
+        for key in keys {
+            let value = self.merge(
+                join_path(path, key.as_str()),
+                base.get(key),
+                ours.get(key),
+                theirs.get(key),
+            );
+            if let Some(value) = value {
+                merged.insert(key.clone(), value);
+            }
+        }
+        merged
+    }
+}
@@ -1,3 +1,4 @@
+use crate::errors::{Expected, SyntaxError};
 use crate::types::*;
 use indexmap::IndexMap;
 
@@ -9,20 +10,125 @@ use nom::{
     error::{ErrorKind, ParseError},
     multi::*,
     sequence::*,
-    IResult,
 };
+use nom_supreme::tag::{complete::tag, TagError};
 use std::borrow::Cow;
 
+pub(crate) type IResult<'a, O> = nom::IResult<&'a str, O, Error<'a>>;
+
 impl Agpref<'_> {
     /// Parse the given string into an Agpref struct.
     #[deprecated]
-    pub fn from_str(s: &str) -> Result<Agpref, crate::errors::Errors> {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Result<Agpref<'_>, crate::errors::Errors> {
         Self::parse(s)
     }
 
     #[inline(always)]
-    pub fn parse(s: &str) -> Result<Agpref, crate::errors::Errors> {
-        Ok(_agprefs(s)?.1)
+    pub fn parse(s: &str) -> Result<Agpref<'_>, crate::errors::Errors> {
+        Self::parse_with(s, ParseOptions::default())
+    }
+
+    /// Parse the given string into an Agpref struct with the given options.
+    pub fn parse_with(s: &str, options: ParseOptions) -> Result<Agpref<'_>, crate::errors::Errors> {
+        #[allow(unused_mut)]
+        let (rest, mut prefs) = finish(s, _agprefs(s, options))?;
+        if options.strict {
+            finish(s, eof(rest))?;
+        }
+        #[cfg(feature = "composer")]
+        if options.detect_style {
+            prefs.style = Some(crate::cst::detect_style(s));
+        }
+        Ok(prefs)
+    }
+}
+
+impl Document<'_> {
+    /// Parse all the top level assignments of the given string
+    #[inline(always)]
+    pub fn parse(s: &str) -> Result<Document<'_>, crate::errors::Errors> {
+        Self::parse_with(s, ParseOptions::default())
+    }
+
+    /// Parse all the top level assignments of the given string with the given options.
+    pub fn parse_with(
+        s: &str,
+        options: ParseOptions,
+    ) -> Result<Document<'_>, crate::errors::Errors> {
+        let mut entries = Vec::new();
+        let mut rest = s;
+        while peek(tuple((multispace0, get_identifier, equals)))(rest).is_ok() {
+            #[allow(unused_mut)]
+            let (r, mut entry) = finish(s, get_assignment(rest, |s| get_value_with(s, options)))?;
+            // Only the entry and the line break after it, the rest of the input is left to
+            // the following entries
+            #[cfg(feature = "composer")]
+            if options.detect_style {
+                let line_break = ["\r\n", "\n"].into_iter().find(|l| r.starts_with(l));
+                let end = rest.len() - r.len() + line_break.map_or(0, str::len);
+                entry.style = Some(crate::cst::detect_style(&rest[..end]));
+            }
+            entries.push(entry);
+            rest = r;
+        }
+        if options.strict || entries.is_empty() {
+            finish(
+                s,
+                preceded(multispace0, expecting(Expected::Identifier, eof))(rest),
+            )?;
+        }
+        Ok(Document { entries })
+    }
+}
+
+/// Options to control how the input is parsed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Fail if there is anything but whitespace after the parsed document
+    pub strict: bool,
+    /// Record the formatting of the input in [`Agpref::style`] so it is written back the
+    /// same way. Requires the `composer` feature, ignored otherwise.
+    pub detect_style: bool,
+    /// Parse numbers into [`Value::Number`] which keeps their spelling, like `1e-05` or `-0`
+    pub preserve_numbers: bool,
+    /// Decode strings holding a whole agpref, like `"recentLibraries = {\ ... }"`, into
+    /// [`Value::Embedded`]. The nested agpref is parsed with the same options.
+    pub decode_embedded: bool,
+    /// Decode strings holding a list, like `"recentLibraries = {\ ... }"`, into
+    /// `Value::NamedList`. Requires the `namedlist` feature, ignored otherwise.
+    /// [`decode_embedded`](ParseOptions::decode_embedded) takes precedence when both are set.
+    pub decode_namedlists: bool,
+}
+
+impl ParseOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    pub fn detect_style(mut self, detect_style: bool) -> Self {
+        self.detect_style = detect_style;
+        self
+    }
+
+    pub fn preserve_numbers(mut self, preserve_numbers: bool) -> Self {
+        self.preserve_numbers = preserve_numbers;
+        self
+    }
+
+    pub fn decode_embedded(mut self, decode_embedded: bool) -> Self {
+        self.decode_embedded = decode_embedded;
+        self
+    }
+
+    pub fn decode_namedlists(mut self, decode_namedlists: bool) -> Self {
+        self.decode_namedlists = decode_namedlists;
+        self
     }
 }
 
@@ -34,18 +140,137 @@ impl Agpref<'_> {
 //     }
 // }
 
-fn _agprefs(s: &str) -> Result<(&str, Agpref), nom::Err<nom::error::Error<&str>>> {
-    let (s, (name, value)) = get_key_value(s)?;
-    let mut prefs = Agpref::with_name(name);
-    match value {
-        Value::Struct(_) => prefs.values = value,
-        Value::Values(_) => prefs.values = value,
+/// The error type of all the parsers in this module
+///
+/// Keeps track of what was expected at the furthest position any parser got to and the keys
+/// of the values enclosing it, so it can be turned into a [`SyntaxError`] at the end.
+#[derive(Debug)]
+pub(crate) struct Error<'a> {
+    input: &'a str,
+    expected: Vec<Expected>,
+    /// Innermost key first
+    path: Vec<Cow<'a, str>>,
+}
 
-        _ => return Err(nom::Err::Error(nom::error::Error::new(s, ErrorKind::Fail))),
+impl<'a> Error<'a> {
+    fn expected(input: &'a str, expected: Expected) -> Self {
+        Self {
+            input,
+            expected: vec![expected],
+            path: Vec::new(),
+        }
     }
+
+    fn in_key(mut self, key: impl Into<Cow<'a, str>>) -> Self {
+        self.path.push(key.into());
+        self
+    }
+
+    fn into_syntax_error(self, original: &str) -> SyntaxError {
+        let offset = original.len() - self.input.len();
+        let path = self.path.iter().rev().map(|k| k.to_string()).collect();
+        SyntaxError::new(original, offset, self.expected, path)
+    }
+}
+
+impl<'a> ParseError<&'a str> for Error<'a> {
+    fn from_error_kind(input: &'a str, _kind: ErrorKind) -> Self {
+        Self {
+            input,
+            expected: Vec::new(),
+            path: Vec::new(),
+        }
+    }
+
+    fn append(_input: &'a str, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    /// Keeps the error which got further into the input, merging the expectations if both got
+    /// equally far
+    fn or(mut self, other: Self) -> Self {
+        match self.input.len().cmp(&other.input.len()) {
+            std::cmp::Ordering::Less => self,
+            std::cmp::Ordering::Greater => other,
+            std::cmp::Ordering::Equal => {
+                for e in other.expected {
+                    if !self.expected.contains(&e) {
+                        self.expected.push(e);
+                    }
+                }
+                if other.path.len() > self.path.len() {
+                    self.path = other.path;
+                }
+                self
+            }
+        }
+    }
+}
+
+impl<'a> TagError<&'a str, &'static str> for Error<'a> {
+    fn from_tag(input: &'a str, tag: &'static str) -> Self {
+        Self::expected(input, Expected::Token(tag))
+    }
+}
+
+/// Turns the result of a top level parser into a [`SyntaxError`] relative to `original`
+pub(crate) fn finish<'a, O>(
+    original: &'a str,
+    result: IResult<'a, O>,
+) -> Result<(&'a str, O), SyntaxError> {
+    result.map_err(|e| match e {
+        nom::Err::Error(e) | nom::Err::Failure(e) => e.into_syntax_error(original),
+        nom::Err::Incomplete(_) => {
+            SyntaxError::new(original, original.len(), vec![Expected::Value], Vec::new())
+        }
+    })
+}
+
+/// Reports `expected` for errors of `parser` which happen before it consumed any input
+fn expecting<'a, O>(
+    expected: Expected,
+    mut parser: impl FnMut(&'a str) -> IResult<'a, O>,
+) -> impl FnMut(&'a str) -> IResult<'a, O> {
+    move |s: &'a str| {
+        parser(s).map_err(|e| {
+            e.map(|mut e| {
+                if e.input.len() == s.len() {
+                    e.expected = vec![expected.clone()];
+                }
+                e
+            })
+        })
+    }
+}
+
+fn _agprefs(s: &str, options: ParseOptions) -> IResult<'_, Agpref<'_>> {
+    get_assignment(s, |s| get_table(s, options))
+}
+
+/// Parses a top level `name = value` assignment
+fn get_assignment<'a>(
+    s: &'a str,
+    value: impl FnMut(&'a str) -> IResult<'a, Value<'a>>,
+) -> IResult<'a, Agpref<'a>> {
+    let (s, _) = multispace0(s)?;
+    let (s, name) = expecting(Expected::Identifier, get_identifier)(s)?;
+    let (s, _) = cut(equals)(s)?;
+    let (s, values) = cut(value)(s).map_err(|e| e.map(|e| e.in_key(name)))?;
+    let mut prefs = Agpref::with_name(name);
+    prefs.values = values;
     Ok((s, prefs))
 }
 
+/// Succeeds if there is nothing but whitespace left
+fn eof(s: &str) -> IResult<'_, ()> {
+    let (s, _) = multispace0(s)?;
+    if s.is_empty() {
+        Ok((s, ()))
+    } else {
+        Err(nom::Err::Error(Error::expected(s, Expected::EndOfInput)))
+    }
+}
+
 #[test]
 fn esc_test() {
     let s = esc(r#"C:\\Users\\harsh\\Pictures\\Lightroom\\Lightroom Catalog.lrcat"#).unwrap();
@@ -71,11 +296,11 @@ fn esc_test_empty() {
 // }
 
 /// Returns an escaped string from a double escaped string
-fn esc(input: &str) -> IResult<&str, Cow<'_, str>> {
-    // Is it an empty string ?
-    let (input, v) = opt(peek(tag("\"")))(input)?;
-    if v.is_some() {
-        return Ok((input, Cow::Borrowed("")));
+fn esc(input: &str) -> IResult<'_, Cow<'_, str>> {
+    // Strings without escapes are borrowed, `escaped_transform` would always allocate
+    let end = input.find(['"', '\\', '\r', '\n']).unwrap_or(input.len());
+    if !input[end..].starts_with('\\') {
+        return Ok((&input[end..], Cow::Borrowed(&input[..end])));
     }
 
     escaped_transform(
@@ -86,169 +311,240 @@ fn esc(input: &str) -> IResult<&str, Cow<'_, str>> {
             value("\"", tag("\"")),
             value("\n", tag("\n")),
             value("\n", tag("\r\n")),
-            value("\r", tag("\r")),
+            value("\n", tag("\r")),
+            value("\r", tag("r")),
         )),
     )(input)
     .map(|(s, r)| (s, Cow::Owned(r)))
 }
 
-fn get_key(s: &str) -> IResult<&str, &str> {
+pub(crate) fn get_key(s: &str) -> IResult<'_, Key<'_>> {
     let (s, _) = multispace0(s)?;
-    let (s, key) = take_till1(|c| c == ' ' || c == '=')(s)?;
+    let (s, key) = expecting(
+        Expected::Key,
+        alt((
+            map(get_bracketed_key, Key::Quoted),
+            map(get_identifier, |k| Key::Ident(Cow::Borrowed(k))),
+        )),
+    )(s)?;
     let (s, _) = multispace0(s)?;
     Ok((s, key))
 }
 
-pub fn take_eov(s: &str) -> IResult<&str, &str> {
-    take_till1(|c| c == ',' || c == ' ' || c == '}' || c == '\n')(s)
+pub(crate) fn get_identifier(s: &str) -> IResult<'_, &str> {
+    recognize(pair(
+        alt((alpha1, tag("_"))),
+        many0_count(alt((alphanumeric1, tag("_")))),
+    ))(s)
+}
+
+/// Parses a key in the form of `["x-default"]`
+fn get_bracketed_key(s: &str) -> IResult<'_, Cow<'_, str>> {
+    delimited(
+        pair(tag("["), multispace0),
+        cut(string_literal),
+        cut(pair(multispace0, tag("]"))),
+    )(s)
+}
+
+pub fn take_eov(s: &str) -> IResult<'_, &str> {
+    take_till1(|c: char| c == ',' || c == '}' || c.is_whitespace())(s)
 }
 
-fn quote(s: &str) -> IResult<&str, &str> {
-    recognize(tuple((multispace0, tag("\""), multispace0)))(s)
+/// A double quoted string without any of the surrounding whitespace
+pub(crate) fn string_literal(s: &str) -> IResult<'_, Cow<'_, str>> {
+    expecting(Expected::String, delimited(tag("\""), esc, tag("\"")))(s)
 }
 
-fn equals(s: &str) -> IResult<&str, &str> {
+fn equals(s: &str) -> IResult<'_, &str> {
     recognize(tuple((multispace0, tag("="), multispace0)))(s)
 }
-fn comma(s: &str) -> IResult<&str, &str> {
+fn comma(s: &str) -> IResult<'_, &str> {
     recognize(tuple((multispace0, tag(","), multispace0)))(s)
 }
 
-fn open(s: &str) -> IResult<&str, &str> {
+fn open(s: &str) -> IResult<'_, &str> {
     recognize(tuple((multispace0, tag("{"), multispace0)))(s)
 }
 
-fn close(s: &str) -> IResult<&str, &str> {
+fn close(s: &str) -> IResult<'_, &str> {
     recognize(tuple((multispace0, tag("}"), multispace0)))(s)
 }
 
-pub fn get_value(s: &str) -> IResult<&str, Value> {
-    alt((
-        map(get_vec, Value::from),
-        map(get_struct, Value::from),
-        #[cfg(feature = "namedlist")]
-        map(get_namedlist, Value::from),
-        map(get_string, Value::from),
-        map(get_num, Value::from),
-        map(get_float, Value::from),
-        map(get_bool, Value::from),
-        map(get_unit, Value::from),
-    ))(s)
+/// Parses a value with the default options, only needed by the [`Cst`](crate::cst::Cst)
+#[cfg(feature = "composer")]
+pub fn get_value(s: &str) -> IResult<'_, Value<'_>> {
+    get_value_with(s, ParseOptions::default())
 }
 
-fn get_string(s: &str) -> IResult<&str, std::borrow::Cow<'_, str>> {
-    let (s, _) = quote(s)?;
-    let (s, text) = esc(s)?;
-    let (s, _) = quote(s)?;
-
-    Ok((s, text))
+fn get_value_with(s: &str, options: ParseOptions) -> IResult<'_, Value<'_>> {
+    let (s, _) = multispace0(s)?;
+    if options.preserve_numbers {
+        if let Ok((rest, number)) = get_number(s) {
+            return Ok((rest, Value::Number(number)));
+        }
+    }
+    expecting(
+        Expected::Value,
+        alt((
+            |s| get_table(s, options),
+            map(get_string, |s| decode_string(s, options)),
+            map(get_zstr, Value::Zstr),
+            map(get_num, Value::from),
+            map(get_float, Value::from),
+            map(get_bool, Value::from),
+        )),
+    )(s)
 }
 
-fn get_num(s: &str) -> IResult<&str, i64> {
+fn get_string(s: &str) -> IResult<'_, Cow<'_, str>> {
     let (s, _) = multispace0(s)?;
-    let (s, num) = take_eov(s)?;
+    let (s, text) = string_literal(s)?;
     let (s, _) = multispace0(s)?;
-    Ok((
-        s,
-        num.parse::<i64>().map_err(|_| {
-            nom::Err::Error(ParseError::from_error_kind(
-                "Failed to parse as float",
-                ErrorKind::AlphaNumeric,
-            ))
-        })?,
-    ))
+
+    Ok((s, text))
 }
 
-fn get_float(s: &str) -> IResult<&str, f64> {
-    let (s, _) = multispace0(s)?;
-    let (s, float) = take_eov(s)?;
+/// A localized string like `ZSTR "$$$/AgDevelop/Presets/Vivid=Vivid"`
+fn get_zstr(s: &str) -> IResult<'_, Cow<'_, str>> {
+    let (s, _) = tag("ZSTR")(s)?;
+    let (s, _) = multispace1(s)?;
+    get_string(s)
+}
+
+/// Decodes the strings holding an agpref or a list if `options` asks for it
+fn decode_string(s: Cow<'_, str>, options: ParseOptions) -> Value<'_> {
+    if options.decode_embedded {
+        if let Ok(agpref) = Agpref::parse_with(&s, options.strict(true)) {
+            return Value::Embedded(Box::new(agpref.into_static()));
+        }
+    }
+    #[cfg(feature = "namedlist")]
+    if options.decode_namedlists {
+        if let Some(namedlist) = decode_namedlist(&s) {
+            return Value::NamedList(namedlist);
+        }
+    }
+    Value::String(s)
+}
+
+fn get_num(s: &str) -> IResult<'_, i64> {
     let (s, _) = multispace0(s)?;
-    Ok((
-        s,
-        float.parse::<f64>().map_err(|_| {
-            nom::Err::Error(ParseError::from_error_kind(
-                "Failed to parse as float",
-                ErrorKind::AlphaNumeric,
-            ))
-        })?,
-    ))
+    let (rest, num) = take_eov(s)?;
+    let (rest, _) = multispace0(rest)?;
+    match num.parse::<i64>() {
+        Ok(num) => Ok((rest, num)),
+        Err(_) => Err(nom::Err::Error(Error::expected(s, Expected::Value))),
+    }
+}
+
+fn get_float(s: &str) -> IResult<'_, f64> {
+    map(get_number, |n| n.as_f64())(s)
 }
 
-fn get_bool(s: &str) -> IResult<&str, bool> {
+/// Parses a decimal number keeping its spelling
+fn get_number(s: &str) -> IResult<'_, Number<'_>> {
     let (s, _) = multispace0(s)?;
-    let (s, text) = alphanumeric1(s)?;
+    let (rest, lexeme) = take_eov(s)?;
+    let (rest, _) = multispace0(rest)?;
+    match Number::parse(lexeme) {
+        Some(number) => Ok((rest, number)),
+        None => Err(nom::Err::Error(Error::expected(s, Expected::Value))),
+    }
+}
+
+fn get_bool(s: &str) -> IResult<'_, bool> {
     let (s, _) = multispace0(s)?;
+    let (rest, text) = alphanumeric1(s)?;
+    let (rest, _) = multispace0(rest)?;
     match text {
-        "true" => Ok((s, true)),
-        "false" => Ok((s, false)),
-        _ => Err(nom::Err::Error(ParseError::from_error_kind(
-            "Failed to parse as bool",
-            ErrorKind::AlphaNumeric,
-        ))),
+        "true" => Ok((rest, true)),
+        "false" => Ok((rest, false)),
+        _ => Err(nom::Err::Error(Error::expected(s, Expected::Value))),
     }
 }
 
-fn get_unit(s: &str) -> IResult<&str, ()> {
-    let (s, _) = multispace0(s)?;
-    let (s, _) = open(s)?;
-    let (s, _) = multispace0(s)?;
-    let (s, _) = close(s)?;
-    let (s, _) = multispace0(s)?;
-    Ok((s, ()))
+/// Whether the entries of a table after the opening `{` are the entries of a struct
+pub(crate) fn starts_struct(s: &str) -> bool {
+    peek(alt((tag("["), recognize(pair(get_identifier, equals)))))(s).is_ok()
 }
 
-fn get_vec(s: &str) -> IResult<&str, Vec<Value<'_>>> {
+/// Parses a `{ ... }` table which is either a list of values or a struct
+fn get_table(s: &str, options: ParseOptions) -> IResult<'_, Value<'_>> {
     let (s, _) = open(s)?;
-    let (s, v) = separated_list0(comma, get_value)(s)?;
-    let (s, _) = opt(comma)(s)?;
-    let (s, _) = close(s)?;
-    Ok((s, v))
+    if let Ok((s, _)) = close(s) {
+        // Lightroom writes empty lists and empty structs the same way
+        return Ok((s, Value::Values(Vec::new())));
+    }
+    if starts_struct(s) {
+        map(|s| get_struct(s, options), Value::Struct)(s)
+    } else {
+        map(|s| get_vec(s, options), Value::Values)(s)
+    }
 }
 
-pub fn get_key_value(s: &str) -> IResult<&str, (&str, Value<'_>)> {
+/// Parses the `,` or `}` following an entry of a table, returns whether more entries follow
+fn get_separator(s: &str) -> IResult<'_, bool> {
+    let (s, more) = cut(alt((value(true, comma), value(false, close))))(s)?;
+    if more {
+        // A trailing comma
+        if let Ok((s, _)) = close(s) {
+            return Ok((s, false));
+        }
+    }
+    Ok((s, more))
+}
+
+/// Parses the entries of a list after the opening `{`
+fn get_vec(mut s: &str, options: ParseOptions) -> IResult<'_, Vec<Value<'_>>> {
+    let mut values = Vec::new();
+    loop {
+        let index = values.len();
+        let (rest, value) = cut(|s| get_value_with(s, options))(s)
+            .map_err(|e| e.map(|e| e.in_key(index.to_string())))?;
+        values.push(value);
+        let (rest, more) = get_separator(rest)?;
+        s = rest;
+        if !more {
+            return Ok((s, values));
+        }
+    }
+}
+
+pub fn get_key_value(s: &str, options: ParseOptions) -> IResult<'_, (Key<'_>, Value<'_>)> {
     let (s, k) = get_key(s)?;
     let (s, _) = equals(s)?;
-    let (s, v) = get_value(s)?;
+    let (s, v) =
+        get_value_with(s, options).map_err(|e| e.map(|e| e.in_key(k.clone().into_inner())))?;
     Ok((s, (k, v)))
 }
 
-fn get_struct(s: &str) -> IResult<&str, IndexMap<Cow<'_, str>, Value<'_>>> {
-    let (s, _) = open(s)?;
-    let (s, v) = separated_list0(comma, get_key_value)(s)?;
-    let (s, _) = opt(comma)(s)?;
-    let (s, _) = close(s)?;
-    Ok((
-        s,
-        v.into_iter().map(|v| (Cow::Borrowed(v.0), v.1)).collect(),
-    ))
+/// Parses the entries of a struct after the opening `{`
+fn get_struct(mut s: &str, options: ParseOptions) -> IResult<'_, IndexMap<Key<'_>, Value<'_>>> {
+    let mut values = IndexMap::new();
+    loop {
+        let (rest, (key, value)) = cut(|s| get_key_value(s, options))(s)?;
+        values.insert(key, value);
+        let (rest, more) = get_separator(rest)?;
+        s = rest;
+        if !more {
+            return Ok((s, values));
+        }
+    }
 }
 
+/// Decodes a list assignment like `recentLibraries = { ... }`
 #[cfg(feature = "namedlist")]
-fn get_namedlist<'v>(s: &'v str) -> IResult<&'v str, Value<'v>> {
-    let (s, _) = quote(s)?;
-    let (s, text) = esc(s)?;
-    let (s, _) = quote(s)?;
-
-    use std::borrow::Borrow;
-    let (ts, kv) = get_key_value(text.borrow()).map_err(|_| {
-        nom::Err::Error(ParseError::from_error_kind(
-            "Failed to parse as named list",
-            ErrorKind::AlphaNumeric,
-        ))
-    })?;
-    if !ts.is_empty() {
-        return Err(nom::Err::Error(ParseError::from_error_kind(
-            "Failed to parse as named list",
-            ErrorKind::AlphaNumeric,
-        )));
-    }
-
-    if let (name, Value::Values(v)) = kv {
-        Ok((s, (name, v).into()))
-    } else {
-        Err(nom::Err::Error(ParseError::from_error_kind(
-            "Failed to parse as named list",
-            ErrorKind::AlphaNumeric,
-        )))
+fn decode_namedlist(s: &str) -> Option<NamedList<'static>> {
+    let agpref = Agpref::parse_with(s, ParseOptions::new().strict(true)).ok()?;
+    match agpref.values {
+        Value::Values(values) => Some(NamedList::from((
+            agpref.name.into_owned(),
+            values
+                .into_iter()
+                .map(Value::into_static)
+                .collect::<Vec<_>>(),
+        ))),
+        _ => None,
     }
 }
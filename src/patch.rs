@@ -0,0 +1,259 @@
+//! Patches modeled on JSON Patch (RFC 6902)
+//!
+//! A [`Patch`] is a list of operations on paths in the syntax of [`Value::pointer`]. It is
+//! applied atomically, if any operation fails the value is left as it was. Serialized it looks
+//! like a JSON patch, `[{ "op": "replace", "path": "Exposure2012", "value": 0.35 }]`.
+use crate::errors::Errors;
+use crate::pointer::join_path;
+use crate::types::{Agpref, Value};
+
+/// A single operation of a [`Patch`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "op", rename_all = "lowercase")
+)]
+pub enum Operation<'v> {
+    /// Adds a struct entry, replacing an existing one, or inserts a list element. `-` as the
+    /// last segment appends to a list.
+    Add {
+        path: String,
+        #[cfg_attr(feature = "serde", serde(borrow))]
+        value: Value<'v>,
+    },
+    /// Removes an existing value
+    Remove { path: String },
+    /// Replaces an existing value
+    Replace {
+        path: String,
+        #[cfg_attr(feature = "serde", serde(borrow))]
+        value: Value<'v>,
+    },
+    /// Removes the value at `from` and adds it at `path`
+    Move { from: String, path: String },
+    /// Adds a copy of the value at `from` at `path`
+    Copy { from: String, path: String },
+    /// Fails the patch unless the value at `path` equals `value`
+    Test {
+        path: String,
+        #[cfg_attr(feature = "serde", serde(borrow))]
+        value: Value<'v>,
+    },
+}
+
+impl<'v> Operation<'v> {
+    fn apply(&self, target: &mut Value<'v>) -> Result<(), Errors> {
+        let not_found = |path: &str| Errors::NotFound(path.to_string());
+        match self {
+            Operation::Add { path, value } => target.add_path(path, value.clone()),
+            Operation::Remove { path } => match target.remove_path(path) {
+                Some(_) => Ok(()),
+                None => Err(not_found(path)),
+            },
+            Operation::Replace { path, value } => {
+                *target.pointer_mut(path).ok_or_else(|| not_found(path))? = value.clone();
+                Ok(())
+            }
+            Operation::Move { from, path } => {
+                if path.starts_with(&format!("{}/", from)) {
+                    return Err(Errors::Other(format!(
+                        "Cannot move `{}` into itself at `{}`",
+                        from, path
+                    )));
+                }
+                let value = target.remove_path(from).ok_or_else(|| not_found(from))?;
+                target.add_path(path, value)
+            }
+            Operation::Copy { from, path } => {
+                let value = target.pointer(from).ok_or_else(|| not_found(from))?.clone();
+                target.add_path(path, value)
+            }
+            Operation::Test { path, value } => {
+                match target.pointer(path).ok_or_else(|| not_found(path))? == value {
+                    true => Ok(()),
+                    false => Err(Errors::TestFailed(path.clone())),
+                }
+            }
+        }
+    }
+}
+
+/// A list of operations applied in order
+///
+/// ```
+/// use agprefs::{Agpref, Patch, Value};
+/// let mut x = Agpref::parse("s = { Exposure2012 = 1.05, GrainAmount = 24 }").unwrap();
+/// let patch = Patch::new()
+///     .test("Exposure2012", 1.05)
+///     .replace("Exposure2012", 0.35)
+///     .remove("GrainAmount");
+/// x.apply(&patch).unwrap();
+/// assert_eq!(x, Agpref::parse("s = { Exposure2012 = 0.35 }").unwrap());
+///
+/// // The test fails now, leaving the value as it is
+/// assert!(x.apply(&patch).is_err());
+/// assert_eq!(x["Exposure2012"], Value::Float(0.35));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct Patch<'v> {
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub operations: Vec<Operation<'v>>,
+}
+
+impl<'v> Patch<'v> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    pub fn push(&mut self, operation: Operation<'v>) {
+        self.operations.push(operation);
+    }
+
+    pub fn add(mut self, path: impl Into<String>, value: impl Into<Value<'v>>) -> Self {
+        self.push(Operation::Add {
+            path: path.into(),
+            value: value.into(),
+        });
+        self
+    }
+
+    pub fn remove(mut self, path: impl Into<String>) -> Self {
+        self.push(Operation::Remove { path: path.into() });
+        self
+    }
+
+    pub fn replace(mut self, path: impl Into<String>, value: impl Into<Value<'v>>) -> Self {
+        self.push(Operation::Replace {
+            path: path.into(),
+            value: value.into(),
+        });
+        self
+    }
+
+    pub fn move_to(mut self, from: impl Into<String>, path: impl Into<String>) -> Self {
+        self.push(Operation::Move {
+            from: from.into(),
+            path: path.into(),
+        });
+        self
+    }
+
+    pub fn copy_to(mut self, from: impl Into<String>, path: impl Into<String>) -> Self {
+        self.push(Operation::Copy {
+            from: from.into(),
+            path: path.into(),
+        });
+        self
+    }
+
+    pub fn test(mut self, path: impl Into<String>, value: impl Into<Value<'v>>) -> Self {
+        self.push(Operation::Test {
+            path: path.into(),
+            value: value.into(),
+        });
+        self
+    }
+
+    /// Applies the operations in order. If one fails the value is left unchanged.
+    pub fn apply(&self, target: &mut Value<'v>) -> Result<(), Errors> {
+        let mut patched = target.clone();
+        for operation in &self.operations {
+            operation.apply(&mut patched)?;
+        }
+        *target = patched;
+        Ok(())
+    }
+
+    /// A patch turning `a` into `b`
+    ///
+    /// Struct entries are removed, added or patched one by one and lists element by element.
+    /// Anything else which differs is replaced as a whole.
+    pub fn diff(a: &Value<'v>, b: &Value<'v>) -> Self {
+        let mut patch = Self::new();
+        patch.diff_values("", a, b);
+        patch
+    }
+
+    fn diff_values(&mut self, path: &str, a: &Value<'v>, b: &Value<'v>) {
+        if a == b {
+            return;
+        }
+        match (a, b) {
+            (Value::Struct(x), Value::Struct(y)) => {
+                for key in x.keys().filter(|key| !y.contains_key(*key)) {
+                    self.push(Operation::Remove {
+                        path: join_path(path, key.as_str()),
+                    });
+                }
+                for (key, value) in y {
+                    let path = join_path(path, key.as_str());
+                    match x.get(key) {
+                        Some(old) => self.diff_values(&path, old, value),
+                        None => self.push(Operation::Add {
+                            path,
+                            value: value.clone(),
+                        }),
+                    }
+                }
+            }
+            (Value::Values(x), Value::Values(y)) if !x.is_empty() && !y.is_empty() => {
+                for (i, (old, value)) in x.iter().zip(y).enumerate() {
+                    self.diff_values(&join_path(path, &i.to_string()), old, value);
+                }
+                for (i, value) in y.iter().enumerate().skip(x.len()) {
+                    self.push(Operation::Add {
+                        path: join_path(path, &i.to_string()),
+                        value: value.clone(),
+                    });
+                }
+                // From the end so the indices stay valid
+                for i in (y.len()..x.len()).rev() {
+                    self.push(Operation::Remove {
+                        path: join_path(path, &i.to_string()),
+                    });
+                }
+            }
+            _ => self.push(Operation::Replace {
+                path: path.to_string(),
+                value: b.clone(),
+            }),
+        }
+    }
+}
+
+impl<'v> IntoIterator for Patch<'v> {
+    type Item = Operation<'v>;
+    type IntoIter = std::vec::IntoIter<Operation<'v>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.operations.into_iter()
+    }
+}
+
+impl<'v> FromIterator<Operation<'v>> for Patch<'v> {
+    fn from_iter<I: IntoIterator<Item = Operation<'v>>>(iter: I) -> Self {
+        Self {
+            operations: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<'a> Agpref<'a> {
+    /// Applies the patch to the values of this agpref, see [`Patch::apply`]
+    pub fn apply(&mut self, patch: &Patch<'a>) -> Result<(), Errors> {
+        patch.apply(&mut self.values)
+    }
+}
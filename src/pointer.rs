@@ -0,0 +1,357 @@
+//! Path based access to nested values
+//!
+//! A path is a list of segments separated by `/`, like `Look/Parameters/ToneCurvePV2012/3`.
+//! Segments are struct keys or list indices. Keys containing a `/` can either be escaped like in
+//! a JSON pointer (`~1` for `/` and `~0` for `~`) or written in the bracketed form Lightroom uses
+//! for them, `["/Users/me/Catalog.lrcat"]/catalogType`. A bracketed segment is always a key,
+//! even if it is a number. Embedded agprefs are looked into as if they were the value they hold.
+use crate::errors::Errors;
+use crate::types::{Agpref, Key, Value};
+use std::borrow::Cow;
+use std::ops::{Index, IndexMut};
+
+/// A single segment of a path
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Segment {
+    text: String,
+    /// Written as `["key"]`
+    quoted: bool,
+}
+
+impl Segment {
+    fn index(&self) -> Option<usize> {
+        match self.quoted {
+            true => None,
+            false => self.text.parse().ok(),
+        }
+    }
+
+    fn to_key<'k>(&self) -> Key<'k> {
+        match self.quoted {
+            true => Key::Quoted(Cow::Owned(self.text.clone())),
+            false => Key::Ident(Cow::Owned(self.text.clone())),
+        }
+    }
+}
+
+fn split_path(path: &str) -> Result<Vec<Segment>, Errors> {
+    let invalid = || Errors::Other(format!("Invalid path `{}`", path));
+    let mut rest = path.strip_prefix('/').unwrap_or(path);
+    let mut segments = Vec::new();
+    if rest.is_empty() {
+        return Ok(segments);
+    }
+    loop {
+        if let Some(quoted) = rest.strip_prefix("[\"") {
+            let mut text = String::new();
+            let mut chars = quoted.char_indices();
+            let end = loop {
+                match chars.next() {
+                    Some((_, '\\')) => text.extend(chars.next().map(|(_, c)| c)),
+                    Some((i, '"')) => break i,
+                    Some((_, c)) => text.push(c),
+                    None => return Err(invalid()),
+                }
+            };
+            rest = quoted[end + 1..].strip_prefix(']').ok_or_else(invalid)?;
+            segments.push(Segment { text, quoted: true });
+        } else {
+            let end = rest.find('/').unwrap_or(rest.len());
+            let text = rest[..end].replace("~1", "/").replace("~0", "~");
+            segments.push(Segment {
+                text,
+                quoted: false,
+            });
+            rest = &rest[end..];
+        }
+        match rest.strip_prefix('/') {
+            Some(r) => rest = r,
+            None if rest.is_empty() => return Ok(segments),
+            None => return Err(invalid()),
+        }
+    }
+}
+
+/// Appends a key or index to a path, escaping it so it reads back as a single segment
+pub(crate) fn join_path(path: &str, segment: &str) -> String {
+    let segment = segment.replace('~', "~0").replace('/', "~1");
+    match path.is_empty() {
+        true => segment,
+        false => format!("{}/{}", path, segment),
+    }
+}
+
+fn child<'a, 'v>(value: &'a Value<'v>, segment: &Segment) -> Option<&'a Value<'v>> {
+    match value {
+        Value::Struct(s) => s.get(segment.text.as_str()),
+        Value::Values(v) => v.get(segment.index()?),
+        Value::Embedded(e) => child(&e.values, segment),
+        #[cfg(feature = "namedlist")]
+        Value::NamedList(n) => n.values.get(segment.index()?),
+        _ => None,
+    }
+}
+
+fn child_mut<'a, 'v>(value: &'a mut Value<'v>, segment: &Segment) -> Option<&'a mut Value<'v>> {
+    match value {
+        Value::Struct(s) => s.get_mut(segment.text.as_str()),
+        Value::Values(v) => v.get_mut(segment.index()?),
+        Value::Embedded(e) => child_mut(&mut e.values, segment),
+        #[cfg(feature = "namedlist")]
+        Value::NamedList(n) => n.values.get_mut(segment.index()?),
+        _ => None,
+    }
+}
+
+/// Whether the value can be turned into a struct without losing anything
+fn is_empty_table(value: &Value) -> bool {
+    match value {
+        Value::Unit => true,
+        // Lightroom writes empty structs as empty lists
+        Value::Values(v) => v.is_empty(),
+        _ => false,
+    }
+}
+
+/// Like [`child_mut`] but creates a struct for a missing key
+fn child_or_insert<'a, 'v>(
+    value: &'a mut Value<'v>,
+    segment: &Segment,
+) -> Option<&'a mut Value<'v>> {
+    if segment.index().is_none() && is_empty_table(value) {
+        *value = Value::Struct(Default::default());
+    }
+    match value {
+        Value::Struct(s) => Some(
+            s.entry(segment.to_key())
+                .or_insert_with(|| Value::Struct(Default::default())),
+        ),
+        Value::Embedded(e) => child_or_insert(&mut e.values, segment),
+        _ => child_mut(value, segment),
+    }
+}
+
+impl<'v> Value<'v> {
+    /// Returns the value at the given path
+    ///
+    /// ```
+    /// use agprefs::{Agpref, Value};
+    /// let x = Agpref::parse("s = { Look = { Name = \"Adobe Color\", Curve = { 0, 16 } } }").unwrap();
+    /// assert_eq!(x.pointer("Look/Name"), Some(&Value::from("Adobe Color")));
+    /// assert_eq!(x.pointer("Look/Curve/1"), Some(&Value::Int(16)));
+    /// assert_eq!(x.pointer("Look/Amount"), None);
+    /// ```
+    pub fn pointer(&self, path: &str) -> Option<&Value<'v>> {
+        split_path(path)
+            .ok()?
+            .iter()
+            .try_fold(self, |value, segment| child(value, segment))
+    }
+
+    /// Returns the value at the given path mutably
+    pub fn pointer_mut(&mut self, path: &str) -> Option<&mut Value<'v>> {
+        split_path(path)
+            .ok()?
+            .iter()
+            .try_fold(self, |value, segment| child_mut(value, segment))
+    }
+
+    /// Sets the value at the given path, returning the previous one.
+    ///
+    /// Missing structs along the path are created, as is the last key of the path if it is
+    /// missing. List elements can be replaced or appended by using the length of the list as the
+    /// last index.
+    pub fn set_path(
+        &mut self,
+        path: &str,
+        value: impl Into<Value<'v>>,
+    ) -> Result<Option<Value<'v>>, Errors> {
+        let not_found = || Errors::NotFound(path.to_string());
+        let segments = split_path(path)?;
+        let Some((last, parents)) = segments.split_last() else {
+            return Ok(Some(std::mem::replace(self, value.into())));
+        };
+        let mut parent = self;
+        for segment in parents {
+            parent = child_or_insert(parent, segment).ok_or_else(not_found)?;
+        }
+        if let Value::Embedded(e) = parent {
+            parent = &mut e.values;
+        }
+        if last.index().is_none() && is_empty_table(parent) {
+            *parent = Value::Struct(Default::default());
+        }
+        match parent {
+            Value::Struct(s) => Ok(s.insert(last.to_key(), value.into())),
+            Value::Values(v) => match last.index().ok_or_else(not_found)? {
+                i if i < v.len() => Ok(Some(std::mem::replace(&mut v[i], value.into()))),
+                i if i == v.len() => {
+                    v.push(value.into());
+                    Ok(None)
+                }
+                _ => Err(not_found()),
+            },
+            #[cfg(feature = "namedlist")]
+            Value::NamedList(n) => match last.index().ok_or_else(not_found)? {
+                i if i < n.len() => Ok(Some(std::mem::replace(&mut n[i], value.into()))),
+                i if i == n.len() => {
+                    n.push(value.into());
+                    Ok(None)
+                }
+                _ => Err(not_found()),
+            },
+            _ => Err(not_found()),
+        }
+    }
+
+    /// Adds a value like the `add` operation of a JSON patch. Unlike [`Value::set_path`] the
+    /// parent has to exist, list elements are inserted and `-` appends to a list.
+    pub(crate) fn add_path(&mut self, path: &str, value: Value<'v>) -> Result<(), Errors> {
+        let not_found = || Errors::NotFound(path.to_string());
+        let segments = split_path(path)?;
+        let Some((last, parents)) = segments.split_last() else {
+            *self = value;
+            return Ok(());
+        };
+        let mut parent = parents
+            .iter()
+            .try_fold(self, |value, segment| child_mut(value, segment))
+            .ok_or_else(not_found)?;
+        if let Value::Embedded(e) = parent {
+            parent = &mut e.values;
+        }
+        let append = !last.quoted && last.text == "-";
+        if is_empty_table(parent) {
+            *parent = match append || last.index() == Some(0) {
+                true => Value::Values(Vec::new()),
+                false => Value::Struct(Default::default()),
+            };
+        }
+        let position = |len: usize| match append {
+            true => Some(len),
+            false => last.index().filter(|i| *i <= len),
+        };
+        match parent {
+            Value::Struct(s) => {
+                s.insert(last.to_key(), value);
+            }
+            Value::Values(v) => v.insert(position(v.len()).ok_or_else(not_found)?, value),
+            #[cfg(feature = "namedlist")]
+            Value::NamedList(n) => {
+                let at = position(n.len()).ok_or_else(not_found)?;
+                n.insert(at, value)
+            }
+            _ => return Err(not_found()),
+        }
+        Ok(())
+    }
+
+    /// Removes the value at the given path, keeping the order of the remaining entries
+    pub fn remove_path(&mut self, path: &str) -> Option<Value<'v>> {
+        let segments = split_path(path).ok()?;
+        let (last, parents) = segments.split_last()?;
+        let mut parent = parents
+            .iter()
+            .try_fold(self, |value, segment| child_mut(value, segment))?;
+        if let Value::Embedded(e) = parent {
+            parent = &mut e.values;
+        }
+        match parent {
+            Value::Struct(s) => s.shift_remove(last.text.as_str()),
+            Value::Values(v) => {
+                let i = last.index().filter(|i| *i < v.len())?;
+                Some(v.remove(i))
+            }
+            #[cfg(feature = "namedlist")]
+            Value::NamedList(n) => {
+                let i = last.index().filter(|i| *i < n.len())?;
+                Some(n.remove(i))
+            }
+            _ => None,
+        }
+    }
+}
+
+static UNIT: Value<'static> = Value::Unit;
+
+impl<'v> Index<&str> for Value<'v> {
+    type Output = Value<'v>;
+
+    /// Returns [`Value::Unit`] if there is no such key so lookups can be chained
+    fn index(&self, key: &str) -> &Self::Output {
+        match self {
+            Value::Struct(s) => s.get(key).unwrap_or(&UNIT),
+            Value::Embedded(e) => &e.values[key],
+            _ => &UNIT,
+        }
+    }
+}
+
+impl<'v> IndexMut<&str> for Value<'v> {
+    /// Inserts [`Value::Unit`] if there is no such key, turning empty values into structs.
+    ///
+    /// Panics if the value is anything else but a struct.
+    fn index_mut(&mut self, key: &str) -> &mut Self::Output {
+        if is_empty_table(self) {
+            *self = Value::Struct(Default::default());
+        }
+        match self {
+            Value::Struct(s) => s.entry(Key::from(key.to_string())).or_default(),
+            Value::Embedded(e) => &mut e.values[key],
+            _ => panic!(
+                "Cannot index into a value which isn't a struct with `{}`",
+                key
+            ),
+        }
+    }
+}
+
+impl<'v> Index<usize> for Value<'v> {
+    type Output = Value<'v>;
+
+    /// Returns [`Value::Unit`] if the index is out of bounds so lookups can be chained
+    fn index(&self, index: usize) -> &Self::Output {
+        match self {
+            Value::Values(v) => v.get(index).unwrap_or(&UNIT),
+            Value::Embedded(e) => &e.values[index],
+            #[cfg(feature = "namedlist")]
+            Value::NamedList(n) => n.get(index).unwrap_or(&UNIT),
+            _ => &UNIT,
+        }
+    }
+}
+
+impl<'v> IndexMut<usize> for Value<'v> {
+    /// Panics if the value isn't a list or the index is out of bounds
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        match self {
+            Value::Values(v) => &mut v[index],
+            Value::Embedded(e) => &mut e.values[index],
+            #[cfg(feature = "namedlist")]
+            Value::NamedList(n) => &mut n[index],
+            _ => panic!(
+                "Cannot index into a value which isn't a list with `{}`",
+                index
+            ),
+        }
+    }
+}
+
+impl<'a, I> Index<I> for Agpref<'a>
+where
+    Value<'a>: Index<I, Output = Value<'a>>,
+{
+    type Output = Value<'a>;
+    fn index(&self, index: I) -> &Self::Output {
+        &self.values[index]
+    }
+}
+
+impl<'a, I> IndexMut<I> for Agpref<'a>
+where
+    Value<'a>: IndexMut<I, Output = Value<'a>>,
+{
+    fn index_mut(&mut self, index: I) -> &mut Self::Output {
+        &mut self.values[index]
+    }
+}
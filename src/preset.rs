@@ -0,0 +1,230 @@
+//! Develop presets saved as `.lrtemplate` files
+//!
+//! Lightroom Classic saved develop presets as agprefs before it switched to XMP:
+//!
+//! ```text
+//! s = {
+//! \tid = "4C5A35F4-5B7E-4B3A-9F3E-2C6D0F8C1E2A",
+//! \tinternalName = "Warm Matte",
+//! \ttitle = "Warm Matte",
+//! \ttype = "Develop",
+//! \tvalue = {
+//! \t\tsettings = {
+//! \t\t\tExposure2012 = 0.15,
+//! \t\t},
+//! \t\tuuid = "9D5B2C31-7B4A-4F0E-8A6C-1F2E3D4C5B6A",
+//! \t},
+//! \tversion = 0,
+//! }
+//! ```
+//!
+//! The presets which come with Lightroom have localized titles like
+//! `title = ZSTR "$$$/AgCameraRawNamedSettings/SaveNamedDialog/Vivid=Vivid"`, which are read as
+//! [`Value::Zstr`].
+use crate::develop::DevelopSettings;
+use crate::errors::Errors;
+use crate::parser::ParseOptions;
+use crate::types::{Agpref, HashMap, Key, Value};
+
+const SETTINGS: &str = "settings";
+
+/// A develop preset, keeping everything it was read with
+///
+/// ```
+/// use agprefs::DevelopPreset;
+/// let input = "s = {\n\tid = \"A\",\n\ttitle = ZSTR \"$$$/Presets/Vivid=Vivid\",\n\
+///     \ttype = \"Develop\",\n\tvalue = {\n\t\tsettings = {\n\t\t\tVibrance = 25,\n\t\t},\n\
+///     \t\tuuid = \"B\",\n\t},\n\tversion = 0,\n}\n";
+/// let mut preset = DevelopPreset::parse(input).unwrap();
+/// assert_eq!(preset.title(), Some("Vivid"));
+/// assert_eq!(preset.title_key(), Some("$$$/Presets/Vivid"));
+/// assert_eq!(preset.develop_settings().unwrap().basic.vibrance, Some(25));
+/// # #[cfg(feature = "composer")]
+/// assert_eq!(preset.to_str().unwrap(), input);
+///
+/// preset.set_title("More Vivid");
+/// # #[cfg(feature = "composer")]
+/// assert!(preset.to_str().unwrap().contains("\ttitle = \"More Vivid\",\n"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct DevelopPreset<'a> {
+    preset: Agpref<'a>,
+}
+
+impl<'a> DevelopPreset<'a> {
+    /// An empty preset with the given `id` and `title`, which is also the `uuid` of its value
+    pub fn new(id: impl Into<String>, title: impl Into<String>) -> Self {
+        let id = id.into();
+        let title = title.into();
+        let value: HashMap<Key, Value> = [
+            (Key::from(SETTINGS), Value::Struct(HashMap::new())),
+            (Key::from("uuid"), Value::from(id.clone())),
+        ]
+        .into_iter()
+        .collect();
+        let preset: HashMap<Key, Value> = [
+            ("id", Value::from(id)),
+            ("internalName", Value::from(title.clone())),
+            ("title", Value::from(title)),
+            ("type", Value::from("Develop")),
+            ("value", Value::Struct(value)),
+            ("version", Value::Int(0)),
+        ]
+        .into_iter()
+        .map(|(k, v)| (Key::from(k), v))
+        .collect();
+        Self {
+            preset: Agpref {
+                values: Value::Struct(preset),
+                ..Agpref::with_name("s")
+            },
+        }
+    }
+
+    /// Parses a preset, keeping its formatting and the spelling of its numbers
+    pub fn parse(input: &'a str) -> Result<Self, Errors> {
+        let options = ParseOptions::new()
+            .detect_style(true)
+            .preserve_numbers(true);
+        Self::from_agpref(Agpref::parse_with(input, options)?)
+    }
+
+    /// Wraps a parsed preset, which must be a struct with the settings in `value`
+    pub fn from_agpref(preset: Agpref<'a>) -> Result<Self, Errors> {
+        let value = preset
+            .get_struct()
+            .ok_or_else(|| Errors::Other("The preset must be a struct".into()))?
+            .get("value");
+        match value.and_then(Value::get_struct) {
+            Some(value) if value.contains_key(SETTINGS) => Ok(Self { preset }),
+            _ => Err(Errors::NotFound("value/settings".into())),
+        }
+    }
+
+    pub fn agpref(&self) -> &Agpref<'a> {
+        &self.preset
+    }
+
+    pub fn into_agpref(self) -> Agpref<'a> {
+        self.preset
+    }
+
+    fn get(&self, key: &str) -> Option<&Value<'a>> {
+        self.preset.get_struct()?.get(key)
+    }
+
+    fn set(&mut self, key: &str, value: Value<'a>) {
+        if let Some(s) = self.preset.get_mut_struct() {
+            s.insert(key.to_string().into(), value);
+        }
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.get("id")?.get_string()
+    }
+
+    /// The `uuid` of the value of the preset
+    pub fn uuid(&self) -> Option<&str> {
+        self.get("value")?.get_struct()?.get("uuid")?.get_string()
+    }
+
+    pub fn internal_name(&self) -> Option<&str> {
+        self.get("internalName")?.get_string()
+    }
+
+    /// The `type` of the preset, `Develop` for develop presets
+    pub fn kind(&self) -> Option<&str> {
+        self.get("type")?.get_string()
+    }
+
+    pub fn version(&self) -> Option<i64> {
+        self.get("version")?.get_int()
+    }
+
+    /// The title shown in Lightroom, the English text of a localized title
+    pub fn title(&self) -> Option<&str> {
+        self.get("title")?.as_text()
+    }
+
+    /// The translation key of a localized title, like `$$$/AgDevelop/Presets/Vivid`
+    pub fn title_key(&self) -> Option<&str> {
+        Some(self.get("title")?.get_zstr()?.split_once('=')?.0)
+    }
+
+    /// Replaces the title with a title which isn't localized
+    pub fn set_title(&mut self, title: impl Into<String>) {
+        self.set("title", Value::from(title.into()));
+    }
+
+    /// The settings as they were read
+    pub fn settings(&self) -> &Value<'a> {
+        &self
+            .get("value")
+            .and_then(|v| v.get_struct())
+            .expect("The preset has a value")[SETTINGS]
+    }
+
+    pub fn settings_mut(&mut self) -> &mut Value<'a> {
+        let value = self
+            .preset
+            .get_mut_struct()
+            .and_then(|s| s.get_mut("value"));
+        let value = value
+            .and_then(Value::get_mut_struct)
+            .expect("The preset has a value");
+        &mut value[SETTINGS]
+    }
+
+    /// The settings as [`DevelopSettings`]
+    pub fn develop_settings(&self) -> Result<DevelopSettings<'a>, Errors> {
+        DevelopSettings::from_value(self.settings().clone())
+    }
+
+    /// Replaces the settings, writing them sorted like Lightroom does. Settings which didn't
+    /// change keep the spelling they were read with, like `Exposure2012 = 0.20`.
+    pub fn set_develop_settings(&mut self, settings: &DevelopSettings<'a>) {
+        let mut value = settings.to_value();
+        if let (Some(new), Some(old)) = (value.get_mut_struct(), self.settings().get_struct()) {
+            for (key, setting) in new.iter_mut() {
+                match old.get(key) {
+                    Some(original) if original == setting => *setting = original.clone(),
+                    _ => {}
+                }
+            }
+        }
+        *self.settings_mut() = value;
+    }
+}
+
+impl<'a> TryFrom<Agpref<'a>> for DevelopPreset<'a> {
+    type Error = Errors;
+
+    fn try_from(preset: Agpref<'a>) -> Result<Self, Errors> {
+        Self::from_agpref(preset)
+    }
+}
+
+impl<'a> From<DevelopPreset<'a>> for Agpref<'a> {
+    fn from(preset: DevelopPreset<'a>) -> Self {
+        preset.preset
+    }
+}
+
+#[cfg(feature = "composer")]
+impl DevelopPreset<'_> {
+    /// Writes the preset back in the formatting it was parsed with, or the way Lightroom
+    /// writes presets if it was created with [`DevelopPreset::new`]
+    pub fn to_str(&self) -> Result<String, Errors> {
+        self.preset.to_str_with(&self.style())
+    }
+
+    pub fn write<W: std::io::Write>(&self, w: W) -> Result<(), Errors> {
+        self.preset.write_with(w, &self.style())
+    }
+
+    fn style(&self) -> crate::ComposeOptions {
+        self.preset
+            .style
+            .unwrap_or_else(crate::ComposeOptions::preferences)
+    }
+}
@@ -0,0 +1,684 @@
+//! Schemas describing the expected shape of values
+//!
+//! A [`Schema`] describes the type of a value along with the range of numbers, the allowed values
+//! of enums and the fields of structs. [`Schema::validate`] returns every [`Violation`] with its
+//! path. [`Schema::develop`] is a schema for the develop settings of Lightroom Classic, other
+//! schemas can be built in code or deserialized, for example from JSON:
+//!
+//! ```
+//! # #[cfg(feature = "serde")] {
+//! use agprefs::{Agpref, Schema};
+//! let schema: Schema = serde_json::from_str(r#"{
+//!     "type": "struct",
+//!     "fields": {
+//!         "Exposure2012": { "type": "number", "min": -5, "max": 5 },
+//!         "LensProfileSetup": { "type": "enum", "values": ["LensDefaults", "Auto", "Custom"] }
+//!     },
+//!     "required": ["Exposure2012"]
+//! }"#).unwrap();
+//! let x = Agpref::parse(r#"s = { Exposure2012 = 7, LensProfileSetup = "Manual" }"#).unwrap();
+//! let violations = schema.validate(&x);
+//! assert_eq!(violations[0].to_string(), "Exposure2012: 7 is out of range [-5, 5]");
+//! assert_eq!(violations[1].path, "LensProfileSetup");
+//! # }
+//! ```
+use crate::diff::Inline;
+use crate::errors::describe;
+use crate::pointer::join_path;
+use crate::types::{HashMap, Value};
+use std::borrow::Cow;
+use std::fmt;
+
+/// The expected shape of a value
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", rename_all = "lowercase")
+)]
+pub enum Schema {
+    /// Any value
+    Any,
+    Bool,
+    /// An integer, a float with a whole value isn't one
+    Integer {
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "Option::is_none")
+        )]
+        min: Option<i64>,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "Option::is_none")
+        )]
+        max: Option<i64>,
+    },
+    /// An integer or a float
+    Number {
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "Option::is_none")
+        )]
+        min: Option<f64>,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "Option::is_none")
+        )]
+        max: Option<f64>,
+    },
+    String,
+    /// One of the given values
+    Enum {
+        #[cfg_attr(feature = "serde", serde(deserialize_with = "static_values"))]
+        values: Vec<Value<'static>>,
+    },
+    /// A list whose elements all match `items`
+    List {
+        items: Box<Schema>,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "Option::is_none")
+        )]
+        min_len: Option<usize>,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "Option::is_none")
+        )]
+        max_len: Option<usize>,
+    },
+    Struct(StructSchema),
+}
+
+/// The fields of a [`Schema::Struct`]
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StructSchema {
+    /// The known keys, which may be missing unless they are `required`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub fields: HashMap<String, Schema>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub required: Vec<String>,
+    /// Whether keys which aren't in `fields` are violations
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub closed: bool,
+}
+
+#[cfg(feature = "serde")]
+fn static_values<'de, D>(deserializer: D) -> Result<Vec<Value<'static>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let values: Vec<Value<'de>> = serde::Deserialize::deserialize(deserializer)?;
+    Ok(values.into_iter().map(Value::into_static).collect())
+}
+
+impl StructSchema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn field(mut self, key: impl Into<String>, schema: Schema) -> Self {
+        self.fields.insert(key.into(), schema);
+        self
+    }
+
+    pub fn required(mut self, key: impl Into<String>, schema: Schema) -> Self {
+        let key = key.into();
+        self.required.push(key.clone());
+        self.field(key, schema)
+    }
+
+    pub fn closed(mut self, closed: bool) -> Self {
+        self.closed = closed;
+        self
+    }
+}
+
+impl From<StructSchema> for Schema {
+    fn from(s: StructSchema) -> Self {
+        Schema::Struct(s)
+    }
+}
+
+/// Something wrong with a value
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(tag = "problem", rename_all = "snake_case")
+)]
+pub enum Problem {
+    /// A required key is missing
+    Missing,
+    /// A key which isn't a field of a closed struct
+    Unexpected,
+    /// The value has the wrong type
+    Type {
+        expected: &'static str,
+        found: String,
+    },
+    /// A number outside of its range
+    OutOfRange {
+        value: f64,
+        min: Option<f64>,
+        max: Option<f64>,
+    },
+    /// A value which isn't one of the values of an enum
+    NotAllowed { value: String, allowed: Vec<String> },
+    /// A list with too few or too many elements
+    Length {
+        len: usize,
+        min: Option<usize>,
+        max: Option<usize>,
+    },
+}
+
+/// A value which doesn't match the schema
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Violation {
+    /// The path of the value in the syntax of [`Value::pointer`]
+    pub path: String,
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub problem: Problem,
+}
+
+/// Writes a range like `[-5, 5]`, `[0, ..]` or `[.., 100]`
+fn write_range<T: fmt::Display>(
+    f: &mut fmt::Formatter<'_>,
+    min: &Option<T>,
+    max: &Option<T>,
+) -> fmt::Result {
+    match min {
+        Some(min) => write!(f, "[{}, ", min)?,
+        None => write!(f, "[.., ")?,
+    }
+    match max {
+        Some(max) => write!(f, "{}]", max),
+        None => write!(f, "..]"),
+    }
+}
+
+/// The value with localized strings replaced by their text
+fn plain<'a>(value: &'a Value<'a>) -> Cow<'a, Value<'a>> {
+    match value {
+        Value::Zstr(_) => Cow::Owned(Value::from(value.as_text().unwrap_or_default())),
+        value => Cow::Borrowed(value),
+    }
+}
+
+impl fmt::Display for Violation {
+    /// Like `Exposure2012: 7 is out of range [-5, 5]`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = match self.path.as_str() {
+            "" => "/",
+            path => path,
+        };
+        write!(f, "{}: ", path)?;
+        match &self.problem {
+            Problem::Missing => write!(f, "missing"),
+            Problem::Unexpected => write!(f, "unexpected key"),
+            Problem::Type { expected, found } => {
+                write!(f, "expected {}, found {}", expected, found)
+            }
+            Problem::OutOfRange { value, min, max } => {
+                write!(f, "{} is out of range ", value)?;
+                write_range(f, min, max)
+            }
+            Problem::NotAllowed { value, allowed } => {
+                write!(f, "{} is not one of {}", value, allowed.join(", "))
+            }
+            Problem::Length { len, min, max } => {
+                write!(f, "{} elements are not in ", len)?;
+                write_range(f, min, max)
+            }
+        }
+    }
+}
+
+impl Schema {
+    pub fn integer(min: i64, max: i64) -> Self {
+        Schema::Integer {
+            min: Some(min),
+            max: Some(max),
+        }
+    }
+
+    pub fn number(min: f64, max: f64) -> Self {
+        Schema::Number {
+            min: Some(min),
+            max: Some(max),
+        }
+    }
+
+    pub fn one_of<'v>(values: impl IntoIterator<Item = impl Into<Value<'v>>>) -> Self {
+        Schema::Enum {
+            values: values.into_iter().map(|v| v.into().into_static()).collect(),
+        }
+    }
+
+    pub fn list(items: Schema) -> Self {
+        Schema::List {
+            items: Box::new(items),
+            min_len: None,
+            max_len: None,
+        }
+    }
+
+    /// Returns every violation of the schema in `value`, nothing if it matches
+    pub fn validate(&self, value: &Value) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        self.check("", value, &mut violations);
+        violations
+    }
+
+    /// Whether `value` matches the schema
+    pub fn is_valid(&self, value: &Value) -> bool {
+        self.validate(value).is_empty()
+    }
+
+    fn check(&self, path: &str, value: &Value, violations: &mut Vec<Violation>) {
+        let mut violation = |problem| {
+            violations.push(Violation {
+                path: path.to_string(),
+                problem,
+            })
+        };
+        let wrong_type = |expected| Problem::Type {
+            expected,
+            found: describe(value),
+        };
+        if let Value::Embedded(e) = value {
+            return self.check(path, &e.values, violations);
+        }
+        match self {
+            Schema::Any => {}
+            Schema::Bool if value.get_bool().is_none() => violation(wrong_type("bool")),
+            Schema::Bool => {}
+            Schema::Integer { min, max } => match value.get_int() {
+                Some(i) if min.is_some_and(|min| i < min) || max.is_some_and(|max| i > max) => {
+                    violation(Problem::OutOfRange {
+                        value: i as f64,
+                        min: min.map(|min| min as f64),
+                        max: max.map(|max| max as f64),
+                    })
+                }
+                Some(_) => {}
+                None => violation(wrong_type("integer")),
+            },
+            Schema::Number { min, max } => match value.as_f64() {
+                Some(n) if min.is_some_and(|min| n < min) || max.is_some_and(|max| n > max) => {
+                    violation(Problem::OutOfRange {
+                        value: n,
+                        min: *min,
+                        max: *max,
+                    })
+                }
+                Some(_) => {}
+                None => violation(wrong_type("number")),
+            },
+            Schema::String if value.as_text().is_none() => violation(wrong_type("string")),
+            Schema::String => {}
+            // Localized strings match by their text
+            Schema::Enum { values } if !values.iter().any(|v| *v == *plain(value)) => {
+                violation(Problem::NotAllowed {
+                    value: describe(value),
+                    allowed: values.iter().map(|v| Inline(v).to_string()).collect(),
+                })
+            }
+            Schema::Enum { .. } => {}
+            Schema::List {
+                items,
+                min_len,
+                max_len,
+            } => {
+                let elements = match value {
+                    Value::Values(v) => v.as_slice(),
+                    #[cfg(feature = "namedlist")]
+                    Value::NamedList(n) => n.values.as_slice(),
+                    Value::Unit => &[],
+                    Value::Struct(s) if s.is_empty() => &[],
+                    _ => return violation(wrong_type("list")),
+                };
+                let len = elements.len();
+                if min_len.is_some_and(|min| len < min) || max_len.is_some_and(|max| len > max) {
+                    violation(Problem::Length {
+                        len,
+                        min: *min_len,
+                        max: *max_len,
+                    });
+                }
+                for (i, element) in elements.iter().enumerate() {
+                    items.check(&join_path(path, &i.to_string()), element, violations);
+                }
+            }
+            Schema::Struct(schema) => {
+                let empty = HashMap::new();
+                let entries = match value {
+                    Value::Struct(s) => s,
+                    Value::Unit => &empty,
+                    Value::Values(v) if v.is_empty() => &empty,
+                    _ => return violation(wrong_type("struct")),
+                };
+                for key in &schema.required {
+                    if !entries.contains_key(key.as_str()) {
+                        violations.push(Violation {
+                            path: join_path(path, key),
+                            problem: Problem::Missing,
+                        });
+                    }
+                }
+                for (key, value) in entries {
+                    let path = join_path(path, key.as_str());
+                    match schema.fields.get(key.as_str()) {
+                        Some(field) => field.check(&path, value, violations),
+                        None if schema.closed => violations.push(Violation {
+                            path,
+                            problem: Problem::Unexpected,
+                        }),
+                        None => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// A schema for the develop settings of Lightroom Classic like [`DevelopSettings`] reads
+    ///
+    /// It covers the types and ranges of the sliders, the values of enums like `ProcessVersion`
+    /// and `LensProfileSetup` and the shape of the `Look`, whose `Parameters` are develop
+    /// settings themselves. Other keys are allowed. The ranges are the ones of raw files, so
+    /// `Temperature` is in kelvin.
+    ///
+    /// [`DevelopSettings`]: crate::DevelopSettings
+    pub fn develop() -> Self {
+        let mut schema = StructSchema::new();
+        let mut fields = |keys: &[&str], field: Schema| {
+            for key in keys {
+                schema.fields.insert(key.to_string(), field.clone());
+            }
+        };
+
+        fields(
+            &["ProcessVersion"],
+            Schema::one_of(["5.0", "5.7", "6.7", "10.0", "11.0", "15.4"]),
+        );
+        fields(
+            &["WhiteBalance"],
+            Schema::one_of([
+                "As Shot",
+                "Auto",
+                "Custom",
+                "Daylight",
+                "Cloudy",
+                "Shade",
+                "Tungsten",
+                "Fluorescent",
+                "Flash",
+            ]),
+        );
+        fields(
+            &["LensProfileSetup"],
+            Schema::one_of(["LensDefaults", "Auto", "Custom"]),
+        );
+        fields(
+            &[
+                "CameraProfile",
+                "CameraProfileDigest",
+                "ToneCurveName2012",
+                "LensProfileName",
+                "LensProfileFilename",
+                "LensProfileDigest",
+            ],
+            Schema::String,
+        );
+        fields(
+            &[
+                "ConvertToGrayscale",
+                "LensProfileIsEmbedded",
+                "OverrideLookVignette",
+            ],
+            Schema::Bool,
+        );
+
+        // Basic
+        fields(
+            &["Temperature", "CustomTemperature"],
+            Schema::number(2000.0, 50000.0),
+        );
+        fields(&["Tint", "CustomTint"], Schema::number(-150.0, 150.0));
+        fields(&["Exposure2012"], Schema::number(-5.0, 5.0));
+        fields(&["Exposure"], Schema::number(-4.0, 4.0));
+        fields(
+            &[
+                "Contrast2012",
+                "Highlights2012",
+                "Shadows2012",
+                "Whites2012",
+                "Blacks2012",
+                "Texture",
+                "Clarity2012",
+                "Dehaze",
+                "Vibrance",
+                "Saturation",
+                "Contrast",
+                "Clarity",
+            ],
+            Schema::integer(-100, 100),
+        );
+        fields(&["Brightness"], Schema::integer(-150, 150));
+        fields(
+            &["Shadows", "HighlightRecovery", "FillLight"],
+            Schema::integer(0, 100),
+        );
+
+        // Tone curve
+        fields(
+            &[
+                "ToneCurvePV2012",
+                "ToneCurvePV2012Red",
+                "ToneCurvePV2012Green",
+                "ToneCurvePV2012Blue",
+                "ExtendedToneCurvePV2012",
+                "ExtendedToneCurvePV2012Red",
+                "ExtendedToneCurvePV2012Green",
+                "ExtendedToneCurvePV2012Blue",
+            ],
+            Schema::List {
+                items: Box::new(Schema::integer(0, 255)),
+                min_len: Some(4),
+                max_len: None,
+            },
+        );
+        fields(
+            &[
+                "ParametricShadows",
+                "ParametricDarks",
+                "ParametricLights",
+                "ParametricHighlights",
+            ],
+            Schema::integer(-100, 100),
+        );
+        fields(
+            &[
+                "ParametricShadowSplit",
+                "ParametricMidtoneSplit",
+                "ParametricHighlightSplit",
+            ],
+            Schema::integer(0, 100),
+        );
+        fields(&["CurveRefineSaturation"], Schema::integer(0, 200));
+
+        // HSL and B&W
+        let colors = [
+            "Red", "Orange", "Yellow", "Green", "Aqua", "Blue", "Purple", "Magenta",
+        ];
+        for prefix in [
+            "HueAdjustment",
+            "SaturationAdjustment",
+            "LuminanceAdjustment",
+            "GrayMixer",
+        ] {
+            for color in colors {
+                fields(
+                    &[&format!("{}{}", prefix, color)],
+                    Schema::integer(-100, 100),
+                );
+            }
+        }
+
+        // Color grading and split toning
+        fields(
+            &[
+                "ColorGradeGlobalHue",
+                "ColorGradeMidtoneHue",
+                "ColorGradeShadowHue",
+                "ColorGradeHighlightHue",
+                "SplitToningShadowHue",
+                "SplitToningHighlightHue",
+            ],
+            Schema::integer(0, 359),
+        );
+        fields(
+            &[
+                "ColorGradeBlending",
+                "ColorGradeGlobalSat",
+                "ColorGradeMidtoneSat",
+                "ColorGradeShadowSat",
+                "ColorGradeHighlightSat",
+                "SplitToningShadowSaturation",
+                "SplitToningHighlightSaturation",
+            ],
+            Schema::integer(0, 100),
+        );
+        fields(
+            &[
+                "ColorGradeGlobalLum",
+                "ColorGradeMidtoneLum",
+                "ColorGradeShadowLum",
+                "ColorGradeHighlightLum",
+                "SplitToningBalance",
+            ],
+            Schema::integer(-100, 100),
+        );
+
+        // Detail
+        fields(&["Sharpness"], Schema::integer(0, 150));
+        fields(&["SharpenRadius"], Schema::number(0.5, 3.0));
+        fields(
+            &[
+                "SharpenDetail",
+                "SharpenEdgeMasking",
+                "LuminanceSmoothing",
+                "LuminanceNoiseReductionDetail",
+                "LuminanceNoiseReductionContrast",
+                "ColorNoiseReduction",
+                "ColorNoiseReductionDetail",
+                "ColorNoiseReductionSmoothness",
+            ],
+            Schema::integer(0, 100),
+        );
+
+        // Lens corrections
+        fields(
+            &["LensProfileEnable", "AutoLateralCA"],
+            Schema::integer(0, 1),
+        );
+        fields(
+            &["LensProfileDistortionScale", "LensProfileVignettingScale"],
+            Schema::integer(0, 200),
+        );
+        fields(
+            &["LensManualDistortionAmount", "VignetteAmount"],
+            Schema::integer(-100, 100),
+        );
+        fields(&["VignetteMidpoint"], Schema::integer(0, 100));
+        fields(
+            &["DefringePurpleAmount", "DefringeGreenAmount"],
+            Schema::integer(0, 20),
+        );
+        fields(
+            &[
+                "DefringePurpleHueLo",
+                "DefringePurpleHueHi",
+                "DefringeGreenHueLo",
+                "DefringeGreenHueHi",
+            ],
+            Schema::integer(0, 100),
+        );
+
+        // Effects
+        fields(
+            &[
+                "PostCropVignetteAmount",
+                "PostCropVignetteRoundness",
+                "PostCropVignetteHighlightContrast",
+            ],
+            Schema::integer(-100, 100),
+        );
+        fields(
+            &[
+                "PostCropVignetteMidpoint",
+                "PostCropVignetteFeather",
+                "GrainAmount",
+                "GrainSize",
+                "GrainFrequency",
+            ],
+            Schema::integer(0, 100),
+        );
+        fields(&["PostCropVignetteStyle"], Schema::integer(1, 3));
+        fields(
+            &["GrainSeed"],
+            Schema::Integer {
+                min: None,
+                max: None,
+            },
+        );
+
+        // Calibration
+        fields(
+            &[
+                "ShadowTint",
+                "RedHue",
+                "RedSaturation",
+                "GreenHue",
+                "GreenSaturation",
+                "BlueHue",
+                "BlueSaturation",
+            ],
+            Schema::integer(-100, 100),
+        );
+
+        // Transform
+        fields(
+            &[
+                "PerspectiveVertical",
+                "PerspectiveHorizontal",
+                "PerspectiveAspect",
+                "PerspectiveX",
+                "PerspectiveY",
+            ],
+            Schema::number(-100.0, 100.0),
+        );
+        fields(&["PerspectiveRotate"], Schema::number(-10.0, 10.0));
+        fields(&["PerspectiveScale"], Schema::number(50.0, 150.0));
+        fields(&["UprightPreview"], Schema::Bool);
+
+        let look = StructSchema::new()
+            .required("Name", Schema::String)
+            .field("Amount", Schema::number(0.0, 2.0))
+            .field("UUID", Schema::String)
+            .field("SupportsAmount", Schema::Bool)
+            .field("SupportsMonochrome", Schema::Bool)
+            .field("SupportsOutputReferred", Schema::Bool)
+            .field("Copyright", Schema::Any)
+            .field("Group", Schema::Any)
+            // The settings the profile applies
+            .field("Parameters", schema.clone().into());
+        schema.fields.insert("Look".to_string(), look.into());
+        schema.into()
+    }
+}
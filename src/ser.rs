@@ -0,0 +1,626 @@
+//! Serialize Rust types straight into agprefs text
+//!
+//! Structs and maps are written as `{ Key = value }` tables and sequences and tuples as lists.
+//! Unit variants are written as strings and all other variants as a table with a single
+//! `Variant = value` entry. There is no nil, so `None` struct fields and map values are left
+//! out and a `None` anywhere else is an error, like NaN and infinite floats which agprefs can't
+//! spell either.
+//!
+//! ```
+//! #[derive(serde::Serialize)]
+//! struct Settings {
+//!     #[serde(rename = "Exposure2012")]
+//!     exposure: f64,
+//!     #[serde(rename = "ToneCurvePV2012")]
+//!     curve: Vec<u8>,
+//! }
+//! let settings = Settings { exposure: 1.0, curve: vec![0, 0, 255, 255] };
+//! assert_eq!(
+//!     agprefs::to_string("s", &settings).unwrap(),
+//!     "s = { Exposure2012 = 1.0,\nToneCurvePV2012 = { 0,\n0,\n255,\n255 } }\n"
+//! );
+//! ```
+use crate::composer::{compose_entries, escape_string_with, format_float};
+use crate::errors::Errors;
+use crate::style::ComposeOptions;
+use crate::types::{Key, ZSTR_TAG};
+use serde::ser::{self, Impossible, Serialize};
+
+/// Serializes `value` as the assignment `name = value` formatted like Lightroom's develop settings
+pub fn to_string<T: ?Sized + Serialize>(name: &str, value: &T) -> Result<String, Errors> {
+    to_string_with(name, value, &ComposeOptions::default())
+}
+
+/// Serializes `value` as the assignment `name = value` with the given formatting options
+pub fn to_string_with<T: ?Sized + Serialize>(
+    name: &str,
+    value: &T,
+    options: &ComposeOptions,
+) -> Result<String, Errors> {
+    let mut serializer = Serializer::new(*options);
+    value.serialize(&mut serializer)?;
+    if serializer.skip {
+        return Err(none_error());
+    }
+    let mut output = format!("{} = {}", name, serializer.into_string());
+    if options.final_newline {
+        output.push_str(options.newline.as_str());
+    }
+    Ok(output)
+}
+
+/// A serializer writing a single value as agprefs text
+#[derive(Debug)]
+pub struct Serializer {
+    output: String,
+    options: ComposeOptions,
+    /// How many tables deep the value is
+    depth: usize,
+    is_table: bool,
+    /// The value was `None` and should be left out of structs
+    skip: bool,
+}
+
+impl Serializer {
+    pub fn new(options: ComposeOptions) -> Self {
+        Self {
+            output: String::new(),
+            options,
+            depth: 0,
+            is_table: false,
+            skip: false,
+        }
+    }
+
+    /// The text of the serialized value
+    pub fn into_string(self) -> String {
+        self.output
+    }
+
+    fn nested(&self, depth: usize) -> Self {
+        Self {
+            depth,
+            ..Self::new(self.options)
+        }
+    }
+
+    fn write(&mut self, text: &str) -> Result<(), Errors> {
+        self.output.push_str(text);
+        Ok(())
+    }
+
+    /// Writes a table at the depth of this serializer from its already serialized entries
+    fn write_table(
+        &mut self,
+        entries: Vec<(Option<Key<'static>>, Serializer)>,
+    ) -> Result<(), Errors> {
+        let entries = entries
+            .into_iter()
+            .filter(|(key, value)| key.is_none() || !value.skip)
+            .collect::<Vec<_>>();
+        let layout = entries
+            .iter()
+            .map(|(key, value)| (key.as_ref(), value.is_table))
+            .collect::<Vec<_>>();
+        let mut buf = Vec::new();
+        compose_entries(
+            &layout,
+            |i, w| cookie_factory::combinator::string(&entries[i].1.output)(w),
+            &self.options,
+            self.depth,
+            cookie_factory::WriteContext::from(&mut buf),
+        )?;
+        self.output.push_str(&String::from_utf8(buf)?);
+        self.is_table = true;
+        Ok(())
+    }
+
+    /// Writes `{ variant = value }`
+    fn write_variant<T: ?Sized + Serialize>(
+        &mut self,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Errors> {
+        let mut inner = self.nested(self.depth + 1);
+        value.serialize(&mut inner)?;
+        if inner.skip {
+            return Err(none_error());
+        }
+        self.write_table(vec![(Some(Key::from(variant)), inner)])
+    }
+}
+
+/// A `None` which can't be left out, there is nothing to read it back from
+fn none_error() -> Errors {
+    Errors::Other("`None` can only be a value of a struct or map".into())
+}
+
+fn float_error(v: f64) -> Errors {
+    Errors::Other(format!("{} can't be written as agprefs", v))
+}
+
+impl<'a> ser::Serializer for &'a mut Serializer {
+    type Ok = ();
+    type Error = Errors;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Errors> {
+        self.write(if v { "true" } else { "false" })
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Errors> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Errors> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Errors> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Errors> {
+        self.write(&v.to_string())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), Errors> {
+        self.write(&v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Errors> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Errors> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Errors> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Errors> {
+        self.write(&v.to_string())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), Errors> {
+        self.write(&v.to_string())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Errors> {
+        if !v.is_finite() {
+            return Err(float_error(v.into()));
+        }
+        // Formatting the f32 itself avoids the noise of widening it
+        self.write(&format!("{:?}", v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Errors> {
+        if !v.is_finite() {
+            return Err(float_error(v));
+        }
+        self.write(&format_float(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Errors> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Errors> {
+        let escaped = escape_string_with(v, self.options.newline);
+        self.write(&format!("\"{}\"", escaped))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Errors> {
+        use ser::SerializeSeq;
+        let mut seq = self.serialize_seq(Some(v.len()))?;
+        for byte in v {
+            seq.serialize_element(byte)?;
+        }
+        seq.end()
+    }
+
+    fn serialize_none(self) -> Result<(), Errors> {
+        self.skip = true;
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), Errors> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Errors> {
+        self.write_table(Vec::new())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Errors> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), Errors> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Errors> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Errors> {
+        self.write_variant(variant, value)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Compound<'a>, Errors> {
+        Ok(Compound::new(self, len, None))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Compound<'a>, Errors> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a>, Errors> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a>, Errors> {
+        Ok(Compound::new(self, Some(len), Some(variant)))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Compound<'a>, Errors> {
+        Ok(Compound::new(self, len, None))
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Compound<'a>, Errors> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a>, Errors> {
+        Ok(Compound::new(self, Some(len), Some(variant)))
+    }
+}
+
+/// Collects the entries of a table before writing it all at once
+pub struct Compound<'a> {
+    ser: &'a mut Serializer,
+    entries: Vec<(Option<Key<'static>>, Serializer)>,
+    /// The key passed to `serialize_key` waiting for its value
+    key: Option<Key<'static>>,
+    /// Wrap the table in `{ variant = ... }`
+    variant: Option<&'static str>,
+}
+
+impl<'a> Compound<'a> {
+    fn new(ser: &'a mut Serializer, len: Option<usize>, variant: Option<&'static str>) -> Self {
+        Self {
+            ser,
+            entries: Vec::with_capacity(len.unwrap_or_default()),
+            key: None,
+            variant,
+        }
+    }
+
+    fn push<T: ?Sized + Serialize>(
+        &mut self,
+        key: Option<Key<'static>>,
+        value: &T,
+    ) -> Result<(), Errors> {
+        let depth = self.ser.depth + if self.variant.is_some() { 2 } else { 1 };
+        let mut serializer = self.ser.nested(depth);
+        value.serialize(&mut serializer)?;
+        if key.is_none() && serializer.skip {
+            return Err(none_error());
+        }
+        self.entries.push((key, serializer));
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), Errors> {
+        match self.variant {
+            Some(variant) => {
+                let mut inner = self.ser.nested(self.ser.depth + 1);
+                inner.write_table(self.entries)?;
+                self.ser
+                    .write_table(vec![(Some(Key::from(variant)), inner)])
+            }
+            // A `Value::Zstr` is written as the localized string it is
+            None => match self.entries.as_slice() {
+                [(Some(key), value)] if key.as_str() == ZSTR_TAG && !value.is_table => {
+                    let zstr = format!("ZSTR {}", value.output);
+                    self.ser.write(&zstr)
+                }
+                _ => self.ser.write_table(self.entries),
+            },
+        }
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = Errors;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Errors> {
+        self.push(None, value)
+    }
+
+    fn end(self) -> Result<(), Errors> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = Errors;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Errors> {
+        self.push(None, value)
+    }
+
+    fn end(self) -> Result<(), Errors> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Errors;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Errors> {
+        self.push(None, value)
+    }
+
+    fn end(self) -> Result<(), Errors> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Errors;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Errors> {
+        self.push(None, value)
+    }
+
+    fn end(self) -> Result<(), Errors> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = ();
+    type Error = Errors;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Errors> {
+        self.key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Errors> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Errors::Other("serialize_value called before serialize_key".into()))?;
+        self.push(Some(key), value)
+    }
+
+    fn end(self) -> Result<(), Errors> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Errors;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Errors> {
+        self.push(Some(Key::from(key)), value)
+    }
+
+    fn end(self) -> Result<(), Errors> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Errors;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Errors> {
+        self.push(Some(Key::from(key)), value)
+    }
+
+    fn end(self) -> Result<(), Errors> {
+        self.finish()
+    }
+}
+
+/// Turns map keys into [`Key`]s, only strings and things that display like them are allowed
+struct KeySerializer;
+
+fn key_error() -> Errors {
+    Errors::Other("Keys must be strings".into())
+}
+
+macro_rules! display_key {
+    ($($name:ident: $ty:ty),*) => {
+        $(
+            fn $name(self, v: $ty) -> Result<Key<'static>, Errors> {
+                Ok(Key::from(v.to_string()))
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = Key<'static>;
+    type Error = Errors;
+    type SerializeSeq = Impossible<Key<'static>, Errors>;
+    type SerializeTuple = Impossible<Key<'static>, Errors>;
+    type SerializeTupleStruct = Impossible<Key<'static>, Errors>;
+    type SerializeTupleVariant = Impossible<Key<'static>, Errors>;
+    type SerializeMap = Impossible<Key<'static>, Errors>;
+    type SerializeStruct = Impossible<Key<'static>, Errors>;
+    type SerializeStructVariant = Impossible<Key<'static>, Errors>;
+
+    display_key!(
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_char: char,
+        serialize_str: &str
+    );
+
+    fn serialize_f32(self, _v: f32) -> Result<Key<'static>, Errors> {
+        Err(key_error())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Key<'static>, Errors> {
+        Err(key_error())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Key<'static>, Errors> {
+        Err(key_error())
+    }
+
+    fn serialize_none(self) -> Result<Key<'static>, Errors> {
+        Err(key_error())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Key<'static>, Errors> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Key<'static>, Errors> {
+        Err(key_error())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Key<'static>, Errors> {
+        Err(key_error())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Key<'static>, Errors> {
+        Ok(Key::from(variant))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Key<'static>, Errors> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Key<'static>, Errors> {
+        Err(key_error())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Errors> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Errors> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Errors> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Errors> {
+        Err(key_error())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Errors> {
+        Err(key_error())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Errors> {
+        Err(key_error())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Errors> {
+        Err(key_error())
+    }
+}
@@ -0,0 +1,151 @@
+//! The formatting options of the composer
+//!
+//! These are always available so [`Agpref::style`](crate::Agpref::style) exists with any set of
+//! features, writing requires the `composer` feature.
+
+/// How the entries of a table are laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// Braces and every entry on their own lines
+    ///
+    /// ```text
+    /// {
+    /// \tA = 1,
+    /// \tB = 2,
+    /// }
+    /// ```
+    Multiline,
+    /// Every entry on its own line, with the first and last one next to the braces
+    ///
+    /// ```text
+    /// { A = 1,
+    /// B = 2 }
+    /// ```
+    Compact,
+    /// All the entries on a single line, `{ A = 1, B = 2 }`
+    Inline,
+}
+
+/// What each level of nesting is indented with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    None,
+    Tabs,
+    Spaces(usize),
+}
+
+/// When to write a comma after the last entry of a table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrailingComma {
+    Never,
+    Always,
+    /// Only for tables written with [`Layout::Multiline`]
+    Multiline,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Newline {
+    Lf,
+    CrLf,
+}
+
+impl Newline {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Newline::Lf => "\n",
+            Newline::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Formatting options for the composer
+///
+/// Lightroom writes two different styles which are available as
+/// [`ComposeOptions::preferences`] and [`ComposeOptions::develop_settings`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComposeOptions {
+    pub indent: Indent,
+    pub trailing_comma: TrailingComma,
+    pub layout: Layout,
+    /// Tables with at most this many entries, none of which are tables themselves, are
+    /// written with [`Layout::Inline`] regardless of `layout`
+    pub max_inline_entries: usize,
+    pub newline: Newline,
+    /// Whether to end the output with a newline
+    pub final_newline: bool,
+}
+
+impl Default for ComposeOptions {
+    fn default() -> Self {
+        Self::develop_settings()
+    }
+}
+
+impl ComposeOptions {
+    /// The style of Lightroom's `.agprefs` preference files
+    ///
+    /// ```text
+    /// prefs = {
+    /// \tlibraryToLoad20 = "/Users/me/Pictures/Lightroom/Lightroom Catalog.lrcat",
+    /// \trecentLibraryBehavior20 = "UseMostRecentLibrary",
+    /// }
+    /// ```
+    pub fn preferences() -> Self {
+        Self {
+            indent: Indent::Tabs,
+            trailing_comma: TrailingComma::Multiline,
+            layout: Layout::Multiline,
+            max_inline_entries: 0,
+            newline: Newline::Lf,
+            final_newline: true,
+        }
+    }
+
+    /// The style of the develop settings stored in Lightroom catalogs
+    ///
+    /// ```text
+    /// s = { AutoLateralCA = 1,
+    /// Blacks2012 = -36,
+    /// Whites2012 = -100 }
+    /// ```
+    pub fn develop_settings() -> Self {
+        Self {
+            indent: Indent::None,
+            trailing_comma: TrailingComma::Never,
+            layout: Layout::Compact,
+            max_inline_entries: 0,
+            newline: Newline::Lf,
+            final_newline: true,
+        }
+    }
+
+    pub fn indent(mut self, indent: Indent) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    pub fn trailing_comma(mut self, trailing_comma: TrailingComma) -> Self {
+        self.trailing_comma = trailing_comma;
+        self
+    }
+
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn max_inline_entries(mut self, max_inline_entries: usize) -> Self {
+        self.max_inline_entries = max_inline_entries;
+        self
+    }
+
+    pub fn newline(mut self, newline: Newline) -> Self {
+        self.newline = newline;
+        self
+    }
+
+    pub fn final_newline(mut self, final_newline: bool) -> Self {
+        self.final_newline = final_newline;
+        self
+    }
+}
@@ -0,0 +1,268 @@
+//! Point curves like `ToneCurvePV2012` or `ExtendedToneCurvePV2012Red`
+//!
+//! Lightroom stores a curve as a flat list of `x, y` pairs between 0 and 255 like
+//! `{ 0, 0, 49, 43, 255, 255 }` and draws it with the spline of the DNG SDK, which passes through
+//! every point and keeps the segments between them smooth.
+use crate::errors::Errors;
+use crate::types::Value;
+
+/// A tone curve with at least two points whose inputs strictly increase
+///
+/// ```
+/// use agprefs::{ToneCurve, Value};
+/// let curve = ToneCurve::from_flat(&[0, 0, 128, 160, 255, 255]).unwrap();
+/// assert_eq!(curve.points(), [(0, 0), (128, 160), (255, 255)]);
+/// assert_eq!(curve.evaluate(128.0), 160.0);
+/// assert!(curve.evaluate(64.0) > 64.0);
+/// let lut = curve.lut8();
+/// assert_eq!((lut[0], lut[128], lut[255]), (0, 160, 255));
+/// assert_eq!(Value::from(&curve), Value::from(vec![0i64, 0, 128, 160, 255, 255]));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "Vec<i64>", into = "Vec<i64>")
+)]
+pub struct ToneCurve {
+    points: Vec<(u8, u8)>,
+}
+
+impl Default for ToneCurve {
+    /// The linear curve `{ 0, 0, 255, 255 }`
+    fn default() -> Self {
+        Self {
+            points: vec![(0, 0), (255, 255)],
+        }
+    }
+}
+
+fn invalid(message: impl std::fmt::Display) -> Errors {
+    Errors::Other(format!("Invalid tone curve: {}", message))
+}
+
+impl ToneCurve {
+    /// The linear curve
+    pub fn linear() -> Self {
+        Self::default()
+    }
+
+    /// A curve through `(input, output)` points sorted by their input
+    pub fn new(points: Vec<(u8, u8)>) -> Result<Self, Errors> {
+        if points.len() < 2 {
+            return Err(invalid("it needs at least 2 points"));
+        }
+        if let Some(w) = points.windows(2).find(|w| w[0].0 >= w[1].0) {
+            return Err(invalid(format_args!(
+                "the input of ({}, {}) doesn't come after ({}, {})",
+                w[1].0, w[1].1, w[0].0, w[0].1
+            )));
+        }
+        Ok(Self { points })
+    }
+
+    /// Reads a curve from a flat list of `x, y` pairs
+    pub fn from_flat(values: &[i64]) -> Result<Self, Errors> {
+        if !values.len().is_multiple_of(2) {
+            return Err(invalid("it has an odd number of values"));
+        }
+        let coordinate = |i: usize| {
+            u8::try_from(values[i])
+                .map_err(|_| invalid(format_args!("{} at {} isn't in 0..=255", values[i], i)))
+        };
+        let points = (0..values.len())
+            .step_by(2)
+            .map(|i| Ok((coordinate(i)?, coordinate(i + 1)?)))
+            .collect::<Result<_, Errors>>()?;
+        Self::new(points)
+    }
+
+    /// Reads a curve from a list like `{ 0, 0, 255, 255 }`
+    pub fn from_value(value: &Value) -> Result<Self, Errors> {
+        let values = value
+            .get_values()
+            .ok_or_else(|| invalid("it isn't a list"))?
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                v.get_int()
+                    .ok_or_else(|| invalid(format_args!("the value at {} isn't an integer", i)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::from_flat(&values)
+    }
+
+    /// The curve as the flat list Lightroom stores
+    pub fn to_flat(&self) -> Vec<i64> {
+        self.points
+            .iter()
+            .flat_map(|&(x, y)| [x.into(), y.into()])
+            .collect()
+    }
+
+    pub fn points(&self) -> &[(u8, u8)] {
+        &self.points
+    }
+
+    pub fn into_points(self) -> Vec<(u8, u8)> {
+        self.points
+    }
+
+    /// Whether the curve leaves every value as it is
+    pub fn is_linear(&self) -> bool {
+        self.points.iter().all(|(x, y)| x == y)
+            && self.points[0].0 == 0
+            && self.points.last() == Some(&(255, 255))
+    }
+
+    /// The output for an input between 0 and 255
+    pub fn evaluate(&self, x: f64) -> f64 {
+        Spline::new(&self.points).evaluate(x / 255.0) * 255.0
+    }
+
+    /// The curve sampled at `size` evenly spaced inputs, with inputs and outputs between 0 and 1
+    pub fn lut(&self, size: usize) -> Vec<f64> {
+        let spline = Spline::new(&self.points);
+        let last = size.saturating_sub(1).max(1) as f64;
+        (0..size)
+            .map(|i| spline.evaluate(i as f64 / last))
+            .collect()
+    }
+
+    /// A 256 entry lookup table for 8 bit values
+    pub fn lut8(&self) -> [u8; 256] {
+        let mut lut = [0; 256];
+        for (entry, y) in lut.iter_mut().zip(self.lut(256)) {
+            *entry = (y * 255.0).round() as u8;
+        }
+        lut
+    }
+
+    /// A 65536 entry lookup table for 16 bit values
+    pub fn lut16(&self) -> Vec<u16> {
+        self.lut(65536)
+            .into_iter()
+            .map(|y| (y * 65535.0).round() as u16)
+            .collect()
+    }
+}
+
+/// The spline solver of the DNG SDK (`dng_spline_solver`) Camera Raw and Lightroom use for
+/// point curves, on coordinates between 0 and 1
+struct Spline {
+    x: Vec<f64>,
+    y: Vec<f64>,
+    /// The slope at each point
+    s: Vec<f64>,
+}
+
+impl Spline {
+    fn new(points: &[(u8, u8)]) -> Self {
+        let x: Vec<f64> = points.iter().map(|p| f64::from(p.0) / 255.0).collect();
+        let y: Vec<f64> = points.iter().map(|p| f64::from(p.1) / 255.0).collect();
+        let n = x.len();
+        let mut s = vec![0.0; n];
+
+        // Start with the weighted average of the slopes to the neighbouring points
+        let mut a = x[1] - x[0];
+        let mut b = (y[1] - y[0]) / a;
+        s[0] = b;
+        for j in 2..n {
+            let c = x[j] - x[j - 1];
+            let d = (y[j] - y[j - 1]) / c;
+            s[j - 1] = (b * c + d * a) / (a + c);
+            a = c;
+            b = d;
+        }
+        s[n - 1] = 2.0 * b - s[n - 2];
+        s[0] = 2.0 * s[0] - s[1];
+
+        // Then make the second derivative continuous by solving the tridiagonal system
+        if n > 2 {
+            let mut e = vec![0.0; n];
+            let mut f = vec![0.0; n];
+            let mut g = vec![0.0; n];
+            f[0] = 0.5;
+            e[n - 1] = 0.5;
+            g[0] = 0.75 * (s[0] + s[1]);
+            g[n - 1] = 0.75 * (s[n - 2] + s[n - 1]);
+            for j in 1..n - 1 {
+                let a = (x[j + 1] - x[j - 1]) * 2.0;
+                e[j] = (x[j + 1] - x[j]) / a;
+                f[j] = (x[j] - x[j - 1]) / a;
+                g[j] = 1.5 * s[j];
+            }
+            for j in 1..n {
+                let a = 1.0 - f[j - 1] * e[j];
+                if j != n - 1 {
+                    f[j] /= a;
+                }
+                g[j] = (g[j] - g[j - 1] * e[j]) / a;
+            }
+            for j in (0..n - 1).rev() {
+                g[j] -= f[j] * g[j + 1];
+            }
+            s = g;
+        }
+        Self { x, y, s }
+    }
+
+    /// The curve at `x`, clamped to the range of the image
+    fn evaluate(&self, x: f64) -> f64 {
+        let n = self.x.len();
+        if x <= self.x[0] {
+            return self.y[0];
+        }
+        if x >= self.x[n - 1] {
+            return self.y[n - 1];
+        }
+        let j = self.x.partition_point(|&p| p <= x);
+        if self.x[j - 1] == x {
+            return self.y[j - 1];
+        }
+        let (x0, y0, s0) = (self.x[j - 1], self.y[j - 1], self.s[j - 1]);
+        let (x1, y1, s1) = (self.x[j], self.y[j], self.s[j]);
+        // Cubic Hermite segment between the two points
+        let a = x1 - x0;
+        let t = (x - x0) / a;
+        let z = 1.0 - t;
+        let y =
+            y0 * (z * z * (1.0 + 2.0 * t)) + y1 * (t * t * (1.0 + 2.0 * z)) + s0 * (a * z * z * t)
+                - s1 * (a * t * t * z);
+        y.clamp(0.0, 1.0)
+    }
+}
+
+impl TryFrom<&Value<'_>> for ToneCurve {
+    type Error = Errors;
+
+    fn try_from(value: &Value) -> Result<Self, Errors> {
+        Self::from_value(value)
+    }
+}
+
+impl TryFrom<Vec<i64>> for ToneCurve {
+    type Error = Errors;
+
+    fn try_from(values: Vec<i64>) -> Result<Self, Errors> {
+        Self::from_flat(&values)
+    }
+}
+
+impl From<ToneCurve> for Vec<i64> {
+    fn from(curve: ToneCurve) -> Self {
+        curve.to_flat()
+    }
+}
+
+impl From<&ToneCurve> for Value<'_> {
+    fn from(curve: &ToneCurve) -> Self {
+        Value::Values(curve.to_flat().into_iter().map(Value::Int).collect())
+    }
+}
+
+impl From<ToneCurve> for Value<'_> {
+    fn from(curve: ToneCurve) -> Self {
+        Value::from(&curve)
+    }
+}
@@ -1,12 +1,12 @@
 // use indexmap::IndexMap as HashMap;
 use std::collections::hash_map::RandomState;
-type HashMap<K, V, S = RandomState> = indexmap::IndexMap<K, V, S>;
+pub(crate) type HashMap<K, V, S = RandomState> = indexmap::IndexMap<K, V, S>;
 
 #[cfg(feature = "serde")]
 use serde::*;
 use std::borrow::Cow;
 
-#[derive(Debug, Clone, Default, PartialEq)]
+#[derive(Debug, Clone, Default)]
 pub enum Value<'v> {
     // Core types
     #[default]
@@ -16,7 +16,273 @@ pub enum Value<'v> {
     Bool(bool),
     String(Cow<'v, str>),
     Values(Vec<Value<'v>>),
-    Struct(HashMap<Cow<'v, str>, Value<'v>>),
+    Struct(HashMap<Key<'v>, Value<'v>>),
+    /// A number which keeps the way it was written, only produced when parsing with
+    /// [`ParseOptions::preserve_numbers`](crate::ParseOptions)
+    Number(Number<'v>),
+    /// An agpref nested in a string like `recentLibraries20 = "recentLibraries = {\ ... }"`,
+    /// only produced when parsing with [`ParseOptions::decode_embedded`](crate::ParseOptions)
+    Embedded(Box<Agpref<'v>>),
+    /// A localized string like `title = ZSTR "$$$/AgDevelop/Presets/Vivid=Vivid"`, the
+    /// translation key followed by `=` and the English text
+    Zstr(Cow<'v, str>),
+    /// A list nested in a string like `recentLibraries20 = "recentLibraries = {\ ... }"`
+    #[cfg(feature = "namedlist")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "namedlist")))]
+    NamedList(NamedList<'v>),
+}
+
+impl PartialEq for Value<'_> {
+    /// Numbers compare by value, so `Number("1.0")` equals `Float(1.0)` but not `Int(1)`
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Unit, Value::Unit) => true,
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a == b,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Values(a), Value::Values(b)) => a == b,
+            (Value::Struct(a), Value::Struct(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Embedded(a), Value::Embedded(b)) => a == b,
+            (Value::Zstr(a), Value::Zstr(b)) => a == b,
+            (Value::Number(n), other) | (other, Value::Number(n)) => &n.to_value() == other,
+            #[cfg(feature = "namedlist")]
+            (Value::NamedList(a), Value::NamedList(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// A number along with its original spelling like `1.0`, `1e-05`, `.5` or `-0`
+///
+/// Lightroom treats `1` and `1.0` differently for some keys, so a number is a float if it is
+/// written with a decimal point or an exponent (or doesn't fit in an `i64`).
+#[derive(Debug, Clone, Eq)]
+pub struct Number<'n> {
+    lexeme: Cow<'n, str>,
+}
+
+impl<'n> Number<'n> {
+    /// Checks that `lexeme` is a decimal number, `None` otherwise
+    pub fn parse(lexeme: impl Into<Cow<'n, str>>) -> Option<Self> {
+        let lexeme = lexeme.into();
+        let is_numeric = lexeme
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-'));
+        (is_numeric && lexeme.parse::<f64>().is_ok()).then_some(Self { lexeme })
+    }
+
+    /// The number as it was written
+    pub fn as_str(&self) -> &str {
+        &self.lexeme
+    }
+
+    pub fn is_float(&self) -> bool {
+        self.as_i64().is_none()
+    }
+
+    /// The value of the number if it is an integer
+    pub fn as_i64(&self) -> Option<i64> {
+        self.lexeme.parse().ok()
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        self.lexeme.parse().unwrap_or_default()
+    }
+
+    /// The number as a [`Value::Int`] or [`Value::Float`], dropping the spelling
+    pub fn to_value(&self) -> Value<'static> {
+        match self.as_i64() {
+            Some(i) => Value::Int(i),
+            None => Value::Float(self.as_f64()),
+        }
+    }
+
+    pub fn into_static(self) -> Number<'static> {
+        Number {
+            lexeme: Cow::Owned(self.lexeme.into_owned()),
+        }
+    }
+}
+
+impl PartialEq for Number<'_> {
+    /// Compares the values, `1.0` and `1.00` are equal but `1` and `1.0` aren't
+    fn eq(&self, other: &Self) -> bool {
+        self.to_value() == other.to_value()
+    }
+}
+
+impl std::fmt::Display for Number<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.lexeme)
+    }
+}
+
+/// The key of an entry in a [`Value::Struct`]
+///
+/// Lightroom writes keys which are valid lua identifiers as is (`Exposure2012 = 0`) and
+/// everything else in the bracketed form (`["x-default"] = "Profiles"`).
+///
+/// Both kinds compare and hash by their (unescaped) text so lookups like `get("x-default")`
+/// work regardless of how the key was written.
+#[derive(Debug, Clone, Eq)]
+pub enum Key<'k> {
+    /// A bare identifier key, `Name = ...`
+    Ident(Cow<'k, str>),
+    /// A bracketed string key, `["Name"] = ...`
+    Quoted(Cow<'k, str>),
+}
+
+impl<'k> Key<'k> {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Key::Ident(k) | Key::Quoted(k) => k,
+        }
+    }
+
+    pub fn is_quoted(&self) -> bool {
+        matches!(self, Key::Quoted(_))
+    }
+
+    /// Whether the key has to be written in the bracketed form
+    pub fn needs_brackets(&self) -> bool {
+        self.is_quoted() || !is_identifier(self.as_str())
+    }
+
+    pub fn into_inner(self) -> Cow<'k, str> {
+        match self {
+            Key::Ident(k) | Key::Quoted(k) => k,
+        }
+    }
+
+    pub fn into_static(self) -> Key<'static> {
+        match self {
+            Key::Ident(k) => Key::Ident(Cow::Owned(k.into_owned())),
+            Key::Quoted(k) => Key::Quoted(Cow::Owned(k.into_owned())),
+        }
+    }
+}
+
+/// Checks if the given string is a valid lua identifier
+pub fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl PartialEq for Key<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl PartialEq<str> for Key<'_> {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Key<'_> {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl std::hash::Hash for Key<'_> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        // Has to match the Hash impl of str for the Borrow impl below
+        self.as_str().hash(state)
+    }
+}
+
+impl std::borrow::Borrow<str> for Key<'_> {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl std::ops::Deref for Key<'_> {
+    type Target = str;
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for Key<'_> {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl std::fmt::Display for Key<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl<'k> From<&'k str> for Key<'k> {
+    fn from(k: &'k str) -> Self {
+        Key::Ident(Cow::Borrowed(k))
+    }
+}
+
+impl From<String> for Key<'_> {
+    fn from(k: String) -> Self {
+        Key::Ident(Cow::Owned(k))
+    }
+}
+
+impl<'k> From<Cow<'k, str>> for Key<'k> {
+    fn from(k: Cow<'k, str>) -> Self {
+        Key::Ident(k)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Key<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de: 'k, 'k> Deserialize<'de> for Key<'k> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::*;
+        struct KeyVisitor;
+        impl<'de> Visitor<'de> for KeyVisitor {
+            type Value = Key<'de>;
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a key")
+            }
+            fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E>
+            where
+                E: Error,
+            {
+                Ok(Key::from(v))
+            }
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: Error,
+            {
+                Ok(Key::from(v.to_string()))
+            }
+            fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+            where
+                E: Error,
+            {
+                Ok(Key::from(v))
+            }
+        }
+        deserializer.deserialize_str(KeyVisitor)
+    }
 }
 
 macro_rules! into_getter {
@@ -52,9 +318,14 @@ impl<'v> Value<'v> {
             Value::Values(v) => Value::Values(v.into_iter().map(|v| v.into_static()).collect()),
             Value::Struct(s) => Value::Struct(
                 s.into_iter()
-                    .map(|(k, v)| (Cow::Owned(k.into_owned()), v.into_static()))
+                    .map(|(k, v)| (k.into_static(), v.into_static()))
                     .collect(),
             ),
+            Value::Number(n) => Value::Number(n.into_static()),
+            Value::Embedded(e) => Value::Embedded(Box::new(e.into_static())),
+            Value::Zstr(s) => Value::Zstr(Cow::Owned(s.into_owned())),
+            #[cfg(feature = "namedlist")]
+            Value::NamedList(n) => Value::NamedList(n.into_static()),
         }
     }
 
@@ -65,6 +336,7 @@ impl<'v> Value<'v> {
     pub fn get_int(&self) -> Option<i64> {
         match self {
             Value::Int(i) => Some(*i),
+            Value::Number(n) => n.as_i64(),
             _ => None,
         }
     }
@@ -72,6 +344,7 @@ impl<'v> Value<'v> {
     pub fn get_float(&self) -> Option<f64> {
         match self {
             Value::Float(f) => Some(*f),
+            Value::Number(n) if n.is_float() => Some(n.as_f64()),
             _ => None,
         }
     }
@@ -83,6 +356,40 @@ impl<'v> Value<'v> {
         }
     }
 
+    /// The value of any number, `Exposure2012 = 0` as well as `Exposure2012 = 1.43`
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Int(i) => Some(*i as f64),
+            Value::Float(f) => Some(*f),
+            Value::Number(n) => Some(n.as_f64()),
+            _ => None,
+        }
+    }
+
+    /// The value of an integer or of a whole float which fits in an `i64`
+    pub fn as_i64(&self) -> Option<i64> {
+        const LIMIT: f64 = 9_223_372_036_854_775_808.0;
+        match self {
+            Value::Int(i) => Some(*i),
+            Value::Number(n) => n.as_i64().or_else(|| Value::Float(n.as_f64()).as_i64()),
+            Value::Float(f) if f.fract() == 0.0 && (-LIMIT..LIMIT).contains(f) => Some(*f as i64),
+            _ => None,
+        }
+    }
+
+    /// A bool, or `0` and `1` which Lightroom uses for flags like `LensProfileEnable`
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            Value::Int(_) | Value::Number(_) => match self.as_i64()? {
+                0 => Some(false),
+                1 => Some(true),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
     pub fn get_string(&self) -> Option<&str> {
         match self {
             Value::String(s) => Some(s),
@@ -90,6 +397,23 @@ impl<'v> Value<'v> {
         }
     }
 
+    /// The text of a localized string, translation key included
+    pub fn get_zstr(&self) -> Option<&str> {
+        match self {
+            Value::Zstr(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The text of a plain or localized string, without the translation key of a localized one
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            Value::Zstr(s) => Some(s.split_once('=').map_or(s, |(_, text)| text)),
+            _ => None,
+        }
+    }
+
     pub fn get_values(&self) -> Option<&[Value<'v>]> {
         match self {
             Value::Values(v) => Some(v),
@@ -97,26 +421,49 @@ impl<'v> Value<'v> {
         }
     }
 
-    pub fn get_struct(&self) -> Option<&HashMap<Cow<'v, str>, Value<'v>>> {
+    pub fn get_struct(&self) -> Option<&HashMap<Key<'v>, Value<'v>>> {
         match self {
             Value::Struct(s) => Some(s),
             _ => None,
         }
     }
 
+    pub fn get_embedded(&self) -> Option<&Agpref<'v>> {
+        match self {
+            Value::Embedded(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "namedlist")]
+    pub fn get_namedlist(&self) -> Option<&NamedList<'v>> {
+        match self {
+            Value::NamedList(n) => Some(n),
+            _ => None,
+        }
+    }
+
     into_getter!(into_int, i64, Int);
     into_getter!(into_float, f64, Float);
     into_getter!(into_bool, bool, Bool);
     into_getter!(into_string, Cow<'v, str>, String);
     into_getter!(into_values, Vec<Value<'v>>, Values);
-    into_getter!(into_struct, HashMap<Cow<'v, str>, Value<'v>>, Struct);
+    into_getter!(into_struct, HashMap<Key<'v>, Value<'v>>, Struct);
+    into_getter!(into_embedded, Box<Agpref<'v>>, Embedded);
+    into_getter!(into_zstr, Cow<'v, str>, Zstr);
+    #[cfg(feature = "namedlist")]
+    into_getter!(into_namedlist, NamedList<'v>, NamedList);
 
     mut_getter!(get_mut_int, i64, Int);
     mut_getter!(get_mut_float, f64, Float);
     mut_getter!(get_mut_bool, bool, Bool);
     mut_getter!(get_mut_string, Cow<'v, str>, String);
     mut_getter!(get_mut_values, Vec<Value<'v>>, Values);
-    mut_getter!(get_mut_struct, HashMap<Cow<'v, str>, Value<'v>>, Struct);
+    mut_getter!(get_mut_struct, HashMap<Key<'v>, Value<'v>>, Struct);
+    mut_getter!(get_mut_embedded, Box<Agpref<'v>>, Embedded);
+    mut_getter!(get_mut_zstr, Cow<'v, str>, Zstr);
+    #[cfg(feature = "namedlist")]
+    mut_getter!(get_mut_namedlist, NamedList<'v>, NamedList);
 }
 
 #[cfg(feature = "serde")]
@@ -146,12 +493,23 @@ impl<'v> Serialize for Value<'v> {
                 ss.end()
             }
             Value::Unit => serializer.serialize_unit(),
+            Value::Number(n) => n.to_value().serialize(serializer),
+            Value::Embedded(e) => e.serialize(serializer),
+            Value::Zstr(s) => {
+                let mut ss = serializer.serialize_map(Some(1))?;
+                ss.serialize_entry(ZSTR_TAG, s)?;
+                ss.end()
+            }
             #[cfg(feature = "namedlist")]
-            Value::NamedList(n) => n.serialize(serializer),
+            Value::NamedList(n) => {
+                let mut ss = serializer.serialize_map(Some(1))?;
+                ss.serialize_entry(NAMEDLIST_TAG, n)?;
+                ss.end()
+            }
         }
     }
 }
-#[cfg(all(feature = "serde", feature = "composer"))]
+#[cfg(feature = "serde")]
 impl<'de: 'v, 'v> Deserialize<'de> for Value<'v> {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -188,11 +546,16 @@ impl<'de: 'v, 'v> Deserialize<'de> for Value<'v> {
             {
                 Ok(Value::Bool(v))
             }
+            fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E>
+            where
+                E: Error,
+            {
+                Ok(Value::String(Cow::Borrowed(v)))
+            }
             fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
             where
                 E: Error,
             {
-                // TODO: Try to make this 0 copy
                 Ok(Value::String(Cow::Owned(v.to_string())))
             }
             fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
@@ -217,8 +580,26 @@ impl<'de: 'v, 'v> Deserialize<'de> for Value<'v> {
             {
                 // let mut values = HashMap::with_capacity_and_hasher(1, Default::default());
                 let mut values = HashMap::new();
-                while let Some((key, value)) = visitor.next_entry()? {
-                    values.insert(key, value);
+                while let Some(key) = visitor.next_key::<Key>()? {
+                    if values.is_empty() && key == ZSTR_TAG {
+                        let zstr = match visitor.next_value()? {
+                            Value::String(s) => s,
+                            _ => return Err(Error::custom("a localized string is a string")),
+                        };
+                        if visitor.next_key::<Key>()?.is_some() {
+                            return Err(Error::custom("a localized string has no other entries"));
+                        }
+                        return Ok(Value::Zstr(zstr));
+                    }
+                    #[cfg(feature = "namedlist")]
+                    if values.is_empty() && key == NAMEDLIST_TAG {
+                        let named: NamedList = visitor.next_value()?;
+                        if visitor.next_key::<Key>()?.is_some() {
+                            return Err(Error::custom("a named list has no other entries"));
+                        }
+                        return Ok(Value::NamedList(named));
+                    }
+                    values.insert(key, visitor.next_value()?);
                 }
                 Ok(Value::Struct(values))
             }
@@ -250,7 +631,7 @@ fn test_value() {
     assert_type(Value::Int(69));
     assert_type(Value::Float(42.0));
     assert_type(Value::Bool(true));
-    // assert_type(Value::String("test".to_string()));
+    assert_type(Value::String("test".into()));
     assert_type(Value::Values(vec![Value::Int(69), Value::Float(42.0)]));
     // assert_type(Value::Struct(
     //     vec![("test".to_string(), Value::Int(666))]
@@ -270,6 +651,9 @@ impl std::fmt::Display for Value<'_> {
             Value::Values(v) => write!(f, "{:?}", v),
             Value::Struct(s) => write!(f, "{:?}", s),
             Value::Unit => write!(f, "{{}}"),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Embedded(e) => write!(f, "{:?}", e),
+            Value::Zstr(s) => write!(f, "{}", s),
             #[cfg(feature = "namedlist")]
             Value::NamedList(nl) => write!(f, "{:?}", nl),
         }
@@ -288,6 +672,12 @@ impl From<i64> for Value<'_> {
     }
 }
 
+impl<'v> From<Number<'v>> for Value<'v> {
+    fn from(n: Number<'v>) -> Self {
+        Value::Number(n)
+    }
+}
+
 impl From<f64> for Value<'_> {
     fn from(f: f64) -> Self {
         Value::Float(f)
@@ -319,14 +709,14 @@ impl<'v> From<Cow<'v, str>> for Value<'v> {
 }
 
 #[cfg(feature = "namedlist")]
-impl<'v, T: Into<NamedList<'v>>> From<T> for Value<'v> {
-    fn from(nl: T) -> Self {
-        Value::NamedList(nl.into())
+impl<'v> From<NamedList<'v>> for Value<'v> {
+    fn from(nl: NamedList<'v>) -> Self {
+        Value::NamedList(nl)
     }
 }
 
-impl<'v> From<HashMap<Cow<'v, str>, Value<'v>>> for Value<'v> {
-    fn from(s: HashMap<Cow<'v, str>, Value<'v>>) -> Self {
+impl<'v> From<HashMap<Key<'v>, Value<'v>>> for Value<'v> {
+    fn from(s: HashMap<Key<'v>, Value<'v>>) -> Self {
         Value::Struct(s)
     }
 }
@@ -346,15 +736,15 @@ where
     }
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub(crate) struct Item<'i> {
-    pub name: Cow<'i, str>,
+    pub name: Key<'i>,
     pub value: Value<'i>,
 }
 
 impl<'i, S, V> From<(S, V)> for Item<'i>
 where
-    S: Into<Cow<'i, str>>,
+    S: Into<Key<'i>>,
     V: Into<Value<'i>>,
 {
     fn from(sv: (S, V)) -> Self {
@@ -365,11 +755,22 @@ where
     }
 }
 
-#[derive(Debug, Default, Clone, PartialEq)]
+#[derive(Debug, Default, Clone)]
 /// A named list of key value pairs that can be used to represent a text field of lrcat files
 pub struct Agpref<'a> {
     pub name: Cow<'a, str>,
     pub values: Value<'a>,
+    /// The formatting the agpref was parsed with, used by default when writing it back.
+    ///
+    /// Only recorded when parsing with [`ParseOptions::detect_style`](crate::ParseOptions)
+    pub style: Option<crate::style::ComposeOptions>,
+}
+
+impl PartialEq for Agpref<'_> {
+    /// Compares the name and values, the formatting doesn't matter
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.values == other.values
+    }
 }
 
 #[cfg(feature = "serde")]
@@ -414,7 +815,11 @@ impl<'de: 'a, 'a> Deserialize<'de> for Agpref<'a> {
                 }
                 let name = name.ok_or_else(|| Error::missing_field("values"))?;
                 let values = values.ok_or_else(|| Error::missing_field("values"))?;
-                Ok(Agpref { name, values })
+                Ok(Agpref {
+                    name,
+                    values,
+                    ..Default::default()
+                })
             }
         }
         deserializer.deserialize_struct("Agpref", &["values"], AgprefVisitor)
@@ -431,6 +836,14 @@ impl<'a> Agpref<'a> {
             ..Self::default()
         }
     }
+
+    pub fn into_static(self) -> Agpref<'static> {
+        Agpref {
+            name: Cow::Owned(self.name.into_owned()),
+            values: self.values.into_static(),
+            style: self.style,
+        }
+    }
 }
 
 impl<'a> std::ops::Deref for Agpref<'a> {
@@ -446,17 +859,141 @@ impl<'a> std::ops::DerefMut for Agpref<'a> {
     }
 }
 
+#[derive(Debug, Default, Clone, PartialEq)]
+/// All the top level `name = value` assignments of an agprefs file in order
+pub struct Document<'a> {
+    pub entries: Vec<Agpref<'a>>,
+}
+
+impl<'a> Document<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the first assignment with the given name
+    pub fn find(&self, name: &str) -> Option<&Agpref<'a>> {
+        self.entries.iter().find(|e| e.name == name)
+    }
+
+    /// Returns the first assignment with the given name
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut Agpref<'a>> {
+        self.entries.iter_mut().find(|e| e.name == name)
+    }
+
+    pub fn into_static(self) -> Document<'static> {
+        Document {
+            entries: self.entries.into_iter().map(Agpref::into_static).collect(),
+        }
+    }
+}
+
+impl<'a> std::ops::Deref for Document<'a> {
+    type Target = Vec<Agpref<'a>>;
+    fn deref(&self) -> &Self::Target {
+        &self.entries
+    }
+}
+
+impl<'a> std::ops::DerefMut for Document<'a> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.entries
+    }
+}
+
+impl<'a> From<Vec<Agpref<'a>>> for Document<'a> {
+    fn from(entries: Vec<Agpref<'a>>) -> Self {
+        Self { entries }
+    }
+}
+
+impl<'a> IntoIterator for Document<'a> {
+    type Item = Agpref<'a>;
+    type IntoIter = std::vec::IntoIter<Agpref<'a>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'a> Serialize for Document<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        use serde::ser::*;
+        let mut ss = serializer.serialize_map(Some(self.entries.len()))?;
+        for entry in &self.entries {
+            ss.serialize_entry(&entry.name, &entry.values)?;
+        }
+        ss.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de: 'a, 'a> Deserialize<'de> for Document<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::*;
+        pub struct DocumentVisitor;
+        impl<'de> Visitor<'de> for DocumentVisitor {
+            type Value = Document<'de>;
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a map of agprefs")
+            }
+            fn visit_map<V>(self, mut visitor: V) -> Result<Self::Value, V::Error>
+            where
+                V: MapAccess<'de>,
+            {
+                let mut entries = Vec::new();
+                while let Some((name, values)) = visitor.next_entry()? {
+                    entries.push(Agpref {
+                        name,
+                        values,
+                        ..Default::default()
+                    });
+                }
+                Ok(Document { entries })
+            }
+        }
+        deserializer.deserialize_map(DocumentVisitor)
+    }
+}
+
+/// The only key of the map a [`Value::Zstr`] is serialized as, `{ "$zstr": "$$$/Key=Text" }`, so
+/// it isn't read back as a plain string
+#[cfg(feature = "serde")]
+pub(crate) const ZSTR_TAG: &str = "$zstr";
+
+/// The only key of the map a [`Value::NamedList`] is serialized as, `{ "$namedlist": { "name":
+/// .., "values": [..] } }`, so it isn't mistaken for a struct with the same fields
+#[cfg(all(feature = "namedlist", feature = "serde"))]
+const NAMEDLIST_TAG: &str = "$namedlist";
+
 #[cfg(feature = "namedlist")]
 #[derive(Debug, Clone, PartialEq)]
-#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(docsrs, doc(cfg(feature = "namedlist")))]
 /// Named list of values for parsing agprefs files
 /// Which store the recent lrcat catalogs
 pub struct NamedList<'n> {
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub name: Cow<'n, str>,
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub values: Vec<Value<'n>>,
 }
 
+#[cfg(feature = "namedlist")]
+impl NamedList<'_> {
+    pub fn into_static(self) -> NamedList<'static> {
+        NamedList {
+            name: Cow::Owned(self.name.into_owned()),
+            values: self.values.into_iter().map(Value::into_static).collect(),
+        }
+    }
+}
+
 #[cfg(feature = "namedlist")]
 impl<'n, S, V> From<(S, V)> for NamedList<'n>
 where
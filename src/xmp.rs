@@ -0,0 +1,449 @@
+//! Develop settings as Camera Raw XMP
+//!
+//! Lightroom writes the develop settings of a photo to XMP sidecars in the `crs:` namespace of
+//! Camera Raw. [`to_string`] turns the develop settings struct `s = { ... }` into such a packet
+//! and [`from_str`] reads the settings back into the same shape:
+//!
+//! ```text
+//! <rdf:Description rdf:about=""
+//!     xmlns:crs="http://ns.adobe.com/camera-raw-settings/1.0/"
+//!    crs:Exposure2012="+1.43"
+//!    crs:ConvertToGrayscale="False">
+//!    <crs:ToneCurvePV2012>
+//!     <rdf:Seq>
+//!      <rdf:li>0, 0</rdf:li>
+//!      <rdf:li>255, 255</rdf:li>
+//!     </rdf:Seq>
+//!    </crs:ToneCurvePV2012>
+//! </rdf:Description>
+//! ```
+//!
+//! Scalars are attributes, numbers of settings which can be negative are signed and bools are
+//! `True` or `False`. Lists are `rdf:Seq`s, with the flat points of tone curves written as pairs,
+//! and structs like `Look` are nested `rdf:Description`s, except for translations like
+//! `Group = { ["x-default"] = "Profiles" }` which are `rdf:Alt`s. The types of known settings come
+//! from [`Schema::develop`], so `ProcessVersion="11.0"` is read back as a string and
+//! `Exposure2012="+1.00"` as the integer `1` like Lightroom stores it. Floats of settings without a
+//! known type keep their decimal point, like `1.0`, so they are read back as floats. Localized
+//! strings are written as their text and read back as plain strings. Settings like `Exposure2012` are
+//! padded to the decimals Lightroom writes, `1` is `+1.00`, while more precise values keep all
+//! of their digits.
+//!
+//! ```
+//! use agprefs::{xmp, Agpref};
+//! let x = Agpref::parse("s = { ConvertToGrayscale = false, Exposure2012 = 1.43, Tint = -3 }")
+//!     .unwrap();
+//! let packet = xmp::to_string(&x.values).unwrap();
+//! assert!(packet.contains("   crs:Exposure2012=\"+1.43\"\n"));
+//! assert!(packet.contains("   crs:ConvertToGrayscale=\"False\"\n"));
+//! assert_eq!(xmp::from_str(&packet).unwrap(), x.values);
+//! ```
+use crate::errors::{ConversionError, Errors};
+use crate::schema::{Schema, StructSchema};
+use crate::types::{HashMap, Key, Value};
+use std::borrow::Cow;
+use std::fmt::Write;
+
+const CRS: &str = "http://ns.adobe.com/camera-raw-settings/1.0/";
+const RDF: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const XML: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// Settings Lightroom writes with at least a number of decimals, always signed
+const DECIMALS: &[(&str, usize)] = &[("Exposure", 2), ("Exposure2012", 2), ("SharpenRadius", 1)];
+
+/// Settings which look like numbers but are strings
+const TEXT: &[&str] = &["Version", "CompatibleVersion"];
+
+/// Writes develop settings as an XMP packet the way Lightroom writes sidecars
+pub fn to_string(settings: &Value) -> Result<String, Errors> {
+    let schema = match Schema::develop() {
+        Schema::Struct(schema) => schema,
+        _ => unreachable!("The develop schema is a struct"),
+    };
+    let mut out = String::new();
+    out.push_str(
+        "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\" \
+         x:xmptk=\"Adobe XMP Core 7.0-c000 1.000000, 0000/00/00-00:00:00        \">\n",
+    );
+    let _ = writeln!(out, " <rdf:RDF xmlns:rdf=\"{}\">", RDF);
+    let head = format!("<rdf:Description rdf:about=\"\"\n    xmlns:crs=\"{}\"", CRS);
+    write_description(&mut out, &head, settings, Some(&schema), 2)?;
+    out.push_str(" </rdf:RDF>\n</x:xmpmeta>\n");
+    Ok(out)
+}
+
+/// Reads the develop settings from the first `rdf:Description` with Camera Raw settings
+pub fn from_str(xmp: &str) -> Result<Value<'static>, Errors> {
+    let document = roxmltree::Document::parse(xmp)?;
+    let description = document
+        .descendants()
+        .filter(|n| is(n, RDF, "Description"))
+        .find(|n| {
+            n.attributes().any(|a| a.namespace() == Some(CRS))
+                || n.children().any(|c| c.tag_name().namespace() == Some(CRS))
+        })
+        .ok_or_else(|| Errors::NotFound("rdf:Description with crs: settings".into()))?;
+    let schema = match Schema::develop() {
+        Schema::Struct(schema) => schema,
+        _ => unreachable!("The develop schema is a struct"),
+    };
+    Ok(read_description(description, Some(&schema)))
+}
+
+fn entries<'a, 'v>(value: &'a Value<'v>) -> Option<Vec<(&'a str, &'a Value<'v>)>> {
+    match value {
+        Value::Struct(s) => Some(s.iter().map(|(k, v)| (k.as_str(), v)).collect()),
+        Value::Unit => Some(Vec::new()),
+        Value::Values(v) if v.is_empty() => Some(Vec::new()),
+        Value::Embedded(e) => entries(&e.values),
+        _ => None,
+    }
+}
+
+fn elements<'a, 'v>(value: &'a Value<'v>) -> Option<&'a [Value<'v>]> {
+    match value {
+        Value::Values(v) => Some(v),
+        Value::Embedded(e) => elements(&e.values),
+        #[cfg(feature = "namedlist")]
+        Value::NamedList(n) => Some(&n.values),
+        _ => None,
+    }
+}
+
+fn field<'s>(schema: Option<&'s StructSchema>, key: &str) -> Option<&'s Schema> {
+    schema?.fields.get(key)
+}
+
+fn struct_schema(schema: Option<&Schema>) -> Option<&StructSchema> {
+    match schema? {
+        Schema::Struct(schema) => Some(schema),
+        _ => None,
+    }
+}
+
+fn indent(out: &mut String, depth: usize) {
+    out.push_str(&" ".repeat(depth));
+}
+
+/// Writes a struct as `rdf:Description` with its scalars as attributes, starting with `head`
+fn write_description(
+    out: &mut String,
+    head: &str,
+    value: &Value,
+    schema: Option<&StructSchema>,
+    depth: usize,
+) -> Result<(), ConversionError> {
+    let entries = entries(value).ok_or_else(|| ConversionError::new("struct", value))?;
+    let (attributes, properties): (Vec<_>, Vec<_>) = entries
+        .into_iter()
+        .filter(|(_, v)| !v.is_unit())
+        .partition(|(_, v)| is_scalar(v));
+
+    indent(out, depth);
+    out.push_str(head);
+    for (key, value) in attributes {
+        let text = format_scalar(key, value, field(schema, key)).expect("Only scalars");
+        out.push('\n');
+        indent(out, depth + 1);
+        let _ = write!(out, "crs:{}=\"{}\"", key, escape(&text));
+    }
+    if properties.is_empty() {
+        out.push_str("/>\n");
+        return Ok(());
+    }
+    out.push_str(">\n");
+    for (key, value) in properties {
+        indent(out, depth + 1);
+        let _ = writeln!(out, "<crs:{}>", key);
+        write_property(out, key, value, field(schema, key), depth + 2)
+            .map_err(|e| e.within(key))?;
+        indent(out, depth + 1);
+        let _ = writeln!(out, "</crs:{}>", key);
+    }
+    indent(out, depth);
+    out.push_str("</rdf:Description>\n");
+    Ok(())
+}
+
+/// Writes the contents of the property element of a value which isn't a scalar
+fn write_property(
+    out: &mut String,
+    key: &str,
+    value: &Value,
+    schema: Option<&Schema>,
+    depth: usize,
+) -> Result<(), ConversionError> {
+    if let Some(languages) = languages(value) {
+        indent(out, depth);
+        out.push_str("<rdf:Alt>\n");
+        for (language, text) in languages {
+            indent(out, depth + 1);
+            let _ = writeln!(
+                out,
+                "<rdf:li xml:lang=\"{}\">{}</rdf:li>",
+                escape(language),
+                escape(text)
+            );
+        }
+        indent(out, depth);
+        out.push_str("</rdf:Alt>\n");
+        return Ok(());
+    }
+    let Some(items) = elements(value).filter(|v| !v.is_empty()) else {
+        return write_description(out, "<rdf:Description", value, struct_schema(schema), depth);
+    };
+    let item_schema = match schema {
+        Some(Schema::List { items, .. }) => Some(&**items),
+        _ => None,
+    };
+    indent(out, depth);
+    out.push_str("<rdf:Seq>\n");
+    if let Some(points) = curve(key, items) {
+        for point in points.chunks(2) {
+            indent(out, depth + 1);
+            let _ = writeln!(out, "<rdf:li>{}, {}</rdf:li>", point[0], point[1]);
+        }
+    } else {
+        for (i, item) in items.iter().enumerate() {
+            indent(out, depth + 1);
+            match format_scalar("", item, item_schema) {
+                Some(text) => {
+                    let _ = writeln!(out, "<rdf:li>{}</rdf:li>", escape(&text));
+                }
+                None => {
+                    out.push_str("<rdf:li>\n");
+                    write_property(out, "", item, item_schema, depth + 2)
+                        .map_err(|e| e.within(&i.to_string()))?;
+                    indent(out, depth + 1);
+                    out.push_str("</rdf:li>\n");
+                }
+            }
+        }
+    }
+    indent(out, depth);
+    out.push_str("</rdf:Seq>\n");
+    Ok(())
+}
+
+/// The points of a tone curve, which Lightroom stores as a flat list of coordinates
+fn curve(key: &str, items: &[Value]) -> Option<Vec<i64>> {
+    if !key.contains("ToneCurve") || !items.len().is_multiple_of(2) {
+        return None;
+    }
+    items.iter().map(Value::as_i64).collect()
+}
+
+/// The translations of a text like `Group = { ["x-default"] = "Profiles" }`
+fn languages<'a>(value: &'a Value) -> Option<Vec<(&'a str, &'a str)>> {
+    let entries = entries(value)?;
+    if !entries.iter().any(|(language, _)| *language == "x-default") {
+        return None;
+    }
+    entries
+        .into_iter()
+        .map(|(language, text)| Some((language, text.as_text()?)))
+        .collect()
+}
+
+/// Whether a value is written as an attribute rather than an element
+fn is_scalar(value: &Value) -> bool {
+    matches!(
+        value,
+        Value::Int(_)
+            | Value::Float(_)
+            | Value::Number(_)
+            | Value::Bool(_)
+            | Value::String(_)
+            | Value::Zstr(_)
+    )
+}
+
+fn format_scalar(key: &str, value: &Value, schema: Option<&Schema>) -> Option<String> {
+    let decimals = DECIMALS.iter().find(|(k, _)| *k == key).map(|(_, d)| *d);
+    let signed = decimals.is_some()
+        || match schema {
+            Some(Schema::Integer { min: Some(min), .. }) => *min < 0,
+            Some(Schema::Number { min: Some(min), .. }) => *min < 0.0,
+            _ => false,
+        };
+    // Only known numbers are read back as integers when they are whole, other floats keep
+    // their decimal point
+    let known = matches!(schema, Some(Schema::Integer { .. } | Schema::Number { .. }));
+    let (number, text) = match value {
+        Value::Bool(true) => return Some("True".into()),
+        Value::Bool(false) => return Some("False".into()),
+        Value::String(_) | Value::Zstr(_) => return value.as_text().map(str::to_string),
+        Value::Int(i) => (*i as f64, i.to_string()),
+        Value::Float(f) if known => (*f, f.to_string()),
+        Value::Float(f) => (*f, format!("{:?}", f)),
+        Value::Number(n) if known => (n.as_f64(), n.to_value().to_string()),
+        Value::Number(n) => (n.as_f64(), n.to_string()),
+        _ => return None,
+    };
+    // Padded to the decimals Lightroom writes, but never rounded so no precision is lost
+    let text = match decimals {
+        Some(decimals) => {
+            let written = text
+                .split_once('.')
+                .map_or(0, |(_, fraction)| fraction.len());
+            format!("{:.*}", decimals.max(written), number)
+        }
+        None => text,
+    };
+    match signed && number > 0.0 {
+        true => Some(format!("+{}", text)),
+        false => Some(text),
+    }
+}
+
+/// Escapes text for attributes and elements. Line breaks and tabs are escaped as well, XML
+/// would turn them into spaces in attributes and `\r` into `\n` everywhere.
+fn escape(text: &str) -> Cow<'_, str> {
+    if !text.contains(['&', '<', '>', '"', '\n', '\r', '\t']) {
+        return Cow::Borrowed(text);
+    }
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\n' => escaped.push_str("&#xA;"),
+            '\r' => escaped.push_str("&#xD;"),
+            '\t' => escaped.push_str("&#x9;"),
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+fn is(node: &roxmltree::Node, namespace: &str, name: &str) -> bool {
+    node.is_element()
+        && node.tag_name().namespace() == Some(namespace)
+        && node.tag_name().name() == name
+}
+
+fn child_elements<'a, 'i>(
+    node: roxmltree::Node<'a, 'i>,
+) -> impl Iterator<Item = roxmltree::Node<'a, 'i>> {
+    node.children().filter(|n| n.is_element())
+}
+
+fn is_resource(node: &roxmltree::Node) -> bool {
+    node.attribute((RDF, "parseType")) == Some("Resource")
+}
+
+/// Reads the `crs:` attributes and elements of a description, sorted like Lightroom stores them
+fn read_description(node: roxmltree::Node, schema: Option<&StructSchema>) -> Value<'static> {
+    let attributes = node
+        .attributes()
+        .filter(|a| a.namespace() == Some(CRS))
+        .map(|a| {
+            (
+                a.name(),
+                parse_scalar(a.name(), a.value(), field(schema, a.name())),
+            )
+        });
+    let properties = child_elements(node)
+        .filter(|n| n.tag_name().namespace() == Some(CRS))
+        .map(|n| {
+            let key = n.tag_name().name();
+            (key, read_property(n, key, field(schema, key)))
+        });
+    let mut entries: Vec<(&str, Value<'static>)> = attributes.chain(properties).collect();
+    if entries.is_empty() {
+        // Like `{ }` in agprefs
+        return Value::Values(Vec::new());
+    }
+    entries.sort_by_key(|(key, _)| *key);
+    let entries: HashMap<Key, Value> = entries
+        .into_iter()
+        .map(|(k, v)| (Key::from(k.to_string()), v))
+        .collect();
+    Value::Struct(entries)
+}
+
+/// Reads the value of a property element
+fn read_property(node: roxmltree::Node, key: &str, schema: Option<&Schema>) -> Value<'static> {
+    if is_resource(&node) {
+        return read_description(node, struct_schema(schema));
+    }
+    let Some(child) = child_elements(node).next() else {
+        return parse_scalar(key, node.text().unwrap_or_default(), schema);
+    };
+    if is(&child, RDF, "Alt") {
+        let translations = child_elements(child).map(|n| {
+            let language = n.attribute((XML, "lang")).unwrap_or("x-default");
+            let text = n.text().unwrap_or_default();
+            (
+                Key::from(language.to_string()),
+                Value::from(text.to_string()),
+            )
+        });
+        return Value::Struct(translations.collect());
+    }
+    if is(&child, RDF, "Seq") || is(&child, RDF, "Bag") {
+        let item_schema = match schema {
+            Some(Schema::List { items, .. }) => Some(&**items),
+            _ => None,
+        };
+        let mut values = Vec::new();
+        for item in child_elements(child).filter(|n| is(n, RDF, "li")) {
+            let text = item.text().unwrap_or_default();
+            match text.split_once(',') {
+                Some((x, y)) if key.contains("ToneCurve") => {
+                    values.push(parse_scalar("", x.trim(), item_schema));
+                    values.push(parse_scalar("", y.trim(), item_schema));
+                }
+                _ => values.push(read_property(item, "", item_schema)),
+            }
+        }
+        return Value::Values(values);
+    }
+    read_description(child, struct_schema(schema))
+}
+
+/// Reads an attribute or the text of an element, as the type of the setting if it is known
+fn parse_scalar(key: &str, text: &str, schema: Option<&Schema>) -> Value<'static> {
+    let string = || Value::String(Cow::Owned(text.to_string()));
+    let number = || {
+        let unsigned = text.strip_prefix('+').unwrap_or(text);
+        if !unsigned.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '.') {
+            return None;
+        }
+        if let Ok(i) = unsigned.parse::<i64>() {
+            return Some(Value::Int(i));
+        }
+        let f = unsigned.parse::<f64>().ok()?;
+        match f.fract() == 0.0 && f.abs() < (1u64 << 53) as f64 {
+            true => Some(Value::Int(f as i64)),
+            false => Some(Value::Float(f)),
+        }
+    };
+    let bool = || match text {
+        "True" | "true" => Some(Value::Bool(true)),
+        "False" | "false" => Some(Value::Bool(false)),
+        _ => None,
+    };
+    match schema {
+        _ if TEXT.contains(&key) => string(),
+        Some(Schema::String) => string(),
+        Some(Schema::Enum { values }) if values.iter().any(|v| v.get_string().is_some()) => {
+            string()
+        }
+        Some(Schema::Bool) => bool().unwrap_or_else(string),
+        Some(Schema::Integer { .. } | Schema::Number { .. }) => number().unwrap_or_else(string),
+        _ => bool()
+            .or_else(|| {
+                // Decimals which aren't known numbers stay floats
+                match number()? {
+                    Value::Int(_) if text.contains(['.', 'e', 'E']) => {
+                        text.parse().ok().map(Value::Float)
+                    }
+                    value => Some(value),
+                }
+            })
+            .unwrap_or_else(string),
+    }
+}
@@ -0,0 +1,133 @@
+#![cfg(feature = "composer")]
+use agprefs::{
+    Agpref, ComposeOptions, Document, Indent, Layout, Newline, ParseOptions, TrailingComma, Value,
+};
+
+#[test]
+pub fn preferences() {
+    for input in [
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/assets/1.agprefs"
+        )),
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/assets/2.agprefs"
+        )),
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/assets/nikhil.agprefs"
+        )),
+    ] {
+        let x = Agpref::parse(input).unwrap();
+        let output = x.to_str_with(&ComposeOptions::preferences()).unwrap();
+        assert_eq!(input, output);
+    }
+}
+
+#[test]
+pub fn nested_preferences() {
+    let input = "pickle = {\n\t[\"/a.lrcat\"] = {\n\t\tcatalogType = \"lr\",\n\t\tupgraded = { },\n\t},\n}\n";
+    let x = Agpref::parse(input).unwrap();
+    let output = x.to_str_with(&ComposeOptions::preferences()).unwrap();
+    assert_eq!(
+        output,
+        "pickle = {\n\t[\"/a.lrcat\"] = {\n\t\tcatalogType = \"lr\",\n\t\tupgraded = {\n\t\t},\n\t},\n}\n"
+    );
+}
+
+#[test]
+pub fn options() {
+    let x = Agpref::parse("s = { A = 1, B = { 1, 2 }, C = { D = \"x\" } }").unwrap();
+    let options = ComposeOptions::develop_settings()
+        .layout(Layout::Multiline)
+        .indent(Indent::Spaces(2))
+        .trailing_comma(TrailingComma::Always)
+        .max_inline_entries(2)
+        .final_newline(false);
+    assert_eq!(
+        x.to_str_with(&options).unwrap(),
+        "s = {\n  A = 1,\n  B = { 1, 2, },\n  C = { D = \"x\", },\n}"
+    );
+    let options = options.trailing_comma(TrailingComma::Multiline);
+    assert_eq!(
+        x.to_str_with(&options).unwrap(),
+        "s = {\n  A = 1,\n  B = { 1, 2 },\n  C = { D = \"x\" },\n}"
+    );
+    assert_eq!(
+        Value::from(vec![1i64, 2]).to_str_with(&options).unwrap(),
+        "{ 1, 2 }"
+    );
+}
+
+#[test]
+pub fn carriage_returns() {
+    let mut x = Agpref::parse("s = { Text = \"\" }").unwrap();
+    x.values["Text"] = Value::from("a\rb\r\nc");
+    let options = ComposeOptions::preferences();
+    for options in [options, options.newline(Newline::CrLf)] {
+        let output = x.to_str_with(&options).unwrap();
+        assert!(output.contains("a\\rb\\r\\"));
+        assert_eq!(Agpref::parse(&output).unwrap(), x);
+    }
+}
+
+#[test]
+pub fn detected_style_roundtrips() {
+    let assets = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/assets");
+    for entry in std::fs::read_dir(assets).unwrap() {
+        let path = entry.unwrap().path();
+        if path.file_name().unwrap() == "failure" {
+            continue;
+        }
+        let input = std::fs::read_to_string(&path).unwrap();
+        let x = Agpref::parse_with(
+            &input,
+            ParseOptions::new()
+                .detect_style(true)
+                .preserve_numbers(true),
+        )
+        .unwrap();
+        assert_eq!(input, x.to_str().unwrap(), "{}", path.display());
+    }
+}
+
+#[test]
+pub fn detect_style() {
+    let options = ParseOptions::new().detect_style(true);
+    let x = Agpref::parse_with("s = {\r\n  a = 1,\r\n  b = { 1, 2 },\r\n}", options).unwrap();
+    assert_eq!(
+        x.style,
+        Some(
+            ComposeOptions::preferences()
+                .indent(Indent::Spaces(2))
+                .newline(Newline::CrLf)
+                .max_inline_entries(2)
+                .final_newline(false)
+        )
+    );
+
+    let x = Agpref::parse_with("s = { a = 1, b = 2 }\n", options).unwrap();
+    assert_eq!(
+        x.style,
+        Some(ComposeOptions::default().layout(Layout::Inline))
+    );
+
+    assert_eq!(Agpref::parse("s = { a = 1 }").unwrap().style, None);
+
+    // Each entry of a document only shows its own style
+    let input = "s = {\n\ta = 1,\n}\nt = { 1, 2 }";
+    let document = Document::parse_with(input, options).unwrap();
+    let styles: Vec<_> = document.iter().map(|e| e.style).collect();
+    assert_eq!(
+        styles,
+        [
+            Some(ComposeOptions::preferences()),
+            Some(
+                ComposeOptions::default()
+                    .layout(Layout::Inline)
+                    .final_newline(false)
+            ),
+        ]
+    );
+}
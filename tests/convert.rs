@@ -0,0 +1,100 @@
+use agprefs::{agprefs, Agpref, ConversionError, Errors, ParseOptions, Value};
+use indexmap::IndexMap;
+use std::collections::HashMap;
+
+#[test]
+pub fn accessors() {
+    let x = Agpref::parse_with(
+        "s = { A = 0, B = 1.43, C = 2.0, D = 1e3, E = 1, F = 2, G = true, H = \"1\" }",
+        ParseOptions::new().preserve_numbers(true),
+    )
+    .unwrap();
+    assert_eq!(x["A"].as_f64(), Some(0.0));
+    assert_eq!(x["B"].as_f64(), Some(1.43));
+    assert_eq!(x["B"].as_i64(), None);
+    assert_eq!(x["C"].as_i64(), Some(2));
+    assert_eq!(x["D"].as_i64(), Some(1000));
+    assert_eq!(x["H"].as_f64(), None);
+
+    assert_eq!(x["A"].as_bool(), Some(false));
+    assert_eq!(x["E"].as_bool(), Some(true));
+    assert_eq!(x["F"].as_bool(), None);
+    assert_eq!(x["G"].as_bool(), Some(true));
+    assert_eq!(x["H"].as_bool(), None);
+
+    assert_eq!(Value::Int(0).as_f64(), Some(0.0));
+    assert_eq!(Value::Float(-3.0).as_i64(), Some(-3));
+    assert_eq!(Value::Float(1e19).as_i64(), None);
+    assert_eq!(Value::Float(i64::MIN as f64).as_i64(), Some(i64::MIN));
+    assert_eq!(Value::Float(f64::NAN).as_i64(), None);
+    assert_eq!(Value::Float(1.0).as_bool(), None);
+}
+
+#[test]
+pub fn scalars() {
+    assert_eq!(u8::try_from(&Value::Int(255)), Ok(255));
+    assert_eq!(i32::try_from(Value::Float(-4.0)), Ok(-4));
+    assert_eq!(f32::try_from(&Value::Int(2)), Ok(2.0));
+    assert_eq!(bool::try_from(&Value::Int(1)), Ok(true));
+    assert_eq!(String::try_from(Value::from("x")), Ok("x".to_string()));
+    let value = Value::from("y");
+    assert_eq!(<&str>::try_from(&value), Ok("y"));
+    assert_eq!(Option::<i64>::try_from(&Value::Unit), Ok(None));
+    assert_eq!(Option::<i64>::try_from(Value::Int(3)), Ok(Some(3)));
+
+    assert_eq!(
+        u8::try_from(&Value::Int(256)),
+        Err(ConversionError {
+            path: String::new(),
+            expected: "u8",
+            found: "integer 256".into()
+        })
+    );
+    assert!(u64::try_from(&Value::Int(-1)).is_err());
+    let error = bool::try_from(Value::from("true")).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "expected bool at `/`, found string \"true\""
+    );
+}
+
+#[test]
+pub fn collections() {
+    let x = agprefs!({
+        Curve = { 0, 0, 128, 140, 255, 255 },
+        Group = { ["x-default"] = "Profiles" },
+        Empty = { },
+        Nested = { A = { 1, 2 }, ["a/b"] = { 3, 1.5 } },
+    });
+    assert_eq!(
+        Vec::<u8>::try_from(&x["Curve"]).unwrap(),
+        [0, 0, 128, 140, 255, 255]
+    );
+    let group = HashMap::<String, String>::try_from(x["Group"].clone()).unwrap();
+    assert_eq!(group["x-default"], "Profiles");
+    assert!(HashMap::<String, i64>::try_from(&x["Empty"])
+        .unwrap()
+        .is_empty());
+    assert!(Vec::<i64>::try_from(&x["Empty"]).unwrap().is_empty());
+
+    // Paths point at the element which failed
+    let error = IndexMap::<String, Vec<i64>>::try_from(&x["Nested"]).unwrap_err();
+    assert_eq!(error.path, "a~1b/1");
+    assert_eq!(
+        error.to_string(),
+        "expected i64 at `a~1b/1`, found float 1.5"
+    );
+    assert_eq!(x["Nested"].pointer(&error.path), Some(&Value::Float(1.5)));
+    let nested = IndexMap::<String, Vec<f64>>::try_from(x["Nested"].clone()).unwrap();
+    assert_eq!(nested.keys().collect::<Vec<_>>(), ["A", "a/b"]);
+    assert_eq!(nested["a/b"], [3.0, 1.5]);
+
+    let error = Vec::<u8>::try_from(&x["Group"]).unwrap_err();
+    assert_eq!((error.expected, error.found.as_str()), ("list", "struct"));
+
+    // Converting with `?` in functions returning `Errors`
+    fn curve(x: &Value) -> Result<Vec<u8>, Errors> {
+        Ok(Vec::try_from(x.pointer("Nested/a~1b").unwrap())?)
+    }
+    assert!(matches!(curve(&x), Err(Errors::Conversion(e)) if e.path == "1"));
+}
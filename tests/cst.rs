@@ -0,0 +1,105 @@
+#![cfg(feature = "composer")]
+use agprefs::{cst::Cst, Value};
+
+#[test]
+pub fn lossless() {
+    for input in [
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/assets/1.agprefs"
+        )),
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/assets/db.agprefs"
+        )),
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/assets/windows.agprefs"
+        )),
+    ] {
+        let cst = Cst::parse(input).unwrap();
+        assert_eq!(cst.as_str(), input);
+        assert_eq!(
+            cst.to_agpref().unwrap(),
+            agprefs::Agpref::parse(input).unwrap()
+        );
+    }
+}
+
+#[test]
+pub fn replace() {
+    let input = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/assets/windows.agprefs"
+    ));
+    let mut cst = Cst::parse(input).unwrap();
+    assert_eq!(cst.name(), "prefs");
+    cst.set(&["libraryToLoad20"], &Value::from("D:\\Catalog.lrcat"))
+        .unwrap();
+    assert_eq!(
+        cst.as_str(),
+        input.replace(
+            "\"C:\\\\Users\\\\harsh\\\\Pictures\\\\Lightroom\\\\Lightroom Catalog.lrcat\",\r\n\trecentLibraries20",
+            "\"D:\\\\Catalog.lrcat\",\r\n\trecentLibraries20"
+        )
+    );
+
+    let input = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/assets/db.agprefs"
+    ));
+    let mut cst = Cst::parse(input).unwrap();
+    let path = ["Look", "Parameters", "Exposure2012"];
+    assert_eq!(cst.get(&path), Some(Value::Int(0)));
+    cst.set(&path, &Value::Float(0.35)).unwrap();
+    assert_eq!(cst.get(&path), Some(Value::Float(0.35)));
+    assert_eq!(
+        cst.as_str(),
+        input.replace(
+            "ConvertToGrayscale = false,\nExposure2012 = 0,\nLensBlur",
+            "ConvertToGrayscale = false,\nExposure2012 = 0.35,\nLensBlur"
+        )
+    );
+    cst.set(&["ToneCurvePV2012Red", "2"], &Value::Int(72))
+        .unwrap();
+    assert_eq!(
+        cst.source(&["ToneCurvePV2012Red"]).unwrap(),
+        "{ 0,\n0,\n72,\n76,\n150,\n161,\n255,\n255 }"
+    );
+}
+
+#[test]
+pub fn preserves_spelling() {
+    let mut cst = Cst::parse("s = { a = 1.0, b = 1e-05, c = \"x\\\"y\" }").unwrap();
+    cst.set(&["a"], &Value::Int(2)).unwrap();
+    assert_eq!(cst.as_str(), "s = { a = 2, b = 1e-05, c = \"x\\\"y\" }");
+    // Setting an equal value doesn't touch the text
+    cst.set(&["b"], &Value::Float(0.00001)).unwrap();
+    assert_eq!(cst.as_str(), "s = { a = 2, b = 1e-05, c = \"x\\\"y\" }");
+}
+
+#[test]
+pub fn insert_and_remove() {
+    let input = "prefs = {\n\ta = 1,\n\tb = 2,\n}\n";
+    let mut cst = Cst::parse(input).unwrap();
+    cst.set(&["x-default"], &Value::from("Profiles")).unwrap();
+    assert_eq!(
+        cst.as_str(),
+        "prefs = {\n\ta = 1,\n\tb = 2,\n\t[\"x-default\"] = \"Profiles\",\n}\n"
+    );
+    cst.remove(&["x-default"]).unwrap();
+    assert_eq!(cst.as_str(), input);
+    cst.remove(&["a"]).unwrap();
+    assert_eq!(cst.as_str(), "prefs = {\n\tb = 2,\n}\n");
+
+    let mut cst = Cst::parse("s = { A = 1,\nB = { 1, 2 } }").unwrap();
+    cst.set(&["C"], &Value::Bool(true)).unwrap();
+    assert_eq!(cst.as_str(), "s = { A = 1,\nB = { 1, 2 },\nC = true }");
+    cst.set(&["B", "2"], &Value::Int(3)).unwrap();
+    assert_eq!(cst.as_str(), "s = { A = 1,\nB = { 1, 2, 3 },\nC = true }");
+    cst.remove(&["C"]).unwrap();
+    cst.remove(&["B", "0"]).unwrap();
+    assert_eq!(cst.as_str(), "s = { A = 1,\nB = { 2, 3 } }");
+    cst.set(&["D", "E"], &Value::Int(1)).unwrap_err();
+    cst.remove(&["D"]).unwrap_err();
+}
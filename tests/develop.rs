@@ -0,0 +1,166 @@
+use agprefs::develop::{Basic, Hsl};
+use agprefs::{Agpref, DevelopSettings, ParseOptions, ToneCurve, Value};
+
+fn keys<'a>(x: &'a Agpref) -> Vec<&'a str> {
+    x.get_struct().unwrap().keys().map(|k| k.as_str()).collect()
+}
+
+#[test]
+pub fn db() {
+    let input = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/assets/db.agprefs"
+    ));
+    let x = Agpref::parse(input).unwrap();
+    let settings = DevelopSettings::try_from(x.clone()).unwrap();
+    assert_eq!(settings.basic.exposure2012, Some(1.05));
+    assert_eq!(
+        settings.basic.camera_profile.as_deref(),
+        Some("Camera Standard")
+    );
+    assert_eq!(settings.basic.convert_to_grayscale, Some(false));
+    assert_eq!(settings.hsl.hue_adjustment_orange, Some(33));
+    assert_eq!(settings.color_grading.color_grade_midtone_sat, Some(79));
+    assert_eq!(settings.detail.color_noise_reduction, Some(55));
+    assert_eq!(settings.effects.grain_seed, Some(1182535882));
+    assert_eq!(settings.calibration.blue_hue, Some(-23));
+    assert_eq!(settings.basic.custom_tint, Some(20.3949));
+    assert_eq!(
+        settings.tone_curve.extended_tone_curve_pv2012_red,
+        Some(ToneCurve::new(vec![(0, 0), (70, 76), (150, 161), (255, 255)]).unwrap())
+    );
+    assert!(settings.extra.contains_key("Look"));
+    assert!(!settings.extra.contains_key("Exposure2012"));
+
+    let y = Agpref::from(settings);
+    assert_eq!(keys(&y), keys(&x));
+    assert_eq!(y, x);
+    #[cfg(feature = "composer")]
+    assert_eq!(y.to_str().unwrap(), input);
+}
+
+#[test]
+pub fn lossless() {
+    let input = "s = { Exposure2012 = 1.0, Contrast2012 = 1.5, Vibrance = \"high\", \
+                 NewSetting = 3, Texture = 10, Tint = 4, ToneCurvePV2012 = { 0, 0.5 } }";
+    let x = Agpref::parse(input).unwrap();
+    let settings = DevelopSettings::try_from(x.clone()).unwrap();
+    assert_eq!(
+        settings.basic,
+        Basic {
+            exposure2012: Some(1.0),
+            texture: Some(10),
+            tint: Some(4.0),
+            ..Default::default()
+        }
+    );
+    assert_eq!(
+        settings
+            .extra
+            .keys()
+            .map(|k| k.as_str())
+            .collect::<Vec<_>>(),
+        ["Contrast2012", "Vibrance", "NewSetting", "ToneCurvePV2012"]
+    );
+    let y = Agpref::from(settings.clone());
+    assert_eq!(keys(&y), keys(&x));
+    assert_eq!(y, x);
+
+    // Keys which weren't read are sorted in
+    let mut settings = settings;
+    settings.basic.clarity2012 = Some(5);
+    settings.basic.whites2012 = Some(-10);
+    assert_eq!(
+        keys(&Agpref::from(settings)),
+        [
+            "Clarity2012",
+            "Exposure2012",
+            "Contrast2012",
+            "Vibrance",
+            "NewSetting",
+            "Texture",
+            "Tint",
+            "ToneCurvePV2012",
+            "Whites2012"
+        ]
+    );
+
+    // Whole floats stay floats until they are edited, and typed settings win over `extra`
+    let mut settings = DevelopSettings::try_from(x.clone()).unwrap();
+    assert_eq!(
+        Agpref::from(settings.clone())["Exposure2012"],
+        Value::Float(1.0)
+    );
+    settings.basic.exposure2012 = Some(0.5);
+    settings.basic.vibrance = Some(3);
+    let y = Agpref::from(settings.clone());
+    assert_eq!(y["Exposure2012"], Value::Float(0.5));
+    assert_eq!(y["Vibrance"], Value::Int(3));
+    assert_eq!(keys(&y), keys(&x));
+    settings.basic.exposure2012 = Some(2.0);
+    assert_eq!(Agpref::from(settings)["Exposure2012"], Value::Int(2));
+
+    // Numbers keeping their spelling compare by value
+    let x = Agpref::parse_with(input, ParseOptions::new().preserve_numbers(true)).unwrap();
+    let settings = DevelopSettings::try_from(x.clone()).unwrap();
+    assert_eq!(settings.basic.texture, Some(10));
+    assert_eq!(settings.basic.exposure2012, Some(1.0));
+    let y = Agpref::from(settings);
+    assert_eq!(keys(&y), keys(&x));
+    assert_eq!(y, x);
+    #[cfg(feature = "composer")]
+    assert_eq!(y.to_str().unwrap(), x.to_str().unwrap());
+
+    assert_eq!(
+        DevelopSettings::try_from(Agpref::parse("s = { }").unwrap()).unwrap(),
+        DevelopSettings::new()
+    );
+    assert!(DevelopSettings::try_from(Value::Int(1)).is_err());
+}
+
+#[test]
+pub fn build() {
+    let settings = DevelopSettings {
+        hsl: Hsl {
+            saturation_adjustment_blue: Some(-20),
+            ..Default::default()
+        },
+        basic: Basic {
+            exposure2012: Some(0.5),
+            whites2012: Some(10),
+            process_version: Some("11.0".into()),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let x = settings.to_agpref("preset");
+    assert_eq!(x.name, "preset");
+    assert_eq!(
+        x.get_struct()
+            .unwrap()
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.clone()))
+            .collect::<Vec<_>>(),
+        [
+            ("Exposure2012", Value::Float(0.5)),
+            ("ProcessVersion", Value::from("11.0")),
+            ("SaturationAdjustmentBlue", Value::Int(-20)),
+            ("Whites2012", Value::Int(10)),
+        ]
+    );
+    assert!(Hsl::KEYS.contains(&"GrayMixerMagenta"));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+pub fn serde() {
+    let input = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/assets/metadata"
+    ));
+    let settings = DevelopSettings::try_from(Agpref::parse(input).unwrap()).unwrap();
+    let json = serde_json::to_string(&settings).unwrap();
+    assert!(json.contains("\"basic\":{\"ProcessVersion\":\"11.0\""));
+    let y: DevelopSettings = serde_json::from_str(&json).unwrap();
+    assert_eq!(y, settings);
+}
@@ -0,0 +1,132 @@
+use agprefs::{diff, diff_with, Agpref, Change, DiffOptions, ListComparison, Value};
+
+fn metadata() -> Agpref<'static> {
+    let input = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/assets/metadata"
+    ));
+    Agpref::parse(input).unwrap()
+}
+
+#[test]
+pub fn changes() {
+    let a = metadata();
+    assert!(diff(&a, &a).is_empty());
+
+    let mut b = a.clone();
+    b["Exposure2012"] = Value::Float(0.93);
+    b["Texture"] = Value::from("high");
+    b["Look"]["Parameters"]["ToneCurvePV2012"][3] = Value::Int(20);
+    b["Look"]["Group"]["x-default"] = Value::from("Presets");
+    b.remove_path("Vibrance");
+    b.set_path("ColorGradeBlending", 100).unwrap();
+    b.set_path("Look/Parameters/Enable~1Disable", true).unwrap();
+
+    let d = diff(&a, &b);
+    assert_eq!(d.len(), 7);
+    match d.get("Exposure2012").unwrap() {
+        Change::Modified {
+            from, to, delta, ..
+        } => {
+            assert_eq!(from, &Value::Float(1.43));
+            assert_eq!(to, &Value::Float(0.93));
+            assert!((delta.unwrap() + 0.5).abs() < 1e-9);
+        }
+        c => panic!("Unexpected {:?}", c),
+    }
+    assert!(matches!(d.get("Texture"), Some(Change::TypeChanged { .. })));
+    assert!(matches!(
+        d.get("Look/Parameters/ToneCurvePV2012/3"),
+        Some(Change::Modified { delta: Some(d), .. }) if *d == 4.0
+    ));
+    assert!(matches!(
+        d.get("Look/Group/x-default"),
+        Some(Change::Modified { delta: None, .. })
+    ));
+    assert_eq!(
+        d.get("Vibrance"),
+        Some(&Change::Removed {
+            path: "Vibrance".into(),
+            value: Value::Int(-31)
+        })
+    );
+    assert!(matches!(
+        d.get("ColorGradeBlending"),
+        Some(Change::Modified { delta: Some(d), .. }) if *d == 50.0
+    ));
+    // Paths can be looked up with pointer
+    let added = d.get("Look/Parameters/Enable~1Disable").unwrap();
+    assert!(matches!(added, Change::Added { .. }));
+    assert_eq!(b.pointer(added.path()), Some(&Value::Bool(true)));
+
+    let report = d.to_string();
+    assert!(report.contains("~ Exposure2012: 1.43 -> 0.93 (-0.5)\n"));
+    assert!(report.contains("! Texture: 26 -> \"high\" (number -> string)\n"));
+    assert!(report.contains("- Vibrance = -31\n"));
+    assert!(report.contains("+ Look/Parameters/Enable~1Disable = true\n"));
+}
+
+#[test]
+pub fn options() {
+    let a = Agpref::parse("s = { A = 1, B = 0.5, C = { 1, 2, 3 }, D = { }, E = 2 }").unwrap();
+    let b =
+        Agpref::parse("s = { A = 1.0, B = 0.5001, C = { 3, 1, 2 }, D = { }, E = 2.0 }").unwrap();
+    // Integers and floats compare by value
+    let d = diff(&a, &b);
+    assert_eq!(
+        d.iter().map(|c| c.path()).collect::<Vec<_>>(),
+        ["B", "C/0", "C/1", "C/2"]
+    );
+
+    let options = DiffOptions::new()
+        .float_tolerance(0.001)
+        .lists(ListComparison::Set);
+    assert!(diff_with(&a, &b, options).is_empty());
+
+    let b = Agpref::parse("s = { A = 1, B = 0.5, C = { 2, 4, 3, 2 }, D = { }, E = 2 }").unwrap();
+    let d = diff_with(&a, &b, options);
+    assert_eq!(
+        d.changes,
+        [
+            Change::Removed {
+                path: "C/0".into(),
+                value: Value::Int(1)
+            },
+            Change::Added {
+                path: "C/1".into(),
+                value: Value::Int(4)
+            },
+            Change::Added {
+                path: "C/3".into(),
+                value: Value::Int(2)
+            },
+        ]
+    );
+    let d = diff(&a, &b);
+    assert_eq!(d.get("C/3").unwrap().to_string(), "+ C/3 = 2");
+
+    // Empty tables are equal however they are written
+    let a = Agpref::parse("s = { A = { }, B = { } }").unwrap();
+    let b = Agpref::parse("s = { A = { }, B = { X = 1 } }").unwrap();
+    assert_eq!(diff(&a, &b).to_string(), "+ B/X = 1\n");
+    assert!(diff(&Value::Unit, &Value::Values(vec![])).is_empty());
+    assert!(diff(&Value::Unit, &Value::Struct(Default::default())).is_empty());
+    let d = diff(&Value::Int(1), &Value::from("1"));
+    assert_eq!(d.to_string(), "! /: 1 -> \"1\" (number -> string)\n");
+}
+
+#[cfg(feature = "serde")]
+#[test]
+pub fn serde() {
+    let a = Agpref::parse("s = { A = 1, B = \"x\" }").unwrap();
+    let b = Agpref::parse("s = { A = 1.5, C = { 1 } }").unwrap();
+    let json = serde_json::to_value(diff(&a, &b)).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!([
+            { "change": "modified", "path": "A", "from": 1, "to": 1.5, "delta": 0.5 },
+            { "change": "removed", "path": "B", "value": "x" },
+            { "change": "added", "path": "C", "value": [1] },
+        ])
+    );
+}
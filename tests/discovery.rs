@@ -0,0 +1,109 @@
+use agprefs::discovery::{Platform, PrefsFile, PrefsLocator};
+use std::path::{Path, PathBuf};
+
+/// A fresh directory to lay out a filesystem in
+fn temp_root(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("agprefs-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    root
+}
+
+fn touch(path: &Path) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, "prefs = {\n}\n").unwrap();
+}
+
+#[test]
+pub fn candidates() {
+    let windows = PrefsLocator::new("C:\\Users\\me", Platform::Windows).root("/mnt/c");
+    let candidates = windows.candidates();
+    assert_eq!(candidates.len(), 5);
+    assert_eq!(candidates[0].version, 7);
+    assert!(candidates.windows(2).all(|c| c[0].version >= c[1].version));
+    let preferences = Path::new("/mnt/c/Users/me/AppData/Roaming/Adobe/Lightroom/Preferences");
+    assert_eq!(
+        candidates[0].path,
+        preferences.join("Lightroom Classic CC 7 Preferences.agprefs")
+    );
+    assert_eq!(
+        candidates[4].path,
+        preferences.join("Lightroom 3 Preferences.agprefs")
+    );
+    // Every spelling of a Windows home ends up in the same place
+    for home in [
+        "C:/Users/me",
+        "\\\\?\\C:\\Users\\me\\",
+        "\\Users\\me",
+        "Users\\me",
+        "\\\\server\\share\\Users\\me",
+        "\\\\?\\UNC\\server\\share\\Users\\me",
+    ] {
+        let locator = PrefsLocator::new(home, Platform::Windows).root("/mnt/c");
+        assert_eq!(locator.candidates(), candidates, "{}", home);
+    }
+
+    let mac = PrefsLocator::new("/Users/me", Platform::MacOs).root("/Volumes/Mac");
+    let candidates = mac.candidates();
+    assert_eq!(candidates.len(), 10);
+    assert_eq!(
+        candidates[0],
+        PrefsFile {
+            version: 7,
+            path: "/Volumes/Mac/Users/me/Library/Preferences/Lightroom Classic CC 7 Preferences.agprefs".into(),
+        }
+    );
+    assert_eq!(
+        candidates[1].path,
+        Path::new("/Volumes/Mac/Users/me/Library/Application Support/Adobe/Lightroom/Preferences/Lightroom Classic CC 7 Preferences.agprefs")
+    );
+}
+
+#[test]
+pub fn from_home() {
+    // The drive or share of a Windows home is the root, the rest is the home directory
+    for (home, root) in [
+        ("C:\\Users\\me", "C:\\"),
+        ("\\\\server\\share\\Users\\me", "\\\\server\\share\\"),
+        ("\\\\?\\C:\\Users\\me", "\\\\?\\C:\\"),
+    ] {
+        let locator = PrefsLocator::from_home(home, Platform::Windows);
+        assert_eq!(
+            locator,
+            PrefsLocator::new(home, Platform::Windows).root(root)
+        );
+        let mut expected = PathBuf::from(root);
+        expected.extend(["Users", "me", "AppData", "Roaming", "Adobe", "Lightroom"]);
+        expected.extend(["Preferences", "Lightroom Classic CC 7 Preferences.agprefs"]);
+        assert_eq!(locator.candidates()[0].path, expected, "{}", home);
+    }
+    assert_eq!(
+        PrefsLocator::from_home("/Users/me", Platform::MacOs),
+        PrefsLocator::new("/Users/me", Platform::MacOs)
+    );
+}
+
+#[test]
+pub fn find() {
+    let root = temp_root("find");
+    let locator = PrefsLocator::new("/Users/me", Platform::Windows).root(&root);
+    assert_eq!(locator.find(), None);
+
+    let preferences = root.join("Users/me/AppData/Roaming/Adobe/Lightroom/Preferences");
+    touch(&preferences.join("Lightroom 5 Preferences.agprefs"));
+    let found = locator.find().unwrap();
+    assert_eq!(found.version, 5);
+
+    touch(&preferences.join("Lightroom Classic CC 7 Preferences.agprefs"));
+    let found = locator.find().unwrap();
+    assert_eq!(found.version, 7);
+    assert_eq!(
+        found.path,
+        preferences.join("Lightroom Classic CC 7 Preferences.agprefs")
+    );
+
+    // Other platforms don't look there
+    let mac = PrefsLocator::new("/Users/me", Platform::MacOs).root(&root);
+    assert_eq!(mac.find(), None);
+    std::fs::remove_dir_all(&root).unwrap();
+}
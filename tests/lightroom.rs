@@ -0,0 +1,264 @@
+use agprefs::{Agpref, CatalogUpgrade, Errors, LightroomPrefs};
+
+const WINDOWS: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/assets/windows.agprefs"
+));
+const NIKHIL: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/assets/nikhil.agprefs"
+));
+
+#[test]
+pub fn catalogs() {
+    let prefs = LightroomPrefs::parse(WINDOWS).unwrap();
+    let catalog = "C:\\Users\\harsh\\Pictures\\Lightroom\\Lightroom Catalog.lrcat";
+    assert_eq!(prefs.library_to_load(), Some(catalog));
+    assert_eq!(prefs.recent_catalogs(), [catalog]);
+    assert!(prefs.missing_catalogs().is_empty());
+
+    let prefs = LightroomPrefs::parse(NIKHIL).unwrap();
+    assert_eq!(
+        prefs.library_to_load(),
+        Some("/Users/nikhil/Downloads/Catalogs/C2/C5/C5.lrcat")
+    );
+    let recent = prefs.recent_catalogs();
+    assert_eq!(recent.len(), 11);
+    assert_eq!(recent[0], "/Users/nikhil/Downloads/Catalogs/C2/C5/C5.lrcat");
+    assert_eq!(
+        recent[10],
+        "/Users/nikhil/Downloads/Alexavier and Ashley-v11.lrcat"
+    );
+    let missing = prefs.missing_catalogs();
+    assert_eq!(
+        missing[0],
+        "/Volumes/T5/AfterShoot Editing/Exact Datasets/Mike J/Dataset/1/AS_TEST_NEUTRAL.lrcat"
+    );
+    assert!(missing.contains(&"/Volumes/T5/Downloads/Diana&Jason/Diana&JasonWedLR copy-v11.lrcat"));
+
+    // Preferences parsed without decoding work the same
+    let plain = LightroomPrefs::from_agpref(Agpref::parse(NIKHIL).unwrap()).unwrap();
+    assert_eq!(plain.recent_catalogs(), recent);
+    assert_eq!(plain.missing_catalogs(), missing);
+
+    assert!(LightroomPrefs::parse("prefs = 1").is_err());
+}
+
+#[test]
+pub fn edit() {
+    let mut prefs = LightroomPrefs::parse(NIKHIL).unwrap();
+    let c1 = "/Users/nikhil/Downloads/Catalogs/C1/C1.lrcat";
+    prefs.add_recent(c1);
+    assert_eq!(prefs.recent_catalogs()[0], c1);
+    assert_eq!(prefs.recent_catalogs().len(), 11);
+
+    prefs.move_recent(c1, 10).unwrap();
+    assert_eq!(prefs.recent_catalogs()[10], c1);
+    assert!(prefs.move_recent(c1, 11).is_err());
+    assert!(prefs.move_recent("/Missing.lrcat", 0).is_err());
+
+    assert!(prefs.remove_recent(c1));
+    assert!(!prefs.remove_recent(c1));
+    assert_eq!(prefs.recent_catalogs().len(), 10);
+
+    let missing = prefs.missing_catalogs()[0].to_string();
+    assert!(prefs.remove_missing(&missing));
+    prefs.add_missing(c1);
+    prefs.add_missing(c1);
+    assert_eq!(prefs.missing_catalogs().last(), Some(&c1));
+    assert_eq!(
+        prefs
+            .missing_catalogs()
+            .iter()
+            .filter(|c| **c == c1)
+            .count(),
+        1
+    );
+
+    prefs.set_recent_catalogs(["/A.lrcat", "/B.lrcat"]);
+    prefs.set_library_to_load("/B.lrcat");
+    assert_eq!(prefs.recent_catalogs(), ["/A.lrcat", "/B.lrcat"]);
+    assert_eq!(prefs.library_to_load(), Some("/B.lrcat"));
+
+    // Missing lists are created
+    let mut prefs = LightroomPrefs::parse("prefs = {\n}\n").unwrap();
+    assert!(prefs.recent_catalogs().is_empty());
+    assert_eq!(prefs.library_to_load(), None);
+    prefs.add_recent("/A.lrcat");
+    assert_eq!(prefs.recent_catalogs(), ["/A.lrcat"]);
+}
+
+#[test]
+pub fn upgrades() {
+    let prefs = LightroomPrefs::parse(NIKHIL).unwrap();
+    let upgrades = prefs.upgrades();
+    assert_eq!(
+        upgrades,
+        [CatalogUpgrade {
+            from: "/Users/nikhil/Downloads/Alexavier and Ashley.lrcat".into(),
+            to: "/Users/nikhil/Downloads/Alexavier and Ashley-v11.lrcat".into(),
+            catalog_type: "lr".into(),
+            id: "70933BD8-9D87-4D87-8541-6A2BFE2979A7".into(),
+            version: 1100000,
+        }]
+    );
+    assert_eq!(upgrades[0].major_version(), 11);
+    assert_eq!(
+        prefs.resolve_catalog("/Users/nikhil/Downloads/Alexavier and Ashley.lrcat"),
+        "/Users/nikhil/Downloads/Alexavier and Ashley-v11.lrcat"
+    );
+    assert_eq!(prefs.resolve_catalog("/A.lrcat"), "/A.lrcat");
+    assert!(LightroomPrefs::parse(WINDOWS)
+        .unwrap()
+        .upgrades()
+        .is_empty());
+}
+
+#[test]
+pub fn upgrade_chains() {
+    let input = r#"prefs = {
+	Adobe_successfulUpgrades1000000 = "pickle = {\
+	[\"/fivek.lrcat\"] = {\
+		catalogType = \"lr\",\
+		id = \"A\",\
+		upgraded = \"/fivek-v10.lrcat\",\
+	},\
+}\
+",
+	Adobe_successfulUpgrades1100000 = "pickle = {\
+	[\"/fivek-v10.lrcat\"] = {\
+		catalogType = \"lr\",\
+		id = \"A\",\
+		upgraded = \"/fivek-v10-v11.lrcat\",\
+	},\
+	[\"/loop.lrcat\"] = {\
+		catalogType = \"lr\",\
+		id = \"B\",\
+		upgraded = \"/loop.lrcat\",\
+	},\
+}\
+",
+}
+"#;
+    let prefs = LightroomPrefs::parse(input).unwrap();
+    let upgrades = prefs.upgrades();
+    assert_eq!(upgrades.len(), 3);
+    assert_eq!(upgrades[0].major_version(), 10);
+    for path in ["/fivek.lrcat", "/fivek-v10.lrcat", "/fivek-v10-v11.lrcat"] {
+        assert_eq!(prefs.resolve_catalog(path), "/fivek-v10-v11.lrcat");
+    }
+    assert_eq!(prefs.resolve_catalog("/loop.lrcat"), "/loop.lrcat");
+
+    // The newest Lightroom wins when a catalog was upgraded more than once
+    let mut upgrades = upgrades;
+    upgrades.push(CatalogUpgrade {
+        to: "/fivek-v12.lrcat".into(),
+        version: 1200000,
+        ..upgrades[0].clone()
+    });
+    assert_eq!(
+        CatalogUpgrade::resolve(&upgrades, "/fivek.lrcat"),
+        "/fivek-v12.lrcat"
+    );
+
+    // A malformed entry is skipped, the chain next to it still resolves
+    let broken = input.replace(r#"upgraded = \"/loop.lrcat\""#, "upgraded = 1");
+    let prefs = LightroomPrefs::parse(&broken).unwrap();
+    assert_eq!(prefs.upgrades().len(), 2);
+    assert_eq!(
+        prefs.resolve_catalog("/fivek.lrcat"),
+        "/fivek-v10-v11.lrcat"
+    );
+    let errors = prefs.upgrade_errors();
+    assert_eq!(errors.len(), 1);
+    match &errors[0] {
+        Errors::Conversion(e) => assert_eq!(
+            e.to_string(),
+            "expected string at `Adobe_successfulUpgrades1100000/~1loop.lrcat/upgraded`, found integer 1"
+        ),
+        e => panic!("unexpected error {}", e),
+    }
+}
+
+#[test]
+pub fn malformed_upgrades() {
+    let input = r#"prefs = {
+	Adobe_successfulUpgrades = "pickle = {\
+}\
+",
+	Adobe_successfulUpgradesBeta = "pickle = {\
+	[\"/beta.lrcat\"] = {\
+		catalogType = \"lr\",\
+		id = \"B\",\
+		upgraded = \"/beta-v12.lrcat\",\
+	},\
+}\
+",
+	Adobe_successfulUpgrades1100000 = "pickle = {\
+	[\"/fivek.lrcat\"] = {\
+		catalogType = \"lr\",\
+		id = \"A\",\
+		upgraded = \"/fivek-v11.lrcat\",\
+	},\
+}\
+",
+	Adobe_successfulUpgrades1200000 = "pickle = {\
+	[\"/fivek-v11.lrcat\"] = {\
+",
+	Adobe_successfulUpgrades1300000 = 7,
+}
+"#;
+    let prefs = LightroomPrefs::parse(input).unwrap();
+    assert_eq!(prefs.upgrades().len(), 1);
+    assert_eq!(prefs.resolve_catalog("/fivek.lrcat"), "/fivek-v11.lrcat");
+    assert_eq!(prefs.resolve_catalog("/beta.lrcat"), "/beta.lrcat");
+    let errors: Vec<_> = prefs
+        .upgrade_errors()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            "`Adobe_successfulUpgrades` doesn't end in a version",
+            "`Adobe_successfulUpgradesBeta` doesn't end in a version",
+            "expected struct at `Adobe_successfulUpgrades1200000`, found string \"pickle = {\\n\\t[\\\"/fivek-v11.lrcat\\\"] = {\\n\"",
+            "expected struct at `Adobe_successfulUpgrades1300000`, found integer 7",
+        ]
+    );
+}
+
+#[cfg(feature = "composer")]
+#[test]
+pub fn write() {
+    use agprefs::Value;
+    // Untouched preferences are written back as they were
+    for input in [WINDOWS, NIKHIL] {
+        let prefs = LightroomPrefs::parse(input).unwrap();
+        assert_eq!(prefs.to_str().unwrap(), input);
+    }
+
+    let mut prefs = LightroomPrefs::parse(WINDOWS).unwrap();
+    let catalog = "D:\\Photos \"2023\"\\Catalog.lrcat";
+    prefs.add_recent(catalog);
+    prefs.add_missing("E:\\Old.lrcat");
+    prefs.set_library_to_load(catalog);
+    let output = prefs.to_str().unwrap();
+    assert!(output.contains(
+        "\trecentLibraries20 = \"recentLibraries = {\\\r\n\
+         \t\\\"D:\\\\\\\\Photos \\\\\\\"2023\\\\\\\"\\\\\\\\Catalog.lrcat\\\",\\\r\n\
+         \t\\\"C:\\\\\\\\Users"
+    ));
+    assert!(
+        output.contains("\tlibraryToLoad20 = \"D:\\\\Photos \\\"2023\\\"\\\\Catalog.lrcat\",\r\n")
+    );
+
+    let plain = Agpref::parse(&output).unwrap();
+    let recents = plain.get_struct().unwrap()["recentLibraries20"].clone();
+    let recents = Agpref::parse(recents.get_string().unwrap()).unwrap();
+    assert_eq!(recents.name, "recentLibraries");
+    assert_eq!(recents[0], Value::from(catalog));
+    let reparsed = LightroomPrefs::parse(&output).unwrap();
+    assert_eq!(reparsed.recent_catalogs()[0], catalog);
+    assert_eq!(reparsed.missing_catalogs(), ["E:\\Old.lrcat"]);
+}
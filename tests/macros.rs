@@ -0,0 +1,255 @@
+use agprefs::{agprefs, Agpref, Key, Value};
+
+#[test]
+pub fn values() {
+    assert_eq!(agprefs!(1), Value::Int(1));
+    assert_eq!(agprefs!(-0.5), Value::Float(-0.5));
+    assert_eq!(agprefs!("x"), Value::from("x"));
+    assert_eq!(agprefs!(true), Value::Bool(true));
+    assert_eq!(agprefs!({}), Value::Values(vec![]));
+    assert_eq!(
+        agprefs!({ 1, "a", { 2, 3 }, { A = 1 }, }),
+        Value::Values(vec![
+            Value::Int(1),
+            Value::from("a"),
+            Value::from(vec![2i64, 3]),
+            agprefs!({ A = 1 }),
+        ])
+    );
+
+    // Expressions up to the next comma
+    let amount = 12;
+    let names = ["Adobe Color", "Adobe Monochrome"];
+    let x = agprefs!({
+        Amount = amount * 2 + 1,
+        Name = names[0].to_string(),
+        Names = names.iter().map(|n| Value::from(*n)).collect::<Vec<_>>(),
+        Size = (amount, 0).0,
+        [names[1]] = { },
+        Unit = (),
+    });
+    assert_eq!(x["Amount"], Value::Int(25));
+    assert_eq!(x["Name"], Value::from("Adobe Color"));
+    assert_eq!(x["Names"][1], Value::from("Adobe Monochrome"));
+    assert_eq!(x["Size"], Value::Int(12));
+    assert_eq!(x["Adobe Monochrome"], Value::Values(vec![]));
+    assert_eq!(x["Unit"], Value::Unit);
+}
+
+#[test]
+pub fn structs() {
+    let x = agprefs!({
+        Zebra = 1,
+        ["x-default"] = "Profiles",
+        Alpha = { Nested = { Deeper = { 1, 2 } } },
+        ["Id"] = 2,
+    });
+    let keys = x.get_struct().unwrap().keys().collect::<Vec<_>>();
+    assert_eq!(keys, ["Zebra", "x-default", "Alpha", "Id"]);
+    // Bracketed keys stay bracketed like when parsing
+    assert!(matches!(keys[0], Key::Ident(_)));
+    assert!(matches!(keys[3], Key::Quoted(_)));
+    assert_eq!(x.pointer("Alpha/Nested/Deeper/1"), Some(&Value::Int(2)));
+
+    let a: Agpref = agprefs!(s = {
+        Zebra = 1,
+        ["x-default"] = "Profiles",
+        Alpha = { Nested = { Deeper = { 1, 2 } } },
+        ["Id"] = 2
+    });
+    let parsed = Agpref::parse(
+        r#"s = { Zebra = 1, ["x-default"] = "Profiles", Alpha = { Nested = { Deeper = { 1, 2 } } }, ["Id"] = 2 }"#,
+    )
+    .unwrap();
+    assert_eq!(a, parsed);
+    assert_eq!(a.values, x);
+    #[cfg(feature = "composer")]
+    assert_eq!(a.to_str().unwrap(), parsed.to_str().unwrap());
+
+    let empty = agprefs!(s = {});
+    assert_eq!(empty, Agpref::parse("s = {}").unwrap());
+}
+
+#[test]
+pub fn develop_settings() {
+    // All the develop settings of a photo, as written by Lightroom
+    let x = agprefs!(s = { AutoLateralCA = 1,
+        Blacks2012 = -36,
+        BlueHue = -23,
+        BlueSaturation = -26,
+        Brightness = 50,
+        CameraProfile = "Camera Standard",
+        CameraProfileDigest = "B4F43AF8E01166960058A6A8BE6E3D54",
+        Clarity2012 = -24,
+        ColorGradeBlending = 62,
+        ColorGradeGlobalHue = 0,
+        ColorGradeGlobalLum = 0,
+        ColorGradeGlobalSat = 0,
+        ColorGradeHighlightLum = 29,
+        ColorGradeMidtoneHue = 42,
+        ColorGradeMidtoneLum = -17,
+        ColorGradeMidtoneSat = 79,
+        ColorGradeShadowLum = -20,
+        ColorNoiseReduction = 55,
+        ColorNoiseReductionDetail = 42,
+        ColorNoiseReductionSmoothness = 38,
+        Contrast = 25,
+        Contrast2012 = 23,
+        ConvertToGrayscale = false,
+        CurveRefineSaturation = 100,
+        CustomTemperature = 5356,
+        CustomTint = 20.3949,
+        DefringeGreenAmount = 0,
+        DefringeGreenHueHi = 60,
+        DefringeGreenHueLo = 40,
+        DefringePurpleAmount = 0,
+        DefringePurpleHueHi = 70,
+        DefringePurpleHueLo = 30,
+        Dehaze = 36,
+        Exposure = 0,
+        Exposure2012 = 1.05,
+        ExtendedToneCurvePV2012 = { 0, 0, 49, 43, 104, 122, 163, 160, 255, 255 },
+        ExtendedToneCurvePV2012Blue = { 0, 0, 81, 83, 200, 175, 255, 255 },
+        ExtendedToneCurvePV2012Green = { 0, 0, 61, 115, 224, 186, 255, 255 },
+        ExtendedToneCurvePV2012Red = { 0, 0, 70, 76, 150, 161, 255, 255 },
+        GrainAmount = 24,
+        GrainFrequency = 36,
+        GrainSeed = 1182535882,
+        GrainSize = 36,
+        GreenHue = 19,
+        GreenSaturation = 19,
+        HDREditMode = 0,
+        HDRMaxValue = 4,
+        Highlights2012 = -48,
+        HueAdjustmentAqua = 18,
+        HueAdjustmentBlue = -29,
+        HueAdjustmentGreen = -44,
+        HueAdjustmentMagenta = -17,
+        HueAdjustmentOrange = 33,
+        HueAdjustmentPurple = -30,
+        HueAdjustmentRed = 45,
+        HueAdjustmentYellow = -37,
+        LensBlur = {},
+        LensManualDistortionAmount = 0,
+        LensProfileDigest = "F749F8674DF9ED014D6B035DB372E8FD",
+        LensProfileDistortionScale = 100,
+        LensProfileEnable = 1,
+        LensProfileIsEmbedded = true,
+        LensProfileName = "Camera Settings",
+        LensProfileSetup = "LensDefaults",
+        LensProfileVignettingScale = 100,
+        Look = {
+            Amount = 0.88,
+            Group = { ["x-default"] = " Sam Hurd - Signal Flow" },
+            Name = "BitDepth CS",
+            Parameters = {
+                CameraProfile = "Camera Standard",
+                CameraProfileDigest = "E89FA237E9D4EF49C7F2A6F0A40F4BC0",
+                ConvertToGrayscale = false,
+                Exposure2012 = 0,
+                LensBlur = {},
+                ProcessVersion = "15.4",
+                RGBTable = "23E5F7A7B76177CCE79F84FAD06799D4",
+                Version = "16.0",
+            },
+            SupportsMonochrome = false,
+            SupportsOutputReferred = false,
+            UUID = "E500ACC1A46145F4B53739D17CEE178C",
+        },
+        LuminanceAdjustmentAqua = -26,
+        LuminanceAdjustmentBlue = -20,
+        LuminanceAdjustmentGreen = -26,
+        LuminanceAdjustmentMagenta = -20,
+        LuminanceAdjustmentOrange = -16,
+        LuminanceAdjustmentPurple = -20,
+        LuminanceAdjustmentRed = 23,
+        LuminanceAdjustmentYellow = -19,
+        LuminanceNoiseReductionContrast = 0,
+        OverrideLookVignette = false,
+        ParametricDarks = -34,
+        ParametricHighlights = 58,
+        ParametricLights = 35,
+        ParametricShadows = 3,
+        PerspectiveHorizontal = 0,
+        PerspectiveRotate = 0,
+        PerspectiveScale = 100,
+        PerspectiveVertical = 0,
+        PerspectiveX = 0,
+        PerspectiveY = 0,
+        PostCropVignetteAmount = 32,
+        PostCropVignetteFeather = 60,
+        PostCropVignetteMidpoint = 33,
+        PostCropVignetteRoundness = -33,
+        ProcessVersion = "15.4",
+        RedEyeInfo = {},
+        RedHue = -28,
+        RedSaturation = -29,
+        RetouchInfo = {},
+        SDRBlend = 0,
+        SDRBrightness = 0,
+        SDRClarity = 0,
+        SDRContrast = 0,
+        SDRHighlights = 0,
+        SDRShadows = 0,
+        SDRWhites = 0,
+        Saturation = 26,
+        SaturationAdjustmentAqua = 24,
+        SaturationAdjustmentBlue = -34,
+        SaturationAdjustmentGreen = -12,
+        SaturationAdjustmentMagenta = -17,
+        SaturationAdjustmentOrange = -6,
+        SaturationAdjustmentPurple = 37,
+        SaturationAdjustmentRed = -17,
+        SaturationAdjustmentYellow = 19,
+        ShadowTint = 21,
+        Shadows = 5,
+        Shadows2012 = -28,
+        SharpenDetail = 41,
+        SharpenEdgeMasking = 41,
+        SharpenRadius = 1.5,
+        Sharpness = 73,
+        SplitToningBalance = -21,
+        SplitToningHighlightHue = 191,
+        SplitToningHighlightSaturation = 33,
+        SplitToningShadowHue = 34,
+        SplitToningShadowSaturation = 22,
+        Temperature = 5356,
+        Texture = 43,
+        Tint = 20,
+        ToneCurveName2012 = "Custom",
+        ToneCurvePV2012 = { 0, 0, 49, 43, 104, 122, 163, 160, 255, 255 },
+        ToneCurvePV2012Blue = { 0, 0, 81, 83, 200, 175, 255, 255 },
+        ToneCurvePV2012Green = { 0, 0, 61, 115, 224, 186, 255, 255 },
+        ToneCurvePV2012Red = { 0, 0, 70, 76, 150, 161, 255, 255 },
+        UprightCenterMode = 0,
+        UprightCenterNormX = 0.5,
+        UprightCenterNormY = 0.5,
+        UprightFocalLength35mm = 35,
+        UprightFocalMode = 0,
+        UprightFourSegmentsCount = 0,
+        UprightPreview = false,
+        UprightTransformCount = 6,
+        UprightVersion = 151388160,
+        Version = "16.0",
+        Vibrance = -45,
+        WhiteBalance = "Custom",
+        Whites2012 = -100 });
+    let input = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/assets/db.agprefs"
+    ));
+    assert_eq!(x.get_struct().unwrap().len(), 144);
+    assert_eq!(x, Agpref::parse(input).unwrap());
+
+    let list = agprefs!({
+        0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19,
+        20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39,
+        40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 51, 52, 53, 54, 55, 56, 57, 58, 59,
+        60, 61, 62, 63, 64, 65, 66, 67, 68, 69, 70, 71, 72, 73, 74, 75, 76, 77, 78, 79,
+        80, 81, 82, 83, 84, 85, 86, 87, 88, 89, 90, 91, 92, 93, 94, 95, 96, 97, 98, 99,
+        100, 101, 102, 103, 104, 105, 106, 107, 108, 109, 110, 111, 112, 113, 114, 115, 116, 117, 118, 119,
+        120, 121, 122, 123, 124, 125, 126, 127, 128, 129, 130, 131, 132, 133, 134, 135, 136, 137, 138, 139,
+        140, 141, 142, 143, 144, 145, 146, 147, 148, 149, 150, 151, 152, 153, 154, 155, 156, 157, 158, 159,
+    });
+    assert_eq!(list, Value::from((0..160).collect::<Vec<i64>>()));
+}
@@ -0,0 +1,138 @@
+use agprefs::{merge, merge_with, Agpref, Conflict, Resolution, Strategy, Value};
+
+fn metadata() -> Agpref<'static> {
+    let input = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/assets/metadata"
+    ));
+    Agpref::parse(input).unwrap()
+}
+
+#[test]
+pub fn independent_changes() {
+    let base = metadata();
+    let mut ours = base.clone();
+    ours["Exposure2012"] = Value::Float(0.5);
+    ours["Look"]["Parameters"]["ProcessVersion"] = Value::from("15.0");
+    ours.remove_path("Vibrance");
+    ours.set_path("Look/Parameters/Added", 1).unwrap();
+    let mut theirs = base.clone();
+    theirs["Texture"] = Value::Int(40);
+    theirs["Look"]["Parameters"]["LookTable"] = Value::from("ABC");
+    theirs["Look"]["Parameters"]["ToneCurvePV2012"] = Value::from(vec![0i64, 10, 255, 255]);
+    theirs.remove_path("Shadows");
+    // Both sides making the same change is fine
+    ours["Dehaze"] = Value::Int(0);
+    theirs["Dehaze"] = Value::Int(0);
+
+    let merged = base.merge(&ours, &theirs).unwrap();
+    assert_eq!(merged["Exposure2012"], Value::Float(0.5));
+    assert_eq!(merged["Texture"], Value::Int(40));
+    assert_eq!(merged["Dehaze"], Value::Int(0));
+    assert_eq!(merged.pointer("Vibrance"), None);
+    assert_eq!(merged.pointer("Shadows"), None);
+    let parameters = &merged["Look"]["Parameters"];
+    assert_eq!(parameters["ProcessVersion"], Value::from("15.0"));
+    assert_eq!(parameters["LookTable"], Value::from("ABC"));
+    assert_eq!(parameters["Added"], Value::Int(1));
+    assert_eq!(
+        parameters["ToneCurvePV2012"],
+        Value::from(vec![0i64, 10, 255, 255])
+    );
+    assert_eq!(merged.name, ours.name);
+
+    // Nothing changed on either side
+    assert_eq!(base.merge(&base, &base).unwrap(), base);
+    assert_eq!(base.merge(&ours, &base).unwrap(), ours);
+    assert_eq!(base.merge(&base, &theirs).unwrap(), theirs);
+}
+
+#[test]
+pub fn key_order() {
+    let base = Agpref::parse("s = { B = 1, D = 1 }").unwrap();
+    let ours = Agpref::parse("s = { B = 1, C = 1, D = 1 }").unwrap();
+    let theirs = Agpref::parse("s = { A = 1, B = 1, D = 1, E = 1 }").unwrap();
+    let merged = base.merge(&ours, &theirs).unwrap();
+    assert_eq!(
+        merged
+            .get_struct()
+            .unwrap()
+            .keys()
+            .map(|k| k.as_str())
+            .collect::<Vec<_>>(),
+        ["A", "B", "C", "D", "E"]
+    );
+}
+
+#[test]
+pub fn conflicts() {
+    let base = Agpref::parse(
+        "s = { A = 1, B = { 0, 0, 255, 255 }, C = { X = 1 }, D = 1, E = { }, F = 1 }",
+    )
+    .unwrap();
+    let ours = Agpref::parse(
+        "s = { A = 2, B = { 0, 10, 255, 255 }, C = { X = 2 }, E = { Y = 1 }, F = 1 }",
+    )
+    .unwrap();
+    let theirs = Agpref::parse(
+        "s = { A = 3, B = { 0, 0, 128, 140, 255, 255 }, C = { X = 3 }, D = 2, E = { Y = 2 }, F = 1 }",
+    )
+    .unwrap();
+    let conflicts = merge(&base, &ours, &theirs).unwrap_err();
+    assert_eq!(
+        conflicts
+            .iter()
+            .map(|c| c.path.as_str())
+            .collect::<Vec<_>>(),
+        ["A", "B", "C/X", "D", "E/Y"]
+    );
+    // Lists are merged as a whole
+    assert_eq!(conflicts[1].theirs, Some(theirs["B"].clone()));
+    assert_eq!(
+        conflicts[3],
+        Conflict {
+            path: "D".into(),
+            base: Some(Value::Int(1)),
+            ours: None,
+            theirs: Some(Value::Int(2)),
+        }
+    );
+    assert_eq!(
+        conflicts[3].to_string(),
+        "D: base 1, ours missing, theirs 2"
+    );
+    assert_eq!(
+        conflicts[4].to_string(),
+        "E/Y: base missing, ours 1, theirs 2"
+    );
+
+    let merged = merge_with(&base, &ours, &theirs, Strategy::Ours).unwrap();
+    assert_eq!(merged, ours.values);
+    let merged = merge_with(&base, &ours, &theirs, Strategy::Theirs).unwrap();
+    assert_eq!(merged, theirs.values);
+
+    // A custom resolver picking the larger number and reporting everything else
+    let mut seen = Vec::new();
+    let conflicts = merge_with(&base, &ours, &theirs, |c: &Conflict| {
+        seen.push(c.path.clone());
+        match (&c.ours, &c.theirs) {
+            (Some(Value::Int(o)), Some(Value::Int(t))) => Resolution::Value(Value::Int(*o.max(t))),
+            (None, _) => Resolution::Remove,
+            _ => Resolution::Unresolved,
+        }
+    })
+    .unwrap_err();
+    assert_eq!(seen, ["A", "B", "C/X", "D", "E/Y"]);
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].path, "B");
+
+    let merged = merge_with(&base, &ours, &theirs, |c: &Conflict| {
+        match c.path.as_str() {
+            "B" => Resolution::Base,
+            _ => Resolution::Theirs,
+        }
+    })
+    .unwrap();
+    assert_eq!(merged["B"], base["B"]);
+    assert_eq!(merged["C"]["X"], Value::Int(3));
+}
@@ -0,0 +1,119 @@
+#![cfg(feature = "namedlist")]
+use agprefs::{Agpref, NamedList, ParseOptions, Value};
+
+fn parse(input: &str) -> Agpref<'_> {
+    Agpref::parse_with(input, ParseOptions::new().decode_namedlists(true)).unwrap()
+}
+
+fn recents(agpref: &Agpref) -> NamedList<'static> {
+    agpref.get_struct().unwrap()["recentLibraries20"]
+        .get_namedlist()
+        .unwrap()
+        .clone()
+        .into_static()
+}
+
+#[test]
+pub fn windows() {
+    let input = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/assets/windows.agprefs"
+    ));
+    let x = parse(input);
+    let recents = recents(&x);
+    assert_eq!(recents.name, "recentLibraries");
+    assert_eq!(
+        recents.values,
+        [Value::from(
+            "C:\\Users\\harsh\\Pictures\\Lightroom\\Lightroom Catalog.lrcat"
+        )]
+    );
+    let missing = x.get_struct().unwrap()["recentLibraries20_missing"]
+        .get_namedlist()
+        .unwrap();
+    assert_eq!(missing.name, "recentMissingLibraries");
+    assert!(missing.is_empty());
+    // Strings which aren't lists stay strings
+    assert!(x.get_struct().unwrap()["recentLibraryBehavior20"]
+        .get_string()
+        .is_some());
+    // Only decoded when asked for, decoding embedded agprefs takes precedence
+    let plain = Agpref::parse(input).unwrap();
+    assert!(plain.get_struct().unwrap()["recentLibraries20"]
+        .get_string()
+        .is_some());
+    let options = ParseOptions::new()
+        .decode_namedlists(true)
+        .decode_embedded(true);
+    let embedded = Agpref::parse_with(input, options).unwrap();
+    assert!(embedded.get_struct().unwrap()["recentLibraries20"]
+        .get_embedded()
+        .is_some());
+
+    #[cfg(feature = "composer")]
+    {
+        use agprefs::{ComposeOptions, Newline};
+        let options = ComposeOptions::preferences().newline(Newline::CrLf);
+        assert_eq!(x.to_str_with(&options).unwrap(), input);
+    }
+}
+
+#[test]
+pub fn nikhil() {
+    let input = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/assets/nikhil.agprefs"
+    ));
+    let mut x = parse(input);
+    let recents = recents(&x);
+    assert_eq!(recents.len(), 11);
+    assert_eq!(
+        recents[0].get_string(),
+        Some("/Users/nikhil/Downloads/Catalogs/C2/C5/C5.lrcat")
+    );
+
+    #[cfg(feature = "composer")]
+    {
+        use agprefs::ComposeOptions;
+        assert_eq!(
+            x.to_str_with(&ComposeOptions::preferences()).unwrap(),
+            input
+        );
+
+        let list = x.get_mut_struct().unwrap()["recentLibraries20"]
+            .get_mut_namedlist()
+            .unwrap();
+        list.values.truncate(1);
+        list.values
+            .push(Value::from("/Users/nikhil/My \"New\" Catalog.lrcat"));
+        let output = x.to_str_with(&ComposeOptions::preferences()).unwrap();
+        assert!(output.contains(
+            "\trecentLibraries20 = \"recentLibraries = {\\\n\
+             \t\\\"/Users/nikhil/Downloads/Catalogs/C2/C5/C5.lrcat\\\",\\\n\
+             \t\\\"/Users/nikhil/My \\\\\\\"New\\\\\\\" Catalog.lrcat\\\",\\\n\
+             }\\\n\","
+        ));
+        assert_eq!(parse(&output), x);
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+pub fn serde() {
+    let input = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/assets/nikhil.agprefs"
+    ));
+    let x = parse(input);
+    let json = serde_json::to_string(&x).unwrap();
+    assert!(
+        json.contains("{\"$namedlist\":{\"name\":\"recentLibraries\",\"values\":[\"/Users/nikhil")
+    );
+    let y: Agpref = serde_json::from_str(&json).unwrap();
+    assert_eq!(x, y);
+
+    // Structs with the fields of a named list stay structs
+    let json = r#"{ "name": "recentLibraries", "values": ["/a.lrcat"] }"#;
+    let value: Value = serde_json::from_str(json).unwrap();
+    assert!(value.get_struct().is_some());
+}
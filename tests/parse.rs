@@ -27,15 +27,17 @@ pub fn nikhil() {
     Agpref::parse(input).unwrap();
 }
 
+#[cfg(feature = "composer")]
 #[test]
 pub fn windows() {
+    use agprefs::{ComposeOptions, Newline};
     let input = include_str!(concat!(
         env!("CARGO_MANIFEST_DIR"),
         "/tests/assets/windows.agprefs"
     ));
     let x = Agpref::parse(input).unwrap();
-    let output = Agpref::to_str(&x).unwrap();
-    std::fs::write("x.agprefs", &output).unwrap();
+    let options = ComposeOptions::preferences().newline(Newline::CrLf);
+    let output = Agpref::to_str_with(&x, &options).unwrap();
     assert_eq!(&input, &output);
 
     let recents = x
@@ -60,6 +62,7 @@ pub fn windows() {
     )
 }
 
+#[cfg(feature = "composer")]
 #[test]
 pub fn db() {
     let input = include_str!(concat!(
@@ -71,6 +74,7 @@ pub fn db() {
     assert_eq!(input, output);
 }
 
+#[cfg(feature = "composer")]
 #[test]
 pub fn metadata() {
     let input = include_str!(concat!(
@@ -87,6 +91,28 @@ pub fn fail() {
     Agpref::parse(input).unwrap_err();
 }
 
+#[test]
+pub fn fail_position() {
+    use agprefs::{Errors, Expected};
+    let input = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/assets/failure"));
+    let Errors::Syntax(e) = Agpref::parse(input).unwrap_err() else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!((e.line, e.column), (4, 1));
+    assert_eq!(&input[e.offset..e.offset + 13], "CameraProfile");
+    assert_eq!(e.expected, [Expected::Token(","), Expected::Token("}")]);
+    assert_eq!(e.key_path(), "s");
+    assert_eq!(e.snippet, "CameraProfile = \"Adobe Standard\",\n^");
+
+    let input = "s = { Look = { Parameters = { Exposure2012 = , } } }";
+    let Errors::Syntax(e) = Agpref::parse(input).unwrap_err() else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(e.column, 46);
+    assert_eq!(e.expected, [Expected::Value]);
+    assert_eq!(e.key_path(), "s.Look.Parameters.Exposure2012");
+}
+
 #[cfg(feature = "serde")]
 #[test]
 pub fn serialize_metadata() {
@@ -99,3 +125,256 @@ pub fn serialize_metadata() {
     let agpref2 = serde_json::from_str(&out).unwrap();
     assert_eq!(agpref, agpref2);
 }
+
+#[test]
+pub fn bracketed_keys() {
+    let input = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/assets/metadata"
+    ));
+    let x = Agpref::parse(input).unwrap();
+    let group = x
+        .get_struct()
+        .unwrap()
+        .get("Look")
+        .unwrap()
+        .get_struct()
+        .unwrap()
+        .get("Group")
+        .unwrap()
+        .get_struct()
+        .unwrap();
+    let (key, value) = group.get_key_value("x-default").unwrap();
+    assert!(key.is_quoted());
+    assert_eq!(value.get_string(), Some("Profiles"));
+
+    let input = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/assets/1.agprefs"
+    ));
+    let x = Agpref::parse(input).unwrap();
+    let pickle = x
+        .get_struct()
+        .unwrap()
+        .get("Adobe_successfulUpgrades1100000")
+        .unwrap()
+        .get_string()
+        .unwrap();
+    let pickle = Agpref::parse(pickle).unwrap();
+    let upgrade = pickle
+        .get_struct()
+        .unwrap()
+        .get("/Users/aayushmanchoudhary/work/fivek/fivek/fivek_dataset/raw_photos/fivek.lrcat")
+        .unwrap();
+    assert_eq!(
+        upgrade.get_struct().unwrap().get("catalogType").unwrap(),
+        &agprefs::Value::from("lr")
+    );
+}
+
+#[cfg(feature = "composer")]
+#[test]
+pub fn compose_bracketed_keys() {
+    use agprefs::{Key, Value};
+    let mut group = indexmap::IndexMap::new();
+    group.insert(Key::from("x-default"), Value::from("Profiles"));
+    group.insert(Key::Quoted("Name".into()), Value::from("Adobe \"Color\""));
+    group.insert(Key::from("Amount"), Value::from(1));
+    let mut agpref = Agpref::with_name("s");
+    agpref.values = Value::Struct(group);
+    let output = agpref.to_str().unwrap();
+    assert_eq!(
+        output,
+        "s = { [\"x-default\"] = \"Profiles\",\n[\"Name\"] = \"Adobe \\\"Color\\\"\",\nAmount = 1 }\n"
+    );
+    assert_eq!(Agpref::parse(&output).unwrap(), agpref);
+}
+
+#[test]
+pub fn strict() {
+    use agprefs::{Errors, Expected, ParseOptions};
+    let strict = ParseOptions::new().strict(true);
+    Agpref::parse("s = {} garbage").unwrap();
+    let Errors::Syntax(e) = Agpref::parse_with("s = {} garbage", strict).unwrap_err() else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(e.offset, 7);
+    assert_eq!(e.expected, [Expected::EndOfInput]);
+    Agpref::parse_with("s = { a = 1 }\n\n", strict).unwrap();
+}
+
+#[test]
+pub fn document() {
+    use agprefs::{Document, ParseOptions, Value};
+    let input = "s = { a = 1 }\nt = { \"x\", \"y\" }\nversion = 3\n";
+    let document = Document::parse(input).unwrap();
+    assert_eq!(document.len(), 3);
+    assert_eq!(
+        document.iter().map(|e| &*e.name).collect::<Vec<_>>(),
+        ["s", "t", "version"]
+    );
+    assert_eq!(document.find("version").unwrap().values, Value::Int(3));
+    assert!(document.find("u").is_none());
+
+    Document::parse("s = {} garbage").unwrap();
+    Document::parse_with("s = {} garbage", ParseOptions::new().strict(true)).unwrap_err();
+    Document::parse("garbage").unwrap_err();
+    assert!(Document::parse("  \n").unwrap().is_empty());
+
+    #[cfg(feature = "composer")]
+    {
+        let output = document.to_str().unwrap();
+        assert_eq!(Document::parse(&output).unwrap(), document);
+    }
+}
+
+#[test]
+pub fn numbers() {
+    use agprefs::{ParseOptions, Value};
+    let x = Agpref::parse("s = { a = 1, b = 1.0, c = .5, d = 1e-05, e = -0 }").unwrap();
+    let values = x.values.get_struct().unwrap();
+    assert_eq!(values["a"], Value::Int(1));
+    assert_eq!(values["b"], Value::Float(1.0));
+    assert_eq!(values["c"], Value::Float(0.5));
+    assert_eq!(values["d"], Value::Float(1e-5));
+    assert_eq!(values["e"], Value::Int(0));
+    assert!(Agpref::parse("s = { a = inf }").is_err());
+
+    let input = "s = { a = 1,\nb = 1.0,\nc = .5,\nd = 1e-05,\ne = -0 }\n";
+    let x = Agpref::parse_with(input, ParseOptions::new().preserve_numbers(true)).unwrap();
+    let values = x.values.get_struct().unwrap();
+    assert_eq!(values["b"], Value::Float(1.0));
+    assert_ne!(values["b"], Value::Int(1));
+    assert_eq!(values["c"].get_float(), Some(0.5));
+    assert_eq!(values["e"].get_int(), Some(0));
+    #[cfg(feature = "composer")]
+    {
+        assert_eq!(x.to_str().unwrap(), input);
+        // Floats stay floats even without the spelling
+        let x = Agpref::parse(input).unwrap();
+        assert_eq!(
+            x.to_str().unwrap(),
+            "s = { a = 1,\nb = 1.0,\nc = 0.5,\nd = 1e-5,\ne = 0 }\n"
+        );
+    }
+}
+
+#[test]
+pub fn embedded() {
+    use agprefs::{ParseOptions, Value};
+    let input = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/assets/windows.agprefs"
+    ));
+    let options = ParseOptions::new().decode_embedded(true);
+    let x = Agpref::parse_with(input, options).unwrap();
+    let recents = x.values.get_struct().unwrap()["recentLibraries20"]
+        .get_embedded()
+        .unwrap();
+    assert_eq!(recents.name, "recentLibraries");
+    assert_eq!(
+        recents.values,
+        Value::Values(vec![Value::from(
+            "C:\\Users\\harsh\\Pictures\\Lightroom\\Lightroom Catalog.lrcat"
+        )])
+    );
+    // Plain strings stay strings
+    assert_eq!(
+        x.values.get_struct().unwrap()["recentLibraryBehavior20"],
+        Value::from("UseMostRecentLibrary")
+    );
+
+    #[cfg(feature = "composer")]
+    {
+        let mut x = x;
+        let missing = x.values.get_mut_struct().unwrap()["recentLibraries20_missing"]
+            .get_mut_embedded()
+            .unwrap();
+        missing.values = Value::Values(vec![Value::from("D:\\Old \"Catalog\".lrcat")]);
+        let output = x.to_str().unwrap();
+        let plain = Agpref::parse(&output).unwrap();
+        assert_eq!(
+            plain.values.get_struct().unwrap()["recentLibraries20_missing"],
+            Value::from("recentMissingLibraries = {\n\t\"D:\\\\Old \\\"Catalog\\\".lrcat\",\n}\n")
+        );
+        assert_eq!(Agpref::parse_with(&output, options).unwrap(), x);
+    }
+}
+
+#[test]
+pub fn borrowed_strings() {
+    use agprefs::Value;
+    use std::borrow::Cow;
+    let x = Agpref::parse("s = { Profile = \"Adobe Standard\", Title = \"My \\\"Preset\\\"\" }")
+        .unwrap();
+    let s = x.get_struct().unwrap();
+    assert!(matches!(
+        s["Profile"],
+        Value::String(Cow::Borrowed("Adobe Standard"))
+    ));
+    assert!(matches!(&s["Title"], Value::String(Cow::Owned(t)) if t == "My \"Preset\""));
+
+    #[cfg(feature = "serde")]
+    {
+        let json = serde_json::to_string(&x).unwrap();
+        let y: Agpref = serde_json::from_str(&json).unwrap();
+        assert!(matches!(y["Profile"], Value::String(Cow::Borrowed(_))));
+        let z: Value = agprefs::from_str(
+            "s = { Profile = \"Adobe Standard\", Title = \"My \\\"Preset\\\"\" }",
+        )
+        .unwrap();
+        assert!(matches!(z["Profile"], Value::String(Cow::Borrowed(_))));
+        assert_eq!(z, x.values);
+    }
+}
+
+#[test]
+pub fn localized_strings() {
+    use agprefs::Value;
+    let input = "s = { title = ZSTR \"$$$/Presets/Vivid=Vivid\", type = \"Develop\" }";
+    let x = Agpref::parse(input).unwrap();
+    assert_eq!(x["title"], Value::Zstr("$$$/Presets/Vivid=Vivid".into()));
+    assert_eq!(x["title"].as_text(), Some("Vivid"));
+    assert_eq!(x["title"].get_string(), None);
+    assert_eq!(x["type"].as_text(), Some("Develop"));
+    #[cfg(feature = "composer")]
+    {
+        let options = agprefs::ParseOptions::new().detect_style(true);
+        let styled = Agpref::parse_with(input, options).unwrap();
+        assert_eq!(styled.to_str().unwrap(), input);
+    }
+    assert!(Agpref::parse("s = { title = ZSTR 1 }").is_err());
+
+    #[cfg(feature = "serde")]
+    {
+        let title: std::collections::HashMap<String, String> = agprefs::from_str(input).unwrap();
+        assert_eq!(title["title"], "Vivid");
+        // Reading a value from the text keeps the text only, unlike parsing it
+        let value: Value = agprefs::from_str(input).unwrap();
+        assert_eq!(value["title"], Value::from("Vivid"));
+
+        let json = serde_json::to_string(&x.values).unwrap();
+        assert!(json.contains(r#""title":{"$zstr":"$$$/Presets/Vivid=Vivid"}"#));
+        assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), x.values);
+        #[cfg(feature = "composer")]
+        assert_eq!(
+            agprefs::to_string_with("s", &x.values, &agprefs::ComposeOptions::develop_settings())
+                .unwrap(),
+            x.to_str_with(&agprefs::ComposeOptions::develop_settings())
+                .unwrap()
+        );
+    }
+
+    // Wherever a string is expected, a localized string is its text
+    use agprefs::{Schema, StructSchema};
+    assert_eq!(String::try_from(&x["title"]).unwrap(), "Vivid");
+    assert_eq!(<&str>::try_from(&x["title"]).unwrap(), "Vivid");
+    let text: std::borrow::Cow<str> = x["title"].clone().try_into().unwrap();
+    assert_eq!(text, "Vivid");
+    let schema = Schema::from(StructSchema::new().required("title", Schema::String));
+    assert!(schema.validate(&x).is_empty());
+    let schema = Schema::Enum {
+        values: vec![Value::from("Vivid")],
+    };
+    assert!(schema.validate(&x["title"]).is_empty());
+}
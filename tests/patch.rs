@@ -0,0 +1,111 @@
+use agprefs::{Agpref, Errors, Operation, Patch, Value};
+
+fn metadata() -> Agpref<'static> {
+    let input = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/assets/metadata"
+    ));
+    Agpref::parse(input).unwrap()
+}
+
+#[test]
+pub fn operations() {
+    let mut x = metadata();
+    let patch = Patch::new()
+        .replace("Exposure2012", 0.35)
+        .remove("Clarity2012")
+        .add("Look/Parameters/ToneCurvePV2012/-", 128)
+        .add("Look/Parameters/ToneCurvePV2012/0", 1)
+        .copy_to("Vibrance", "Look/Vibrance")
+        .move_to("Texture", "Look/Parameters/Texture")
+        .test("Look/Vibrance", -31);
+    x.apply(&patch).unwrap();
+    assert_eq!(x["Exposure2012"], Value::Float(0.35));
+    assert_eq!(x.pointer("Clarity2012"), None);
+    assert_eq!(x.pointer("Texture"), None);
+    assert_eq!(x["Look"]["Vibrance"], x["Vibrance"]);
+    assert_eq!(x["Look"]["Parameters"]["Texture"], Value::Int(26));
+    let curve = x["Look"]["Parameters"]["ToneCurvePV2012"]
+        .get_values()
+        .unwrap();
+    assert_eq!(curve.first(), Some(&Value::Int(1)));
+    assert_eq!(curve.last(), Some(&Value::Int(128)));
+}
+
+#[test]
+pub fn atomic() {
+    let original = metadata();
+    let mut x = original.clone();
+    let patch = Patch::new()
+        .replace("Exposure2012", 0.35)
+        .test("Look/Parameters/ProcessVersion", "15.0");
+    match x.apply(&patch) {
+        Err(Errors::TestFailed(path)) => assert_eq!(path, "Look/Parameters/ProcessVersion"),
+        r => panic!("Unexpected {:?}", r),
+    }
+    assert_eq!(x, original);
+
+    for patch in [
+        Patch::new().remove("Missing"),
+        Patch::new().replace("Look/Missing", 1),
+        Patch::new().add("Missing/Key", 1),
+        Patch::new().move_to("Look", "Look/Parameters/Look"),
+    ] {
+        assert!(x.apply(&patch).is_err(), "{:?}", patch);
+        assert_eq!(x, original);
+    }
+}
+
+#[test]
+pub fn diff() {
+    let a = metadata();
+    let mut b = a.clone();
+    b["Exposure2012"] = Value::Float(0.93);
+    b["Texture"] = Value::from("high");
+    b["Look"]["Parameters"]["ToneCurvePV2012"] = Value::from(vec![0i64, 10, 255]);
+    b.remove_path("Vibrance");
+    b.set_path("Look/Parameters/Enable~1Disable", true).unwrap();
+    assert!(Patch::diff(&a, &a).is_empty());
+
+    let patch = Patch::diff(&a, &b);
+    let mut x = a.clone();
+    x.apply(&patch).unwrap();
+    assert_eq!(x, b);
+    assert!(patch.operations.contains(&Operation::Remove {
+        path: "Vibrance".into()
+    }));
+    assert!(patch.operations.contains(&Operation::Add {
+        path: "Look/Parameters/Enable~1Disable".into(),
+        value: Value::Bool(true)
+    }));
+
+    let mut x = b.clone();
+    x.apply(&Patch::diff(&b, &a)).unwrap();
+    assert_eq!(x, a);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+pub fn serde() {
+    let json = r#"[
+        { "op": "test", "path": "Exposure2012", "value": 1.43 },
+        { "op": "replace", "path": "Exposure2012", "value": 0.35 },
+        { "op": "remove", "path": "Clarity2012" },
+        { "op": "move", "from": "Texture", "path": "Clarity2012" }
+    ]"#;
+    let patch: Patch = serde_json::from_str(json).unwrap();
+    assert_eq!(
+        patch,
+        Patch::new()
+            .test("Exposure2012", 1.43)
+            .replace("Exposure2012", 0.35)
+            .remove("Clarity2012")
+            .move_to("Texture", "Clarity2012")
+    );
+    let mut x = metadata();
+    x.apply(&patch).unwrap();
+    assert_eq!(x["Clarity2012"], Value::Int(26));
+
+    let value: serde_json::Value = serde_json::from_str(json).unwrap();
+    assert_eq!(serde_json::to_value(&patch).unwrap(), value);
+}
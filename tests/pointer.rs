@@ -0,0 +1,123 @@
+use agprefs::{Agpref, ParseOptions, Value};
+
+fn metadata() -> Agpref<'static> {
+    let input = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/assets/metadata"
+    ));
+    Agpref::parse(input).unwrap()
+}
+
+#[test]
+pub fn pointer() {
+    let x = metadata();
+    assert_eq!(
+        x.pointer("Look/Parameters/ToneCurvePV2012/3"),
+        Some(&Value::Int(16))
+    );
+    assert_eq!(x.pointer("/Look/Name"), Some(&Value::from("Adobe Color")));
+    assert_eq!(
+        x.pointer("Look/Group/[\"x-default\"]"),
+        Some(&Value::from("Profiles"))
+    );
+    assert_eq!(
+        x.pointer("Look/Group/x-default"),
+        Some(&Value::from("Profiles"))
+    );
+    assert_eq!(x.pointer(""), Some(&x.values));
+    assert_eq!(x.pointer("Look/Parameters/ToneCurvePV2012/99"), None);
+    assert_eq!(x.pointer("Look/Name/0"), None);
+    assert_eq!(x.pointer("Look/[\"Name"), None);
+
+    assert_eq!(x["Look"]["Name"], Value::from("Adobe Color"));
+    assert_eq!(
+        x["Look"]["Parameters"]["ToneCurvePV2012"][2],
+        Value::Int(22)
+    );
+    assert_eq!(x["Nope"]["Name"], Value::Unit);
+}
+
+#[test]
+pub fn escaped_segments() {
+    let input = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/assets/1.agprefs"
+    ));
+    let x = Agpref::parse_with(input, ParseOptions::new().decode_embedded(true)).unwrap();
+    let catalog = "/Users/aayushmanchoudhary/work/fivek/fivek/fivek_dataset/raw_photos/fivek.lrcat";
+    let expected = Some(Value::from("lr"));
+    let bracketed = format!(
+        "Adobe_successfulUpgrades1100000/[\"{}\"]/catalogType",
+        catalog
+    );
+    assert_eq!(x.pointer(&bracketed).cloned(), expected);
+    let escaped = format!(
+        "Adobe_successfulUpgrades1100000/{}/catalogType",
+        catalog.replace('/', "~1")
+    );
+    assert_eq!(x.pointer(&escaped).cloned(), expected);
+}
+
+#[test]
+pub fn set_and_remove() {
+    let mut x = metadata();
+    assert_eq!(
+        x.set_path("Look/Parameters/ToneCurvePV2012/3", 20).unwrap(),
+        Some(Value::Int(16))
+    );
+    assert_eq!(
+        x["Look"]["Parameters"]["ToneCurvePV2012"][3],
+        Value::Int(20)
+    );
+
+    // Intermediate structs are created
+    assert_eq!(x.set_path("Extra/Nested/[\"1\"]", "one").unwrap(), None);
+    assert_eq!(x["Extra"]["Nested"]["1"], Value::from("one"));
+    assert!(x["Extra"]["Nested"]
+        .get_struct()
+        .unwrap()
+        .keys()
+        .next()
+        .unwrap()
+        .is_quoted());
+
+    // Lists can be appended to but not indexed past their end
+    let len = x["Look"]["Parameters"]["ToneCurvePV2012"]
+        .get_values()
+        .unwrap()
+        .len();
+    let path = format!("Look/Parameters/ToneCurvePV2012/{}", len);
+    assert_eq!(x.set_path(&path, 255).unwrap(), None);
+    assert!(x.set_path("Look/Parameters/ToneCurvePV2012/99", 1).is_err());
+    assert!(x.set_path("Look/Name/Inner", 1).is_err());
+
+    assert_eq!(x.remove_path("Look/Name"), Some(Value::from("Adobe Color")));
+    assert_eq!(x.pointer("Look/Name"), None);
+    assert_eq!(x.remove_path("Look/Name"), None);
+    assert_eq!(x.remove_path(&path), Some(Value::Int(255)));
+    let look = x["Look"].get_struct().unwrap();
+    assert_eq!(
+        look.keys().take(3).map(|k| k.as_str()).collect::<Vec<_>>(),
+        ["Amount", "Group", "Parameters"]
+    );
+
+    x["Look"]["Amount"] = Value::Float(0.5);
+    x["New"]["Key"] = Value::Bool(true);
+    x["Look"]["Parameters"]["ToneCurvePV2012"][0] = Value::Int(1);
+    assert_eq!(x.pointer("Look/Amount"), Some(&Value::Float(0.5)));
+    assert_eq!(x.pointer("New/Key"), Some(&Value::Bool(true)));
+    assert_eq!(
+        x.pointer("Look/Parameters/ToneCurvePV2012/0"),
+        Some(&Value::Int(1))
+    );
+}
+
+#[test]
+pub fn empty_tables() {
+    let mut x = Agpref::parse("s = { Look = { } }").unwrap();
+    x.set_path("Look/Name", "Adobe Color").unwrap();
+    assert_eq!(x["Look"]["Name"], Value::from("Adobe Color"));
+    let mut x = Agpref::parse("s = { List = { } }").unwrap();
+    x.set_path("List/0", 1).unwrap();
+    assert_eq!(x["List"], Value::Values(vec![Value::Int(1)]));
+}
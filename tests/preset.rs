@@ -0,0 +1,105 @@
+use agprefs::{DevelopPreset, Errors, ToneCurve, Value};
+
+const WARM: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/assets/warm.lrtemplate"
+));
+const BW: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/assets/bw.lrtemplate"
+));
+
+#[test]
+pub fn read() {
+    let preset = DevelopPreset::parse(WARM).unwrap();
+    assert_eq!(preset.id(), Some("4C5A35F4-5B7E-4B3A-9F3E-2C6D0F8C1E2A"));
+    assert_eq!(preset.uuid(), Some("9D5B2C31-7B4A-4F0E-8A6C-1F2E3D4C5B6A"));
+    assert_eq!(preset.internal_name(), Some("Warm Matte"));
+    assert_eq!(preset.title(), Some("Warm Matte"));
+    assert_eq!(preset.title_key(), None);
+    assert_eq!(preset.kind(), Some("Develop"));
+    assert_eq!(preset.version(), Some(0));
+    assert_eq!(preset.settings()["Vibrance"], Value::Int(8));
+
+    let settings = preset.develop_settings().unwrap();
+    assert_eq!(settings.basic.exposure2012, Some(0.15));
+    assert_eq!(settings.basic.temperature, Some(5600.0));
+    assert_eq!(
+        settings.tone_curve.tone_curve_pv2012,
+        Some(ToneCurve::new(vec![(0, 20), (64, 60), (255, 240)]).unwrap())
+    );
+
+    let preset = DevelopPreset::parse(BW).unwrap();
+    assert_eq!(preset.title(), Some("B&W High Contrast"));
+    assert_eq!(
+        preset.title_key(),
+        Some("$$$/AgCameraRawNamedSettings/SaveNamedDialog/BWHighContrast")
+    );
+    assert_eq!(
+        preset.agpref()["title"],
+        Value::Zstr(
+            "$$$/AgCameraRawNamedSettings/SaveNamedDialog/BWHighContrast=B&W High Contrast".into()
+        )
+    );
+    assert_eq!(
+        preset.develop_settings().unwrap().basic.exposure2012,
+        Some(0.2)
+    );
+
+    let error = DevelopPreset::parse("s = { title = \"x\", value = { uuid = \"A\" } }");
+    assert!(matches!(error, Err(Errors::NotFound(path)) if path == "value/settings"));
+}
+
+#[cfg(feature = "composer")]
+#[test]
+pub fn write() {
+    use agprefs::Agpref;
+    for input in [WARM, BW] {
+        let preset = DevelopPreset::parse(input).unwrap();
+        assert_eq!(preset.to_str().unwrap(), input);
+        let mut buf = Vec::new();
+        preset.write(&mut buf).unwrap();
+        assert_eq!(buf, input.as_bytes());
+    }
+
+    // Untouched settings keep their spelling when others change
+    let mut preset = DevelopPreset::parse(BW).unwrap();
+    let mut settings = preset.develop_settings().unwrap();
+    settings.basic.contrast2012 = Some(60);
+    preset.set_develop_settings(&settings);
+    let output = preset.to_str().unwrap();
+    assert_eq!(output, BW.replace("Contrast2012 = 50", "Contrast2012 = 60"));
+    assert!(output.contains(
+        "\ttitle = ZSTR \"$$$/AgCameraRawNamedSettings/SaveNamedDialog/BWHighContrast=B&W High Contrast\",\n"
+    ));
+
+    // Whole floats are typed settings which can be edited
+    let input = BW.replace("Exposure2012 = 0.20", "Exposure2012 = 1.0");
+    let mut preset = DevelopPreset::parse(&input).unwrap();
+    let mut settings = preset.develop_settings().unwrap();
+    assert_eq!(settings.basic.exposure2012, Some(1.0));
+    preset.set_develop_settings(&settings);
+    assert_eq!(preset.to_str().unwrap(), input);
+    settings.basic.exposure2012 = Some(0.5);
+    preset.set_develop_settings(&settings);
+    assert_eq!(
+        preset.to_str().unwrap(),
+        BW.replace("Exposure2012 = 0.20", "Exposure2012 = 0.5")
+    );
+
+    let mut preset = DevelopPreset::new("C0FFEE", "Faded");
+    preset
+        .settings_mut()
+        .get_mut_struct()
+        .unwrap()
+        .insert("Exposure2012".into(), Value::Float(-0.25));
+    assert_eq!(
+        preset.to_str().unwrap(),
+        "s = {\n\tid = \"C0FFEE\",\n\tinternalName = \"Faded\",\n\ttitle = \"Faded\",\n\
+         \ttype = \"Develop\",\n\tvalue = {\n\t\tsettings = {\n\t\t\tExposure2012 = -0.25,\n\
+         \t\t},\n\t\tuuid = \"C0FFEE\",\n\t},\n\tversion = 0,\n}\n"
+    );
+    let output = preset.to_str().unwrap();
+    let reparsed = DevelopPreset::parse(&output).unwrap();
+    assert_eq!(reparsed.agpref().values, Agpref::from(preset).values);
+}
@@ -0,0 +1,148 @@
+use agprefs::{agprefs, Agpref, Problem, Schema, StructSchema, Value, Violation};
+
+#[test]
+pub fn develop() {
+    let schema = Schema::develop();
+    for input in [
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/assets/db.agprefs"
+        )),
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/assets/metadata"
+        )),
+    ] {
+        let x = Agpref::parse(input).unwrap();
+        assert_eq!(schema.validate(&x), []);
+    }
+
+    let x = agprefs!(s = {
+        Exposure2012 = 5.5,
+        Temperature = 1500,
+        HueAdjustmentRed = -101,
+        ProcessVersion = "9.9",
+        LensProfileSetup = "Auto",
+        Look = { Amount = 1, Parameters = { Vibrance = "high" } },
+        ToneCurvePV2012 = { 0, 0, 255, 256 },
+        Unknown = { 1, 2 },
+    });
+    let violations = schema.validate(&x);
+    let report = violations.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        report,
+        [
+            "Exposure2012: 5.5 is out of range [-5, 5]",
+            "Temperature: 1500 is out of range [2000, 50000]",
+            "HueAdjustmentRed: -101 is out of range [-100, 100]",
+            "ProcessVersion: string \"9.9\" is not one of \"5.0\", \"5.7\", \"6.7\", \"10.0\", \"11.0\", \"15.4\"",
+            "Look/Name: missing",
+            "Look/Parameters/Vibrance: expected integer, found string \"high\"",
+            "ToneCurvePV2012/3: 256 is out of range [0, 255]",
+        ]
+    );
+    // Paths can be looked up with pointer
+    assert_eq!(x.pointer(&violations[5].path), Some(&Value::from("high")));
+}
+
+#[test]
+pub fn custom() {
+    let schema: Schema = StructSchema::new()
+        .required("Name", Schema::String)
+        .field("Amount", Schema::number(0.0, 2.0))
+        .field(
+            "Curve",
+            Schema::List {
+                items: Box::new(Schema::Integer {
+                    min: Some(0),
+                    max: None,
+                }),
+                min_len: Some(2),
+                max_len: Some(4),
+            },
+        )
+        .field("Kind", Schema::one_of([1i64, 2]))
+        .field("Flag", Schema::Bool)
+        .closed(true)
+        .into();
+    assert!(schema.is_valid(&agprefs!({ Name = "x", Curve = { 0, 1 }, Kind = 2 })));
+    // Empty tables are empty structs and lists
+    assert_eq!(
+        schema.validate(&agprefs!({ Name = "x", Curve = {} })),
+        [Violation {
+            path: "Curve".into(),
+            problem: Problem::Length {
+                len: 0,
+                min: Some(2),
+                max: Some(4)
+            }
+        }]
+    );
+
+    let violations = schema.validate(&agprefs!({
+        Amount = "1",
+        Curve = { -1, 0, 1, 2, 3 },
+        Kind = 1.0,
+        Flag = 1,
+        Extra = true,
+    }));
+    let report = violations.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        report,
+        [
+            "Name: missing",
+            "Amount: expected number, found string \"1\"",
+            "Curve: 5 elements are not in [2, 4]",
+            "Curve/0: -1 is out of range [0, ..]",
+            "Kind: float 1 is not one of 1, 2",
+            "Flag: expected bool, found integer 1",
+            "Extra: unexpected key",
+        ]
+    );
+    assert!(!schema.is_valid(&Value::Int(1)));
+    assert_eq!(
+        schema.validate(&Value::Int(1))[0].to_string(),
+        "/: expected struct, found integer 1"
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+pub fn serde() {
+    let json = serde_json::json!({
+        "type": "struct",
+        "fields": {
+            "Exposure2012": { "type": "number", "min": -5.0, "max": 5.0 },
+            "Look": {
+                "type": "struct",
+                "fields": { "Name": { "type": "string" } },
+                "required": ["Name"],
+                "closed": true
+            },
+            "ToneCurvePV2012": {
+                "type": "list",
+                "items": { "type": "integer", "min": 0, "max": 255 },
+                "min_len": 4
+            },
+            "ProcessVersion": { "type": "enum", "values": ["11.0", "15.4"] },
+            "Extra": { "type": "any" }
+        },
+        "closed": false
+    });
+    let text = json.to_string();
+    let schema: Schema = serde_json::from_str(&text).unwrap();
+    assert_eq!(serde_json::to_value(&schema).unwrap(), json);
+
+    let develop = Schema::develop();
+    let text = serde_json::to_string(&develop).unwrap();
+    assert_eq!(serde_json::from_str::<Schema>(&text).unwrap(), develop);
+
+    let violations = schema.validate(&agprefs!({ Look = { Name = 1, Amount = 1 } }));
+    assert_eq!(
+        serde_json::to_value(&violations).unwrap(),
+        serde_json::json!([
+            { "path": "Look/Name", "problem": "type", "expected": "string", "found": "integer 1" },
+            { "path": "Look/Amount", "problem": "unexpected" },
+        ])
+    );
+}
@@ -0,0 +1,199 @@
+#![cfg(all(feature = "serde", feature = "composer"))]
+use agprefs::{Agpref, Errors, Expected, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Mode {
+    Auto,
+    Manual(f64),
+    Custom { temperature: u32, tint: f64 },
+    Pair(i32, i32),
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Settings {
+    name: String,
+    enabled: bool,
+    amount: Option<f64>,
+    missing: Option<i64>,
+    curve: Vec<u8>,
+    modes: Vec<Mode>,
+    #[serde(rename = "x-default")]
+    quoted: BTreeMap<String, i64>,
+    point: (i32, f32),
+    nothing: (),
+}
+
+#[test]
+pub fn roundtrip() {
+    let settings = Settings {
+        name: "My \"Preset\"\n".into(),
+        enabled: true,
+        amount: Some(0.5),
+        missing: None,
+        curve: vec![0, 0, 255, 255],
+        modes: vec![
+            Mode::Auto,
+            Mode::Manual(-1.0),
+            Mode::Custom {
+                temperature: 6500,
+                tint: 10.5,
+            },
+            Mode::Pair(1, -2),
+        ],
+        quoted: [("a b".to_string(), 1), ("1".to_string(), 2)].into(),
+        point: (3, 1.5),
+        nothing: (),
+    };
+    let text = agprefs::to_string("s", &settings).unwrap();
+    assert!(!text.contains("missing"));
+    assert!(text.contains("[\"a b\"] = 1"));
+    assert!(text.contains("\"Auto\""));
+    // The text is agprefs and reads back to the same value
+    let value = Agpref::parse(&text).unwrap();
+    assert_eq!(value["modes"][1]["Manual"], Value::Float(-1.0));
+    assert_eq!(value.to_str().unwrap(), text);
+    assert_eq!(agprefs::from_str::<Settings>(&text).unwrap(), settings);
+}
+
+#[derive(Debug, Deserialize)]
+struct Look {
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "Amount")]
+    amount: f64,
+    #[serde(rename = "Parameters")]
+    parameters: BTreeMap<String, serde::de::IgnoredAny>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Metadata {
+    #[serde(rename = "Exposure2012")]
+    exposure: f64,
+    #[serde(rename = "ToneCurvePV2012")]
+    curve: Vec<u8>,
+    #[serde(rename = "Look")]
+    look: Look,
+    #[serde(rename = "ProcessVersion")]
+    process: String,
+}
+
+#[test]
+pub fn metadata() {
+    let input = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/assets/metadata"
+    ));
+    let x: Metadata = agprefs::from_str(input).unwrap();
+    let expected = Agpref::parse(input).unwrap();
+    assert_eq!(Value::Float(x.exposure), expected["Exposure2012"]);
+    assert_eq!(
+        Value::Values(x.curve.iter().map(|&i| Value::Int(i.into())).collect()),
+        expected["ToneCurvePV2012"]
+    );
+    assert_eq!(x.look.name, "Adobe Color");
+    assert_eq!(x.look.amount, 1.0);
+    assert!(x.look.parameters.contains_key("ToneCurvePV2012"));
+    assert_eq!(x.process, "11.0");
+
+    // Values can be read directly as well
+    let y: Value = agprefs::from_str(input).unwrap();
+    assert_eq!(y, expected.values);
+}
+
+#[derive(Debug, Deserialize)]
+struct BorrowedLook<'a> {
+    #[serde(rename = "Name")]
+    name: &'a str,
+    #[serde(rename = "Parameters")]
+    parameters: BTreeMap<&'a str, serde::de::IgnoredAny>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BorrowedMetadata<'a> {
+    #[serde(borrow, rename = "Look")]
+    look: BorrowedLook<'a>,
+    #[serde(rename = "ProcessVersion")]
+    process: &'a str,
+}
+
+#[test]
+pub fn metadata_borrowed() {
+    let input = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/assets/metadata"
+    ));
+    // Strings without escapes are borrowed from the input
+    let x: BorrowedMetadata = agprefs::from_str(input).unwrap();
+    assert_eq!(x.look.name, "Adobe Color");
+    assert!(x.look.parameters.contains_key("ToneCurvePV2012"));
+    assert_eq!(x.process, "11.0");
+}
+
+#[test]
+pub fn errors() {
+    let input = "s = { Look = { Curve = { 0, 16, }, Name = oops } }";
+    match agprefs::from_str::<Value>(input) {
+        Err(Errors::Syntax(e)) => {
+            assert_eq!(e.path, ["s", "Look", "Name"]);
+            assert_eq!(e.offset, input.find("oops").unwrap());
+            assert_eq!(e.expected, [Expected::Value]);
+        }
+        x => panic!("Unexpected {:?}", x),
+    }
+    let input = "s = { 1, 2 } x";
+    match agprefs::from_str::<Vec<i32>>(input) {
+        Err(Errors::Syntax(e)) => assert_eq!(e.offset, input.len() - 1),
+        x => panic!("Unexpected {:?}", x),
+    }
+    assert!(agprefs::from_str::<Vec<i32>>("s = { 1, \"2\" }").is_err());
+    assert!(agprefs::to_string("s", &BTreeMap::from([(1.5f64.to_bits(), 1)])).is_ok());
+    assert!(agprefs::to_string("s", &BTreeMap::from([((1, 2), 1)])).is_err());
+}
+
+#[test]
+pub fn keys_and_missing_values() {
+    // Integer and bool keys are written as strings and parsed back
+    let numbered = BTreeMap::from([(1u32, "a".to_string()), (20, "b".into())]);
+    let text = agprefs::to_string("s", &numbered).unwrap();
+    assert!(text.contains("[\"20\"] = \"b\""));
+    assert_eq!(
+        agprefs::from_str::<BTreeMap<u32, String>>(&text).unwrap(),
+        numbered
+    );
+    let signed = BTreeMap::from([(-1i64, 1.5), (7, 0.0)]);
+    let text = agprefs::to_string("s", &signed).unwrap();
+    assert_eq!(
+        agprefs::from_str::<BTreeMap<i64, f64>>(&text).unwrap(),
+        signed
+    );
+    let flags = BTreeMap::from([(false, 0), (true, 1)]);
+    let text = agprefs::to_string("s", &flags).unwrap();
+    assert_eq!(
+        agprefs::from_str::<BTreeMap<bool, i32>>(&text).unwrap(),
+        flags
+    );
+    assert!(agprefs::from_str::<BTreeMap<u32, i32>>("s = { a = 1 }").is_err());
+
+    // `None` is left out of maps and structs, but has no spelling anywhere else
+    let sparse = BTreeMap::from([("a".to_string(), Some(1)), ("b".into(), None)]);
+    let text = agprefs::to_string("s", &sparse).unwrap();
+    let read: BTreeMap<String, Option<i32>> = agprefs::from_str(&text).unwrap();
+    assert_eq!(read, BTreeMap::from([("a".to_string(), Some(1))]));
+    let some = vec![Some(1), Some(2)];
+    let text = agprefs::to_string("s", &some).unwrap();
+    assert_eq!(agprefs::from_str::<Vec<Option<i32>>>(&text).unwrap(), some);
+    assert!(agprefs::to_string("s", &vec![Some(1), None]).is_err());
+    assert!(agprefs::to_string("s", &(1, None::<i32>)).is_err());
+    assert!(agprefs::to_string("s", &None::<i32>).is_err());
+
+    // Neither are NaN and infinite floats
+    for float in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        assert!(agprefs::to_string("s", &vec![float]).is_err());
+        assert!(agprefs::to_string("s", &vec![float as f32]).is_err());
+    }
+    let finite = vec![f64::MAX, f64::MIN_POSITIVE, -0.0];
+    let text = agprefs::to_string("s", &finite).unwrap();
+    assert_eq!(agprefs::from_str::<Vec<f64>>(&text).unwrap(), finite);
+}
@@ -0,0 +1,80 @@
+use agprefs::{Agpref, ToneCurve, Value};
+
+#[test]
+pub fn db() {
+    let input = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/assets/db.agprefs"
+    ));
+    let x = Agpref::parse(input).unwrap();
+    let curve = ToneCurve::try_from(&x["ExtendedToneCurvePV2012"]).unwrap();
+    assert_eq!(
+        curve.points(),
+        [(0, 0), (49, 43), (104, 122), (163, 160), (255, 255)]
+    );
+    assert!(!curve.is_linear());
+    assert_eq!(Value::from(&curve), x["ExtendedToneCurvePV2012"]);
+    assert_eq!(ToneCurve::try_from(&x["ToneCurvePV2012"]).unwrap(), curve);
+
+    // The curve passes through its points and stays smooth between them
+    let lut = curve.lut8();
+    for &(px, py) in curve.points() {
+        assert_eq!(lut[px as usize], py);
+    }
+    assert!(lut.windows(2).all(|w| w[0] <= w[1]));
+    let lut16 = curve.lut16();
+    assert_eq!(lut16.len(), 65536);
+    assert_eq!((lut16[0], lut16[65535]), (0, 65535));
+    assert_eq!(lut16[49 * 257], 43 * 257);
+}
+
+#[test]
+pub fn spline() {
+    assert!(ToneCurve::linear()
+        .lut8()
+        .iter()
+        .enumerate()
+        .all(|(i, &y)| i == y as usize));
+    assert!(ToneCurve::new(vec![(0, 0), (128, 128), (255, 255)])
+        .unwrap()
+        .is_linear());
+
+    // A symmetric S curve darkens the shadows and crosses the middle unchanged
+    let curve = ToneCurve::new(vec![(0, 0), (63, 31), (192, 224), (255, 255)]).unwrap();
+    assert!(curve.evaluate(32.0) < 16.0);
+    assert!((curve.evaluate(127.5) - 127.5).abs() < 1e-9);
+    // Steep curves overshoot between their points and are clamped
+    let curve = ToneCurve::new(vec![(0, 0), (16, 250), (255, 255)]).unwrap();
+    assert!(curve.lut(1024).iter().all(|y| (0.0..=1.0).contains(y)));
+    assert_eq!(curve.evaluate(40.0), 255.0);
+
+    // Inputs before the first and after the last point are flat
+    let curve = ToneCurve::new(vec![(20, 30), (235, 200)]).unwrap();
+    assert_eq!(curve.evaluate(0.0), 30.0);
+    assert_eq!(curve.evaluate(255.0), 200.0);
+    assert!(!curve.is_linear());
+}
+
+#[test]
+pub fn invalid() {
+    let parse = |s| ToneCurve::try_from(&Agpref::parse(s).unwrap().values);
+    assert!(parse("s = { 0, 0, 255 }").is_err());
+    assert!(parse("s = { 0, 0 }").is_err());
+    assert!(parse("s = { 0, 0, 0, 255 }").is_err());
+    assert!(parse("s = { 0, 0, 200, 100, 100, 200 }").is_err());
+    assert!(parse("s = { 0, 0, 256, 255 }").is_err());
+    assert!(parse("s = { 0, -1, 255, 255 }").is_err());
+    assert!(parse("s = { 0, 0, 255, 255.5 }").is_err());
+    assert!(parse("s = { A = 1 }").is_err());
+    assert!(ToneCurve::from_flat(&[0, 0, 128, 64, 255, 255]).is_ok());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+pub fn serde() {
+    let curve = ToneCurve::new(vec![(0, 10), (255, 245)]).unwrap();
+    let json = serde_json::to_string(&curve).unwrap();
+    assert_eq!(json, "[0,10,255,245]");
+    assert_eq!(serde_json::from_str::<ToneCurve>(&json).unwrap(), curve);
+    assert!(serde_json::from_str::<ToneCurve>("[10,0,5,245]").is_err());
+}
@@ -0,0 +1,167 @@
+#![cfg(feature = "xmp")]
+use agprefs::{xmp, Agpref, Errors, Value};
+
+const DB: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/assets/db.agprefs"
+));
+
+#[test]
+pub fn roundtrip() {
+    let x = Agpref::parse(DB).unwrap();
+    let packet = xmp::to_string(&x.values).unwrap();
+    for line in [
+        "   crs:AutoLateralCA=\"1\"\n",
+        "   crs:Blacks2012=\"-36\"\n",
+        "   crs:Brightness=\"+50\"\n",
+        "   crs:CustomTemperature=\"5356\"\n",
+        "   crs:CustomTint=\"+20.3949\"\n",
+        "   crs:Exposure=\"0.00\"\n",
+        "   crs:Exposure2012=\"+1.05\"\n",
+        "   crs:ConvertToGrayscale=\"False\"\n",
+        "   crs:LensProfileIsEmbedded=\"True\"\n",
+        "   crs:ProcessVersion=\"15.4\"",
+        "   <crs:ExtendedToneCurvePV2012Blue>\n    <rdf:Seq>\n     <rdf:li>0, 0</rdf:li>\n     \
+         <rdf:li>81, 83</rdf:li>\n",
+        "   <crs:Look>\n    <rdf:Description\n     crs:Amount=\"0.88\"\n     \
+         crs:Name=\"BitDepth CS\"\n",
+        "     <crs:Group>\n      <rdf:Alt>\n       \
+         <rdf:li xml:lang=\"x-default\"> Sam Hurd - Signal Flow</rdf:li>\n",
+        "     <crs:Parameters>\n      <rdf:Description\n       \
+         crs:CameraProfile=\"Camera Standard\"\n",
+    ] {
+        assert!(packet.contains(line), "{} not in\n{}", line, packet);
+    }
+    assert!(packet.starts_with("<x:xmpmeta xmlns:x=\"adobe:ns:meta/\""));
+    assert!(packet.ends_with(" </rdf:RDF>\n</x:xmpmeta>\n"));
+
+    let y = xmp::from_str(&packet).unwrap();
+    assert_eq!(y, x.values);
+    #[cfg(feature = "composer")]
+    {
+        let y = Agpref {
+            values: y,
+            ..Agpref::with_name("s")
+        };
+        assert_eq!(y.to_str().unwrap(), DB);
+    }
+}
+
+#[test]
+pub fn lightroom() {
+    let sidecar = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/" x:xmptk="Adobe XMP Core 7.0-c000 1.000000, 0000/00/00-00:00:00        ">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:tiff="http://ns.adobe.com/tiff/1.0/"
+    xmlns:crs="http://ns.adobe.com/camera-raw-settings/1.0/"
+   tiff:Make="Canon"
+   crs:Version="14.0"
+   crs:ProcessVersion="11.0"
+   crs:WhiteBalance="As Shot"
+   crs:Exposure2012="+1.00"
+   crs:Contrast2012="-12"
+   crs:Sharpness="40"
+   crs:SharpenRadius="+1.0"
+   crs:ConvertToGrayscale="False"
+   crs:HasSettings="True"
+   crs:CameraProfile="Adobe Standard &amp; more">
+   <crs:ToneCurvePV2012>
+    <rdf:Seq>
+     <rdf:li>0, 0</rdf:li>
+     <rdf:li>255, 255</rdf:li>
+    </rdf:Seq>
+   </crs:ToneCurvePV2012>
+   <crs:Look rdf:parseType="Resource">
+    <crs:Name>Adobe Color</crs:Name>
+    <crs:Amount>1</crs:Amount>
+    <crs:SupportsAmount>false</crs:SupportsAmount>
+   </crs:Look>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>
+"#;
+    let x = xmp::from_str(sidecar).unwrap();
+    let expected = Agpref::parse(
+        r#"s = { CameraProfile = "Adobe Standard & more", Contrast2012 = -12,
+        ConvertToGrayscale = false, Exposure2012 = 1, HasSettings = true,
+        Look = { Amount = 1, Name = "Adobe Color", SupportsAmount = false },
+        ProcessVersion = "11.0", SharpenRadius = 1, Sharpness = 40,
+        ToneCurvePV2012 = { 0, 0, 255, 255 }, Version = "14.0", WhiteBalance = "As Shot" }"#,
+    )
+    .unwrap();
+    assert_eq!(x, expected.values);
+    let keys: Vec<_> = x.get_struct().unwrap().keys().map(|k| k.as_str()).collect();
+    assert_eq!(
+        keys[..3],
+        ["CameraProfile", "Contrast2012", "ConvertToGrayscale"]
+    );
+
+    let packet = xmp::to_string(&x).unwrap();
+    assert!(packet.contains("   crs:CameraProfile=\"Adobe Standard &amp; more\"\n"));
+    assert!(packet.contains("   crs:SharpenRadius=\"+1.0\"\n"));
+    assert!(packet.contains("   crs:Exposure2012=\"+1.00\"\n"));
+    assert!(packet.contains("   crs:Version=\"14.0\"\n"));
+
+    assert!(matches!(xmp::from_str("<x:xmpmeta"), Err(Errors::Xml(_))));
+    assert!(matches!(
+        xmp::from_str("<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"/>"),
+        Err(Errors::NotFound(_))
+    ));
+    // Lists of lists and structs
+    let nested =
+        Agpref::parse(r#"s = { RetouchAreas = { { Feather = 0.5, What = "Spot" }, { 1, "a" } } }"#)
+            .unwrap();
+    let packet = xmp::to_string(&nested.values).unwrap();
+    assert_eq!(xmp::from_str(&packet).unwrap(), nested.values);
+    match xmp::to_string(&Value::Int(1)) {
+        Err(Errors::Conversion(e)) => {
+            assert_eq!(e.to_string(), "expected struct at `/`, found integer 1")
+        }
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+pub fn precision() {
+    let x =
+        Agpref::parse("s = { Exposure2012 = 1.434, SharpenRadius = 0.75, Exposure = -2 }").unwrap();
+    let packet = xmp::to_string(&x.values).unwrap();
+    assert!(packet.contains("   crs:Exposure2012=\"+1.434\"\n"));
+    assert!(packet.contains("   crs:SharpenRadius=\"+0.75\"\n"));
+    assert!(packet.contains("   crs:Exposure=\"-2.00\"/>\n"));
+    assert_eq!(xmp::from_str(&packet).unwrap(), x.values);
+}
+
+#[test]
+pub fn localized() {
+    let x = Agpref::parse(
+        r#"s = { Look = { Group = { ["x-default"] = ZSTR "$$$/Looks/Group=Profiles" },
+        Name = ZSTR "$$$/Looks/Color=Adobe Color" } }"#,
+    )
+    .unwrap();
+    let packet = xmp::to_string(&x.values).unwrap();
+    assert!(packet.contains("     crs:Name=\"Adobe Color\">\n"));
+    assert!(packet.contains("<rdf:li xml:lang=\"x-default\">Profiles</rdf:li>\n"));
+    let expected = Agpref::parse(
+        r#"s = { Look = { Group = { ["x-default"] = "Profiles" }, Name = "Adobe Color" } }"#,
+    )
+    .unwrap();
+    assert_eq!(xmp::from_str(&packet).unwrap(), expected.values);
+}
+
+#[test]
+pub fn unknown_settings() {
+    // Whole floats read back as floats and text keeps its line breaks
+    let mut x = Agpref::parse(
+        "s = { CameraProfile = \"\", Custom = { Amount = 1.0, Steps = 2 }, NewSetting = -3.0, \
+         Curve = { 1.0, 2 } }",
+    )
+    .unwrap();
+    x.values["CameraProfile"] = Value::from("a\nb\r\n\tc");
+    let packet = xmp::to_string(&x.values).unwrap();
+    assert!(packet.contains("   crs:NewSetting=\"-3.0\">\n"));
+    assert!(packet.contains("     crs:Amount=\"1.0\"\n     crs:Steps=\"2\"/>\n"));
+    assert!(packet.contains("   crs:CameraProfile=\"a&#xA;b&#xD;&#xA;&#x9;c\"\n"));
+    assert!(packet.contains("     <rdf:li>1.0</rdf:li>\n"));
+    assert_eq!(xmp::from_str(&packet).unwrap(), x.values);
+}